@@ -1,4 +1,5 @@
 use std::env;
+use std::fmt::Write as _;
 use std::fs;
 use std::path::PathBuf;
 
@@ -14,4 +15,33 @@ fn main() {
 
     // Tell the linker the path.
     println!("cargo:rustc-link-arg=-T{}", target.display());
+
+    embed_programs(&out);
+}
+
+/// Writes the table of `src/programs.rs`: one entry per file of the
+/// `XV6_USER_PROGRAMS` directory, if set.
+fn embed_programs(out: &std::path::Path) {
+    println!("cargo:rerun-if-env-changed=XV6_USER_PROGRAMS");
+
+    let mut programs = Vec::new();
+    if let Some(dir) = env::var_os("XV6_USER_PROGRAMS") {
+        println!("cargo:rerun-if-changed={}", PathBuf::from(&dir).display());
+        for entry in fs::read_dir(&dir).expect("Could not read XV6_USER_PROGRAMS") {
+            let path = entry.expect("Could not read XV6_USER_PROGRAMS").path();
+            if path.is_file() {
+                println!("cargo:rerun-if-changed={}", path.display());
+                programs.push(path);
+            }
+        }
+    }
+    programs.sort();
+
+    let mut table = String::from("&[\n");
+    for path in &programs {
+        let name = path.file_name().unwrap().to_string_lossy();
+        writeln!(table, "    (\"/{name}\", include_bytes!({path:?})),").unwrap();
+    }
+    table.push(']');
+    fs::write(out.join("programs.rs"), table).expect("Could not write programs.rs");
 }
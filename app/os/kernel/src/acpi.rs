@@ -0,0 +1,234 @@
+//! ACPI: processors, interrupt routing and power-off from the firmware tables
+//!
+//! Finds the RSDP and walks the RSDT. The MADT gives the CPUs, the I/O APIC
+//! and the ISA interrupt overrides; the FADT and the `\_S5` object of the
+//! DSDT give how to turn the machine off. The tables themselves are parsed
+//! by the `acpi` crate.
+//!
+//! With the `acpi-dump` feature, the tables read are also printed in hex, for
+//! `cargo xtask acpi-tables` to save as test data of the `acpi` crate.
+
+use core::sync::atomic::{AtomicU16, AtomicU32, Ordering};
+
+use ::acpi::{Fadt, Madt, MadtEntry, Rsdp, Rsdt, SCI_EN, Sdt, SdtHeader, SleepType, find_s5};
+
+use crate::params::NCPU;
+use crate::x86::{inw, outb, outw};
+use crate::{ioapic, pit, proc, vm};
+
+/// Bios Data Area: real mode segment of the EBDA
+const BDA_EBDA: usize = 0x40E;
+/// BIOS read-only memory
+const BIOS_ROM: usize = 0xE0000;
+const BIOS_ROM_END: usize = 0x10_0000;
+
+/// How long to wait for the firmware to switch to ACPI mode.
+const ACPI_ENABLE_TIMEOUT_MS: u32 = 3000;
+
+// How to enter the soft off state (S5). Written once by `init`, and read
+// without a lock: `poweroff` runs on the panic path, where a lock may be
+// held by the CPU that panicked.
+
+/// PM1a control block port in the low half, PM1b (0 if none) in the high
+/// half, or 0 if there is no way to power off. Stored last.
+static PM1_CNT: AtomicU32 = AtomicU32::new(0);
+/// SMI command port (0 if the machine is always in ACPI mode) in the low
+/// half, the value that enables ACPI mode in the high half.
+static SMI_CMD: AtomicU32 = AtomicU32::new(0);
+/// `SLP_TYPa` in the low byte, `SLP_TYPb` in the high byte.
+static SLP_TYP: AtomicU16 = AtomicU16::new(0);
+
+/// Search for the RSDP, which according to the spec is in one of the
+/// following two locations:
+/// 1) in the first KB of the EBDA;
+/// 2) in the BIOS ROM between 0xE0000 and 0xFFFFF.
+///
+/// Returns it along with its bytes.
+fn find_rsdp() -> Option<(Rsdp, &'static [u8])> {
+    let bda = vm::firmware_bytes(BDA_EBDA, 2)?;
+    let ebda = usize::from(u16::from_le_bytes([bda[0], bda[1]])) << 4;
+    let ebda = (ebda != 0)
+        .then(|| vm::firmware_bytes(ebda, 1024))
+        .flatten();
+    let rom = vm::firmware_bytes(BIOS_ROM, BIOS_ROM_END - BIOS_ROM);
+    ebda.into_iter()
+        .chain(rom)
+        .find_map(|region| {
+            let (offset, rsdp) = Rsdp::search(region)?;
+            Some((rsdp, &region[offset..offset + rsdp.size()]))
+        })
+}
+
+/// The whole table at physical address `pa`, unchecked.
+fn table(pa: u32) -> Option<&'static [u8]> {
+    let header = vm::firmware_bytes(pa as usize, SdtHeader::SIZE)?;
+    let header = SdtHeader::parse(header).ok()?;
+    vm::firmware_bytes(pa as usize, header.size())
+}
+
+/// Prints `bytes`, the table `name`, as one line of hex.
+#[cfg(feature = "acpi-dump")]
+fn dump(name: &[u8], bytes: &[u8]) {
+    print!("acpi-dump {} ", core::str::from_utf8(name).unwrap_or("????"));
+    for byte in bytes {
+        print!("{byte:02x}");
+    }
+    println!();
+}
+
+/// Reads the ACPI tables. If there is a MADT, fills the CPU table and
+/// returns the physical address of the local APICs.
+pub fn init() -> Option<usize> {
+    let Some((rsdp, _rsdp_bytes)) = find_rsdp() else {
+        debug!("acpi: no RSDP");
+        return None;
+    };
+    #[cfg(feature = "acpi-dump")]
+    {
+        dump(b"RSDP", _rsdp_bytes);
+        if let Some(rsdt) = table(rsdp.rsdt_address) {
+            dump(b"RSDT", rsdt);
+        }
+    }
+    let rsdt = match table(rsdp.rsdt_address).map(Rsdt::parse) {
+        Some(Ok(rsdt)) => rsdt,
+        Some(Err(err)) => {
+            warn!("acpi: RSDT: {err}");
+            return None;
+        }
+        None => {
+            warn!("acpi: RSDT at {:#x} is out of reach", rsdp.rsdt_address);
+            return None;
+        }
+    };
+
+    let mut lapic = None;
+    for bytes in rsdt.entries().filter_map(table) {
+        #[cfg(feature = "acpi-dump")]
+        dump(&bytes[..4], bytes);
+        if bytes.starts_with(&Madt::SIGNATURE) {
+            lapic = madt(bytes);
+        } else if bytes.starts_with(&Fadt::SIGNATURE) {
+            fadt(bytes);
+        }
+    }
+    #[cfg(feature = "acpi-dump")]
+    println!("acpi-dump end");
+    lapic
+}
+
+/// Takes the CPUs and interrupt routing from the MADT.
+fn madt(bytes: &[u8]) -> Option<usize> {
+    let madt = Madt::parse(bytes)
+        .inspect_err(|err| warn!("acpi: MADT: {err}"))
+        .ok()?;
+
+    let mut apicids = [0u8; NCPU];
+    let mut ncpu = 0;
+    for entry in madt.entries() {
+        match entry {
+            MadtEntry::LocalApic(lapic) if lapic.is_enabled() => {
+                if ncpu < NCPU {
+                    apicids[ncpu] = lapic.apic_id;
+                    ncpu += 1;
+                } else {
+                    warn!("acpi: ignoring cpu with apicid {}", lapic.apic_id);
+                }
+            }
+            MadtEntry::IoApic(ioapic) => ioapic::set_id(ioapic.id),
+            MadtEntry::InterruptOverride(iso) if iso.bus == 0 => {
+                debug!("acpi: irq {} -> pin {}", iso.source, iso.gsi);
+                ioapic::set_override(
+                    iso.source,
+                    iso.gsi,
+                    iso.is_level_triggered(),
+                    iso.is_active_low(),
+                );
+            }
+            _ => {}
+        }
+    }
+    if ncpu == 0 {
+        warn!("acpi: no enabled processors in the MADT");
+        return None;
+    }
+
+    // SAFETY: the other CPUs have not been started.
+    unsafe { proc::set_cpus(&apicids[..ncpu]) };
+    info!("acpi: {ncpu} cpus, lapic at {:#x}", madt.local_apic_address);
+    Some(madt.local_apic_address as usize)
+}
+
+/// Takes the PM1 control blocks from the FADT, and the sleep type of S5
+/// from the DSDT.
+fn fadt(bytes: &[u8]) {
+    let fadt = match Fadt::parse(bytes) {
+        Ok(fadt) => fadt,
+        Err(err) => {
+            warn!("acpi: FADT: {err}");
+            return;
+        }
+    };
+    #[cfg(feature = "acpi-dump")]
+    if let Some(dsdt) = table(fadt.dsdt) {
+        dump(b"DSDT", dsdt);
+    }
+    let dsdt = table(fadt.dsdt).and_then(|dsdt| Sdt::parse_as(dsdt, *b"DSDT").ok());
+    let Some(slp_typ) = dsdt.and_then(|dsdt| find_s5(dsdt.data)) else {
+        warn!("acpi: no \\_S5 in the DSDT; cannot power off");
+        return;
+    };
+    let port = |port: u32| u16::try_from(port).ok();
+    let (Some(smi_cmd), Some(pm1a_cnt), Some(pm1b_cnt)) = (
+        port(fadt.smi_cmd),
+        port(fadt.pm1a_cnt_blk).filter(|&port| port != 0),
+        port(fadt.pm1b_cnt_blk),
+    ) else {
+        warn!("acpi: bad PM1 control block; cannot power off");
+        return;
+    };
+    SMI_CMD.store(
+        u32::from(smi_cmd) | u32::from(fadt.acpi_enable) << 16,
+        Ordering::Relaxed,
+    );
+    SLP_TYP.store(
+        u16::from_le_bytes([slp_typ.a, slp_typ.b]),
+        Ordering::Relaxed,
+    );
+    PM1_CNT.store(
+        u32::from(pm1a_cnt) | u32::from(pm1b_cnt) << 16,
+        Ordering::Release,
+    );
+}
+
+/// Turns the machine off (ACPI S5).
+///
+/// Returns if the firmware tables gave no way to, or the machine did not
+/// turn off.
+pub fn poweroff() {
+    let pm1_cnt = PM1_CNT.load(Ordering::Acquire);
+    if pm1_cnt == 0 {
+        return;
+    }
+    let (pm1a_cnt, pm1b_cnt) = (pm1_cnt as u16, (pm1_cnt >> 16) as u16);
+    let smi = SMI_CMD.load(Ordering::Relaxed);
+    let (smi_cmd, acpi_enable) = (smi as u16, (smi >> 16) as u8);
+    let [a, b] = SLP_TYP.load(Ordering::Relaxed).to_le_bytes();
+    let slp_typ = SleepType { a, b };
+    unsafe {
+        if smi_cmd != 0 && inw(pm1a_cnt) & SCI_EN == 0 {
+            // Ask the firmware to switch to ACPI mode.
+            outb(smi_cmd, acpi_enable);
+            for _ in 0..ACPI_ENABLE_TIMEOUT_MS / 10 {
+                if inw(pm1a_cnt) & SCI_EN != 0 {
+                    break;
+                }
+                pit::delay_ms(10);
+            }
+        }
+        outw(pm1a_cnt, slp_typ.pm1a_cnt());
+        if pm1b_cnt != 0 {
+            outw(pm1b_cnt, slp_typ.pm1b_cnt());
+        }
+    }
+}
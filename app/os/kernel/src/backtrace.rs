@@ -0,0 +1,46 @@
+//! Call stack walking through saved `%ebp` frame pointers.
+//!
+//! The target spec forces frame pointers, so every kernel function starts
+//! with `push ebp; mov ebp, esp` and a frame looks like:
+//!
+//! ```txt
+//! ebp + 4 -> return address into the caller
+//! ebp     -> caller's saved ebp
+//! ```
+//!
+//! The walk stops at a null frame pointer, which `entry` sets up before
+//! calling `main`.
+
+use crate::memlayout::KERNEL_BASE;
+use crate::x86::read_ebp;
+
+/// Walks the frames starting at frame pointer `ebp`, innermost first,
+/// yielding return addresses.
+#[derive(Debug, Clone)]
+pub struct Backtrace {
+    ebp: usize,
+}
+
+impl Backtrace {
+    /// Starts at the function calling this one.
+    #[inline(always)]
+    pub fn current() -> Self {
+        Self { ebp: read_ebp() }
+    }
+}
+
+impl Iterator for Backtrace {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let ebp = self.ebp;
+        if ebp < KERNEL_BASE || ebp == usize::MAX || ebp % align_of::<usize>() != 0 {
+            return None;
+        }
+        let frame = core::ptr::with_exposed_provenance::<usize>(ebp);
+        let (saved_ebp, ret) = unsafe { (frame.read(), frame.add(1).read()) };
+        // Stacks grow down, so callers' frames must be at higher addresses.
+        self.ebp = if saved_ebp > ebp { saved_ebp } else { 0 };
+        Some(ret)
+    }
+}
@@ -0,0 +1,284 @@
+//! Console output (inherited xv6 console.c)
+//!
+//! Everything printed by the kernel goes through [`print!`]/[`println!`] or
+//! the leveled [`error!`], [`warn!`], [`info!`], [`debug!`] and [`trace!`]
+//! macros. Output is serialized by one lock, so lines from different CPUs and
+//! interrupt handlers never interleave.
+//!
+//! The most verbose level compiled in is chosen with the `log-*` cargo
+//! features of the `kernel` crate; messages above it cost nothing at runtime.
+//!
+//! Input arrives through [`intr`], which echoes it and edits the current
+//! line in a buffer; [`read`] hands out whole lines to processes.
+
+use core::fmt::{self, Write as _};
+
+use crate::spinlock::Spinlock;
+use crate::{panic, proc, uart};
+
+/// Severity of a log message. Lower is more severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+            Self::Trace => "TRACE",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// Most verbose level compiled in, or `None` if logging is disabled.
+pub const MAX_LEVEL: Option<Level> = if cfg!(feature = "log-trace") {
+    Some(Level::Trace)
+} else if cfg!(feature = "log-debug") {
+    Some(Level::Debug)
+} else if cfg!(feature = "log-info") {
+    Some(Level::Info)
+} else if cfg!(feature = "log-warn") {
+    Some(Level::Warn)
+} else if cfg!(feature = "log-error") {
+    Some(Level::Error)
+} else {
+    None
+};
+
+/// Whether messages of `level` are compiled in.
+#[inline]
+pub const fn enabled(level: Level) -> bool {
+    match MAX_LEVEL {
+        Some(max) => level as u8 <= max as u8,
+        None => false,
+    }
+}
+
+/// Serializes console output, and guards the input buffer.
+static CONS: Spinlock<Console> = Spinlock::new(
+    "console",
+    Console {
+        input: Input {
+            buf: [0; INPUT_BUF],
+            r: 0,
+            w: 0,
+            e: 0,
+        },
+    },
+);
+
+/// Output devices behind the console, and the input typed into it.
+struct Console {
+    input: Input,
+}
+
+const INPUT_BUF: usize = 128;
+
+/// Console input: a ring buffer of `INPUT_BUF` bytes, indexed modulo its
+/// size.
+struct Input {
+    buf: [u8; INPUT_BUF],
+    /// Read index
+    r: usize,
+    /// Write index: the end of the lines that can be read
+    w: usize,
+    /// Edit index: the end of the line being typed
+    e: usize,
+}
+
+/// Control-x
+const fn ctrl(x: u8) -> u8 {
+    x - b'@'
+}
+
+/// Erases the last echoed character.
+const BACKSPACE: &[u8] = b"\x08 \x08";
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        uart::Uart.write_str(s)
+    }
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments<'_>) {
+    freeze_if_panicked();
+    // Console writes cannot fail.
+    let _ = CONS.lock().write_fmt(args);
+}
+
+#[doc(hidden)]
+pub fn _log(level: Level, args: fmt::Arguments<'_>) {
+    freeze_if_panicked();
+    let _ = writeln!(CONS.lock(), "[{level:<5}] {args}");
+}
+
+/// Writes bytes from user space as they are (xv6 `consolewrite`).
+pub fn write(buf: &[u8]) {
+    freeze_if_panicked();
+    let _cons = CONS.lock();
+    for &c in buf {
+        uart::putc(c);
+    }
+}
+
+/// Input from a device: takes each byte `getc` returns until it has no
+/// more, and edits the current line with it (xv6 `consoleintr`).
+///
+/// - backspace or delete erases the last character,
+/// - ^U erases the line,
+/// - a newline or ^D ends the line and wakes up the readers.
+pub fn intr(mut getc: impl FnMut() -> Option<u8>) {
+    let mut cons = CONS.lock();
+    let input = &mut cons.input;
+    while let Some(c) = getc() {
+        match c {
+            // Kill line.
+            c if c == ctrl(b'U') => {
+                while input.e != input.w && input.buf[(input.e - 1) % INPUT_BUF] != b'\n' {
+                    input.e -= 1;
+                    BACKSPACE.iter().for_each(|&c| uart::putc(c));
+                }
+            }
+            // Backspace
+            0x08 | 0x7F => {
+                if input.e != input.w {
+                    input.e -= 1;
+                    BACKSPACE.iter().for_each(|&c| uart::putc(c));
+                }
+            }
+            0 => {}
+            c if input.e - input.r < INPUT_BUF => {
+                let c = if c == b'\r' { b'\n' } else { c };
+                input.buf[input.e % INPUT_BUF] = c;
+                input.e += 1;
+                uart::putc(c);
+                if c == b'\n' || c == ctrl(b'D') || input.e == input.r + INPUT_BUF {
+                    input.w = input.e;
+                    proc::wakeup(&raw const input.r);
+                }
+            }
+            // Buffer full: drop the byte.
+            _ => {}
+        }
+    }
+}
+
+/// Reads up to `dst.len()` bytes of console input into `dst`, sleeping
+/// until a line has been typed (xv6 `consoleread`). Stops after a newline,
+/// which is kept, and before ^D, which reads as end of file.
+///
+/// Returns the number of bytes read, or `None` if the process is killed
+/// while waiting.
+pub fn read(dst: &mut [u8]) -> Option<usize> {
+    let mut cons = CONS.lock();
+    let mut n = 0;
+    while n < dst.len() {
+        while cons.input.r == cons.input.w {
+            if proc::killed() {
+                return None;
+            }
+            let chan = &raw const cons.input.r;
+            cons = proc::sleep(chan, cons);
+        }
+        let input = &mut cons.input;
+        let c = input.buf[input.r % INPUT_BUF];
+        input.r += 1;
+        if c == ctrl(b'D') {
+            // EOF
+            if n > 0 {
+                // Save ^D for next time, to make sure
+                // caller gets a 0-byte result.
+                input.r -= 1;
+            }
+            break;
+        }
+        dst[n] = c;
+        n += 1;
+        if c == b'\n' {
+            break;
+        }
+    }
+    Some(n)
+}
+
+/// Another CPU panicked: stop here so its report is not interleaved.
+fn freeze_if_panicked() {
+    if panic::panicked() {
+        panic::freeze();
+    }
+}
+
+/// Prints to the console.
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::console::_print(format_args!($($arg)*))
+    };
+}
+
+/// Prints to the console, with a newline.
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::print!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::console::_print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+/// Logs a line at the given [`Level`](crate::console::Level) if it is compiled in.
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {{
+        let level: $crate::console::Level = $level;
+        if $crate::console::enabled(level) {
+            $crate::console::_log(level, format_args!($($arg)+));
+        }
+    }};
+}
+
+/// Logs at [`Level::Error`](crate::console::Level::Error).
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::log!($crate::console::Level::Error, $($arg)+) };
+}
+
+/// Logs at [`Level::Warn`](crate::console::Level::Warn).
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::log!($crate::console::Level::Warn, $($arg)+) };
+}
+
+/// Logs at [`Level::Info`](crate::console::Level::Info).
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::log!($crate::console::Level::Info, $($arg)+) };
+}
+
+/// Logs at [`Level::Debug`](crate::console::Level::Debug).
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::log!($crate::console::Level::Debug, $($arg)+) };
+}
+
+/// Logs at [`Level::Trace`](crate::console::Level::Trace).
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => { $crate::log!($crate::console::Level::Trace, $($arg)+) };
+}
@@ -1,5 +1,9 @@
 use core::arch::global_asm;
 
+use page::cr::{CR0_PG, CR0_WP, CR4_PSE};
+use page::{PageDirectory, Pte, PteFlags, pdx};
+
+use crate::memlayout::KERNEL_BASE;
 use crate::params::K_STACK_SIZE;
 
 global_asm!(
@@ -18,49 +22,52 @@ _start = [entry - {kernel_base}]   # addr - KERNEL_BASE: Virt to Phys addr
 
 .global entry
 entry:
+    # Turn on page size extension for 4Mbyte pages
     mov eax, cr4
-    or eax, 0x10          # CR4_PSE
+    or eax, {cr4_pse}
     mov cr4, eax
 
     # Set page dir
-    mov eax, [ENTRY_PG_DIR - {kernel_base}] # eax = virt_to_phys_addr(ENTRY_PG_DIR)
+    mov eax, offset ENTRY_PG_DIR - {kernel_base} # eax = virt_to_phys_addr(ENTRY_PG_DIR)
     mov cr3, eax
 
     # Turn on paging
     mov eax, cr0
-    or eax, 0x80010000    # CR0_PG | CR0_WP
+    or eax, {cr0_pg_wp}
     mov cr0, eax
 
-    mov esp, [STACK - {k_stack_size}]
+    # Set up the stack pointer (stack grows down from the end of STACK)
+    mov esp, offset STACK + {k_stack_size}
+    # Terminate the frame pointer chain for backtraces
+    xor ebp, ebp
 
     mov eax, offset main
     jmp eax
 "#,
     kernel_base = const KERNEL_BASE,
     k_stack_size = const K_STACK_SIZE,
+    cr4_pse = const CR4_PSE,
+    cr0_pg_wp = const CR0_PG | CR0_WP,
 );
 
+/// The boot page table used in entry, and by the other CPUs in
+/// [`entryother`](crate::entryother).
+///
+/// Page directories (and page tables) must start on page boundaries,
+/// hence the alignment of [`PageDirectory`]. Use PTE_PS in page directory
+/// entry to enable 4Mbyte pages.
 #[unsafe(no_mangle)]
-static ENTRY_PG_DIR: AlignedPdeArray = make_entry_pg_dir();
-
-const N_PD_ENTRIES: usize = 1024;
-const PTE_P: u32 = 0x001; // Present
-const PTE_W: u32 = 0x002; // Writable
-const PTE_PS: u32 = 0x080; // Page size (4MB)
-const KERNEL_BASE: usize = 0x8000_0000; // 例
-/// 4MiB shift
-const PDX_SHIFT: usize = 22;
-
-#[allow(unused)]
-#[repr(align(4096))]
-struct AlignedPdeArray([u32; N_PD_ENTRIES]);
+pub static ENTRY_PG_DIR: PageDirectory = {
+    const LARGE_RW: PteFlags = PteFlags::PRESENT
+        .union(PteFlags::WRITABLE)
+        .union(PteFlags::LARGE);
 
-const fn make_entry_pg_dir() -> AlignedPdeArray {
-    let mut arr = [0u32; N_PD_ENTRIES];
-    arr[0] = (0) | PTE_P | PTE_W | PTE_PS;
-    arr[KERNEL_BASE >> PDX_SHIFT] = (0) | PTE_P | PTE_W | PTE_PS;
-    AlignedPdeArray(arr)
-}
+    PageDirectory::new()
+        // Map VA's [0, 4MB) to PA's [0, 4MB)
+        .with_entry(0, Pte::new(0, LARGE_RW))
+        // Map VA's [KERNEL_BASE, KERNEL_BASE+4MB) to PA's [0, 4MB)
+        .with_entry(pdx(KERNEL_BASE), Pte::new(0, LARGE_RW))
+};
 
 #[unsafe(no_mangle)]
 #[unsafe(link_section = ".bss.stack")]
@@ -0,0 +1,125 @@
+//! Entry code for the non-boot processors (inherited xv6 entryother.S)
+//!
+//! Each non-boot CPU ("AP") is started up in response to a STARTUP
+//! IPI from the boot CPU.  Section B.4.2 of the Multi-Processor
+//! Specification says that the AP will start in real mode with CS:IP
+//! set to XY00:0000, where XY is an 8-bit value sent with the
+//! STARTUP. Thus this code must start at a 4096-byte boundary.
+//!
+//! Because this code sets DS to zero, it must sit
+//! at an address in the low 2^16 bytes.
+//!
+//! `startothers` (in main.rs) sends the STARTUPs one at a time.
+//! It copies this code ([`code`]) at [`START`]. It puts the address of a
+//! newly allocated per-core stack in `START-4`, the address of the place to
+//! jump to (`mpenter`) in `START-8`, and the physical address of
+//! `ENTRY_PG_DIR` in `START-12`.
+//!
+//! This code combines elements of bootasm.S and entry.S.
+
+use core::arch::global_asm;
+
+use page::cr::{CR0_PE, CR0_PG, CR0_WP, CR4_PSE};
+use segment::{Ring, SegmentDescriptor, SegmentSelector};
+
+/// Physical address the code is copied to.
+pub const START: usize = 0x7000;
+
+unsafe extern "C" {
+    #[link_name = "entryother_start"]
+    static ENTRYOTHER_START: [u8; 0];
+    #[link_name = "entryother_end"]
+    static ENTRYOTHER_END: [u8; 0];
+}
+
+// The code only runs at START, so every address in it is computed from there.
+global_asm!(
+    r#"
+.section .rodata.entryother, "a"
+.code16
+.global entryother_start
+entryother_start:
+    cli
+
+    # Zero data segment registers DS, ES, and SS.
+    xorw    %ax, %ax
+    movw    %ax, %ds
+    movw    %ax, %es
+    movw    %ax, %ss
+
+    # Switch from real to protected mode.  Use a bootstrap GDT that makes
+    # virtual addresses map directly to physical addresses so that the
+    # effective memory map doesn't change during the transition.
+    lgdt    (gdtdesc - entryother_start + {start})
+    movl    %cr0, %eax
+    orl     ${cr0_pe}, %eax
+    movl    %eax, %cr0
+
+    # Complete the transition to 32-bit protected mode by using a long jmp
+    # to reload %cs and %eip.  The segment descriptors are set up with no
+    # translation, so that the mapping is still the identity mapping.
+    ljmpl   ${kcode}, $(start32 - entryother_start + {start})
+
+.code32  # Tell assembler to generate 32-bit code now.
+start32:
+    # Set up the protected-mode data segment registers
+    movw    ${kdata}, %ax
+    movw    %ax, %ds
+    movw    %ax, %es
+    movw    %ax, %ss
+    movw    $0, %ax
+    movw    %ax, %fs
+    movw    %ax, %gs
+
+    # Turn on page size extension for 4Mbyte pages
+    movl    %cr4, %eax
+    orl     ${cr4_pse}, %eax
+    movl    %eax, %cr4
+    # Use ENTRY_PG_DIR as our initial page table
+    movl    ({start} - 12), %eax
+    movl    %eax, %cr3
+    # Turn on paging.
+    movl    %cr0, %eax
+    orl     ${cr0_pe_pg_wp}, %eax
+    movl    %eax, %cr0
+
+    # Switch to the stack allocated by startothers()
+    movl    ({start} - 4), %esp
+    # Terminate the frame pointer chain for backtraces
+    xorl    %ebp, %ebp
+    # Call mpenter()
+    call    *({start} - 8)
+
+spin:
+    jmp     spin
+
+.p2align 2
+gdt:
+    .quad   0
+    .quad   {code}
+    .quad   {data}
+
+gdtdesc:
+    .word   (gdtdesc - gdt - 1)
+    .long   (gdt - entryother_start + {start})
+
+.global entryother_end
+entryother_end:
+"#,
+    start = const START,
+    cr0_pe = const CR0_PE,
+    cr0_pe_pg_wp = const CR0_PE | CR0_PG | CR0_WP,
+    cr4_pse = const CR4_PSE,
+    kcode = const SegmentSelector::KERNEL_CODE.bits(),
+    kdata = const SegmentSelector::KERNEL_DATA.bits(),
+    code = const SegmentDescriptor::code(Ring::Kernel).bits(),
+    data = const SegmentDescriptor::data(Ring::Kernel).bits(),
+    options(att_syntax),
+);
+
+/// The entry code, to be copied to [`START`].
+pub fn code() -> &'static [u8] {
+    let start = (&raw const ENTRYOTHER_START).cast::<u8>();
+    let len = (&raw const ENTRYOTHER_END).addr() - start.addr();
+    unsafe { core::slice::from_raw_parts(start, len) }
+}
@@ -0,0 +1,138 @@
+//! Replacing the program of a process (inherited xv6 exec.c)
+
+use core::ptr::NonNull;
+
+use elf::file::{ElfFile, SegmentType};
+use page::{PG_SIZE, PageDirectory, pg_round_up};
+use syscall::MAXARG;
+
+use crate::proc::myproc;
+use crate::{programs, vm};
+
+/// An argument string in the memory of the process calling exec, checked to
+/// be nul-terminated there.
+#[derive(Debug, Clone, Copy)]
+pub struct UserStr {
+    pub addr: usize,
+    /// Length without the NUL
+    pub len: usize,
+}
+
+/// Replace the program of the running process with the one at `path`,
+/// started as `main(argc, argv)` with copies of the strings `argv`.
+/// Returns `None`, leaving the process as it was, if that fails.
+///
+/// # Panics
+/// Panics if no process is running.
+pub fn exec(path: &[u8], argv: &[UserStr]) -> Option<()> {
+    // SAFETY: the running process's own fields.
+    let Some(curproc) = (unsafe { myproc() }) else {
+        panic!("exec: no process");
+    };
+    let image = programs::lookup(path)?;
+    let oldpgdir = curproc.pgdir?;
+
+    let pgdir = vm::setupkvm()?;
+    let Some((entry, sz, sp)) = load(pgdir, image, oldpgdir, argv) else {
+        unsafe { vm::freevm(pgdir) };
+        return None;
+    };
+
+    // Save program name for debugging.
+    let last = path.rsplit(|&c| c == b'/').next().unwrap_or(path);
+    curproc.set_name(last);
+
+    // Commit to the user image.
+    curproc.pgdir = Some(pgdir);
+    curproc.sz = sz;
+    // SAFETY: the trap frame of the running process's system call.
+    unsafe {
+        (*curproc.tf).eip = entry; // main
+        (*curproc.tf).esp = sp as u32;
+    }
+    vm::switchuvm(curproc);
+    // SAFETY: no CPU uses the old page table any more.
+    unsafe { vm::freevm(oldpgdir) };
+    Some(())
+}
+
+/// Builds the new user image in `pgdir`: the program's segments, then a
+/// guard page and a stack page holding the arguments, copied from
+/// `oldpgdir`. Returns the entry point, the size and the stack pointer.
+fn load(
+    pgdir: NonNull<PageDirectory>,
+    image: &[u8],
+    oldpgdir: NonNull<PageDirectory>,
+    argv: &[UserStr],
+) -> Option<(u32, usize, usize)> {
+    // Check ELF header
+    let elf = ElfFile::parse(image).ok()?;
+
+    // Load program into memory.
+    // `parse` checked that each segment's contents are in the file, and
+    // that it fits in memory and the address space.
+    let mut sz = 0;
+    for ph in elf.segments() {
+        if ph.segment_type() != SegmentType::Load {
+            continue;
+        }
+        let va = ph.virtual_address() as usize;
+        sz = vm::allocuvm(pgdir, sz, va + ph.memory_size() as usize)?;
+        if va % PG_SIZE != 0 {
+            return None;
+        }
+        vm::loaduvm(pgdir, va, ph.data());
+    }
+
+    // Allocate two pages at the next page boundary.
+    // Make the first inaccessible.  Use the second as the user stack.
+    sz = pg_round_up(sz);
+    sz = vm::allocuvm(pgdir, sz, sz + 2 * PG_SIZE)?;
+    vm::clearpteu(pgdir, sz - 2 * PG_SIZE);
+    let mut sp = sz;
+
+    // Push argument strings, prepare rest of stack in ustack.
+    if argv.len() > MAXARG {
+        return None;
+    }
+    let mut ustack = [0_u32; 3 + MAXARG + 1];
+    for (i, arg) in argv.iter().enumerate() {
+        sp = (sp.checked_sub(arg.len + 1)?) & !3;
+        copy_str(oldpgdir, *arg, pgdir, sp)?;
+        ustack[3 + i] = sp as u32;
+    }
+    let argc = argv.len();
+    ustack[3 + argc] = 0;
+
+    ustack[0] = 0xffff_ffff; // fake return PC
+    ustack[1] = argc as u32;
+    ustack[2] = (sp - (argc + 1) * 4) as u32; // argv pointer
+
+    let ustack = &ustack[..3 + argc + 1];
+    sp = sp.checked_sub(size_of_val(ustack))?;
+    let mut words = [0_u8; 4 * (3 + MAXARG + 1)];
+    for (chunk, word) in words.chunks_exact_mut(4).zip(ustack) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    vm::copyout(pgdir, sp, &words[..size_of_val(ustack)])?;
+
+    Some((elf.entry(), sz, sp))
+}
+
+/// Copies the string `arg` of `from`, and its NUL, to `va` in `to`.
+fn copy_str(
+    from: NonNull<PageDirectory>,
+    arg: UserStr,
+    to: NonNull<PageDirectory>,
+    va: usize,
+) -> Option<()> {
+    let mut buf = [0; 64];
+    let mut off = 0;
+    while off <= arg.len {
+        let n = (arg.len + 1 - off).min(buf.len());
+        vm::copyin(from, arg.addr + off, &mut buf[..n])?;
+        vm::copyout(to, va + off, &buf[..n])?;
+        off += n;
+    }
+    Some(())
+}
@@ -0,0 +1,58 @@
+//! Open files (inherited xv6 file.h/file.c)
+//!
+//! There is no file system: the only file is the console, which the first
+//! process starts with as its standard input, output and error (see
+//! `userinit`) and every other process inherits. A file holds no state, so
+//! descriptors share it by copy rather than by reference count.
+
+use core::ptr::NonNull;
+
+use page::PageDirectory;
+use syscall::UserMemory;
+
+use crate::sleeplock::SleepLock;
+use crate::{console, vm};
+
+/// Held for the whole of a console write, so that the output of one `write`
+/// is not interleaved with another process's.
+static CONSOLE_WRITE: SleepLock<()> = SleepLock::new("console write", ());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum File {
+    Console,
+}
+
+impl File {
+    /// Read up to `n` bytes from file f to user address `addr` of `pgdir`.
+    /// The console returns at most one line, sleeping until it is typed.
+    pub fn read(self, pgdir: NonNull<PageDirectory>, addr: usize, n: usize) -> Option<usize> {
+        match self {
+            Self::Console => {
+                let mut buf = [0; 128];
+                let len = n.min(buf.len());
+                let n = console::read(&mut buf[..len])?;
+                vm::copyout(pgdir, addr, &buf[..n])?;
+                Some(n)
+            }
+        }
+    }
+
+    /// Write `n` bytes at user address `addr` of `mem` to file f.
+    /// The range must be inside `mem` (see `Args::argptr`).
+    pub fn write(self, mem: &impl UserMemory, addr: usize, n: usize) -> Option<usize> {
+        match self {
+            Self::Console => {
+                let _writing = CONSOLE_WRITE.lock();
+                let mut buf = [0; 128];
+                let mut off = 0;
+                while off < n {
+                    let len = (n - off).min(buf.len());
+                    mem.read(addr + off, &mut buf[..len]);
+                    console::write(&buf[..len]);
+                    off += len;
+                }
+                Some(n)
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! The I/O APIC manages hardware interrupts for an SMP system (inherited xv6 ioapic.c)
+//!
+//! <http://www.intel.com/design/chipsets/datashts/29056601.pdf>
+
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
+
+use trap::T_IRQ0;
+
+/// Default physical address of IO APIC. Device space is mapped 1:1.
+const IOAPIC: usize = 0xFEC0_0000;
+
+/// Register index: ID
+const REG_ID: u32 = 0x00;
+/// Register index: version
+const REG_VER: u32 = 0x01;
+/// Redirection table base
+const REG_TABLE: u32 = 0x10;
+
+// The redirection table starts at REG_TABLE and uses
+// two registers to configure each interrupt.
+// The first (low) register in a pair contains configuration bits.
+// The second (high) register contains a bitmask telling which
+// CPUs can serve that interrupt.
+/// Interrupt disabled
+const INT_DISABLED: u32 = 0x0001_0000;
+/// Level-triggered (vs edge-)
+const INT_LEVEL: u32 = 0x0000_8000;
+/// Active low (vs high)
+const INT_ACTIVELOW: u32 = 0x0000_2000;
+
+/// Number of ISA IRQs
+const NISA: usize = 16;
+
+/// Set by [`init`]; machines without an IO APIC use the PIC instead.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// IO APIC ID the firmware tables give.
+static IOAPICID: AtomicU8 = AtomicU8::new(0);
+
+/// Pin and flags of each ISA IRQ wired differently from the default
+/// (same-numbered pin, edge-triggered, active high), or [`NO_OVERRIDE`].
+static OVERRIDES: [AtomicU32; NISA] = [const { AtomicU32::new(NO_OVERRIDE) }; NISA];
+const NO_OVERRIDE: u32 = u32::MAX;
+
+/// IO APIC MMIO structure: write reg, then read or write data.
+#[repr(C)]
+struct Ioapic {
+    reg: u32,
+    _pad: [u32; 3],
+    data: u32,
+}
+
+fn ioapic() -> *mut Ioapic {
+    ptr::with_exposed_provenance_mut(IOAPIC)
+}
+
+fn ioapicread(reg: u32) -> u32 {
+    let ioapic = ioapic();
+    unsafe {
+        ptr::write_volatile(&raw mut (*ioapic).reg, reg);
+        ptr::read_volatile(&raw const (*ioapic).data)
+    }
+}
+
+fn ioapicwrite(reg: u32, data: u32) {
+    let ioapic = ioapic();
+    unsafe {
+        ptr::write_volatile(&raw mut (*ioapic).reg, reg);
+        ptr::write_volatile(&raw mut (*ioapic).data, data);
+    }
+}
+
+/// Marks all interrupts edge-triggered, active high, disabled,
+/// and not routed to any CPUs.
+pub fn init() {
+    let maxintr = (ioapicread(REG_VER) >> 16) & 0xFF;
+    let id = ioapicread(REG_ID) >> 24;
+    debug!("ioapic: id {id}, {} interrupts", maxintr + 1);
+    if id != u32::from(IOAPICID.load(Ordering::Relaxed)) {
+        warn!("ioapic: id isn't equal to ioapicid; not a MP");
+    }
+
+    for i in 0..=maxintr {
+        ioapicwrite(REG_TABLE + 2 * i, INT_DISABLED | (T_IRQ0 + i));
+        ioapicwrite(REG_TABLE + 2 * i + 1, 0);
+    }
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Records the IO APIC ID given by the MP or ACPI tables, which [`init`]
+/// checks.
+pub fn set_id(id: u8) {
+    IOAPICID.store(id, Ordering::Relaxed);
+}
+
+/// Records that ISA `irq` is wired to input pin `pin`, with the given
+/// trigger mode and polarity. Overrides for other buses are ignored.
+pub fn set_override(irq: u8, pin: u32, level: bool, active_low: bool) {
+    let Some(entry) = OVERRIDES.get(usize::from(irq)) else {
+        return;
+    };
+    let mut flags = 0;
+    if level {
+        flags |= INT_LEVEL;
+    }
+    if active_low {
+        flags |= INT_ACTIVELOW;
+    }
+    entry.store((pin << 16) | flags, Ordering::Relaxed);
+}
+
+/// Input pin and redirection flags of ISA `irq`.
+fn route(irq: u32) -> (u32, u32) {
+    match OVERRIDES
+        .get(irq as usize)
+        .map(|entry| entry.load(Ordering::Relaxed))
+    {
+        Some(NO_OVERRIDE) | None => (irq, 0),
+        Some(route) => (route >> 16, route & 0xFFFF),
+    }
+}
+
+/// Routes `irq` to the CPU with APIC ID `apicid`.
+///
+/// Mark interrupt enabled, and routed to the given cpunum, which happens to
+/// be that cpu's APIC ID. It is edge-triggered and active high unless the
+/// firmware said otherwise (see [`set_override`]).
+/// Does nothing without an IO APIC.
+pub fn enable(irq: u32, apicid: u32) {
+    if !ENABLED.load(Ordering::Acquire) {
+        return;
+    }
+    let (pin, flags) = route(irq);
+    ioapicwrite(REG_TABLE + 2 * pin, flags | (T_IRQ0 + irq));
+    ioapicwrite(REG_TABLE + 2 * pin + 1, apicid << 24);
+}
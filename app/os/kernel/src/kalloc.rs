@@ -0,0 +1,72 @@
+//! Physical memory allocator (inherited xv6 kalloc.c)
+//!
+//! The free list itself lives in the `memory` crate; this module owns the
+//! global instance and seeds it from the memory after the kernel image.
+
+use core::ptr::NonNull;
+
+use memory::Kmem;
+
+use crate::memlayout::{PHYSTOP, p2v};
+use crate::spinlock::Spinlock;
+
+unsafe extern "C" {
+    /// First address after kernel loaded from ELF file.
+    /// Defined by the kernel linker script.
+    #[link_name = "end"]
+    static KERNEL_END: [u8; 0];
+}
+
+static KMEM: Spinlock<Kmem> = Spinlock::new("kmem", Kmem::new());
+
+/// First address after the kernel image.
+pub fn kernel_end() -> *mut u8 {
+    (&raw const KERNEL_END).cast::<u8>().cast_mut()
+}
+
+/// Initialization happens in two phases.
+/// 1. main() calls kinit1() while still using ENTRY_PG_DIR to place just
+///    the pages mapped by ENTRY_PG_DIR on free list.
+/// 2. main() calls kinit2() with the rest of the physical pages
+///    after installing a full page table that maps them on all cores.
+///
+/// # Safety
+/// `[vstart, vend)` must be mapped and unused by anything else.
+pub unsafe fn kinit1(vstart: *mut u8, vend: *mut u8) {
+    let mut kmem = KMEM.lock();
+    kmem.init(
+        kernel_end(),
+        core::ptr::with_exposed_provenance_mut(p2v(PHYSTOP)),
+    );
+    unsafe { kmem.free_range(vstart, vend) };
+}
+
+/// Second phase of initialization; see [`kinit1`].
+///
+/// # Safety
+/// `[vstart, vend)` must be mapped and unused by anything else.
+pub unsafe fn kinit2(vstart: *mut u8, vend: *mut u8) {
+    unsafe { KMEM.lock().free_range(vstart, vend) };
+}
+
+/// Allocate one 4096-byte page of physical memory.
+///
+/// Returns a pointer that the kernel can use.
+/// Returns `None` if the memory cannot be allocated.
+pub fn kalloc() -> Option<NonNull<u8>> {
+    KMEM.lock().alloc()
+}
+
+/// Free the page of physical memory pointed at by `page`, which normally
+/// should have been returned by a call to [`kalloc`].
+///
+/// # Safety
+/// `page` must not be used after this call.
+pub unsafe fn kfree(page: NonNull<u8>) {
+    unsafe { KMEM.lock().free(page) };
+}
+
+/// Number of free pages.
+pub fn free_pages() -> usize {
+    KMEM.lock().free_pages()
+}
@@ -0,0 +1,30 @@
+//! PC keyboard controller (inherited xv6 kbd.c)
+//!
+//! Scancodes are not translated yet; they are only drained, so the
+//! controller keeps interrupting, and logged at trace level.
+
+use trap::IRQ_KBD;
+
+use crate::x86::inb;
+use crate::{ioapic, lapic, picirq};
+
+/// kbd controller status port(I)
+const KBSTATP: u16 = 0x64;
+/// kbd data in buffer
+const KBS_DIB: u8 = 0x01;
+/// kbd data port(I)
+const KBDATAP: u16 = 0x60;
+
+/// Routes the keyboard interrupt to this CPU.
+pub fn init() {
+    picirq::enable(IRQ_KBD);
+    ioapic::enable(IRQ_KBD, lapic::lapicid());
+}
+
+/// Keyboard interrupt.
+pub fn intr() {
+    while unsafe { inb(KBSTATP) } & KBS_DIB != 0 {
+        let data = unsafe { inb(KBDATAP) };
+        trace!("kbd: scancode {data:#04x}");
+    }
+}
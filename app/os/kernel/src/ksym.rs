@@ -0,0 +1,56 @@
+//! Kernel symbol table for backtraces.
+//!
+//! The kernel reserves [`KSYMTAB_SIZE`] bytes in the `.ksymtab` section.
+//! After linking, `xtask image` fills them with an [`elf::symbol_map`] table
+//! built from the kernel's own `.symtab`. Without that step (or when the
+//! table does not fit) the section stays zeroed and addresses are printed raw.
+
+use core::fmt;
+
+use elf::symbol_map::{Entry, SymbolMap};
+
+/// Bytes reserved for the table. `xtask image` fails if it does not fit.
+const KSYMTAB_SIZE: usize = 256 * 1024;
+
+#[used]
+#[unsafe(link_section = ".ksymtab")]
+static KSYMTAB_SPACE: [u8; KSYMTAB_SIZE] = [0; KSYMTAB_SIZE];
+
+unsafe extern "C" {
+    /// Bounds of the `.ksymtab` section. Defined by the kernel linker script.
+    static __KSYMTAB_BEGIN__: [u8; 0];
+    static __KSYMTAB_END__: [u8; 0];
+}
+
+fn symbol_map() -> Option<SymbolMap<'static>> {
+    // Read through the linker symbols: the contents of KSYMTAB_SPACE seen by
+    // the compiler (all zero) are not what ends up in the image.
+    let begin = (&raw const __KSYMTAB_BEGIN__).cast::<u8>();
+    let len = (&raw const __KSYMTAB_END__).addr() - begin.addr();
+    let data = unsafe { core::slice::from_raw_parts(begin, len) };
+    SymbolMap::new(data)
+}
+
+/// Finds the function containing `pc`.
+pub fn lookup(pc: usize) -> Option<(Entry<'static>, usize)> {
+    let (entry, offset) = symbol_map()?.lookup(u32::try_from(pc).ok()?)?;
+    Some((entry, offset as usize))
+}
+
+/// Formats a return address as `0x80101234 function+0x1c`.
+///
+/// The address looked up is `pc - 1`, so a call at the very end of a
+/// function is not attributed to the next one.
+#[derive(Debug, Clone, Copy)]
+pub struct ReturnAddress(pub usize);
+
+impl fmt::Display for ReturnAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pc = self.0;
+        write!(f, "{pc:#010x}")?;
+        if let Some((entry, offset)) = pc.checked_sub(1).and_then(lookup) {
+            write!(f, " {}+{:#x}", entry.name, offset + 1)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+//! The local APIC manages internal (non-I/O) interrupts (inherited xv6 lapic.c)
+//!
+//! Besides routing interrupts to this CPU, each local APIC has a timer. The
+//! boot CPU calibrates its one against the PIT, before the other CPUs start
+//! and use the same count; then they all fire [`T_IRQ0`] + [`IRQ_TIMER`]
+//! [`TICK_HZ`] times a second.
+
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+
+use trap::{IRQ_ERROR, IRQ_SPURIOUS, IRQ_TIMER, T_IRQ0};
+
+use crate::memlayout::p2v;
+use crate::params::TICK_HZ;
+use crate::pit;
+use crate::x86::outb;
+
+/// Default physical address of the local APIC. Device space is mapped
+/// 1:1 (see `vm::kmap`).
+pub const DEFAULT_BASE: usize = 0xFEE0_0000;
+
+// Local APIC registers, divided by 4 for use as u32 indices.
+/// ID
+const ID: usize = 0x0020 / 4;
+/// Version
+const VER: usize = 0x0030 / 4;
+/// Task Priority
+const TPR: usize = 0x0080 / 4;
+/// EOI
+const EOI: usize = 0x00B0 / 4;
+/// Spurious Interrupt Vector
+const SVR: usize = 0x00F0 / 4;
+/// Unit Enable
+const ENABLE: u32 = 0x0000_0100;
+/// Error Status
+const ESR: usize = 0x0280 / 4;
+/// Interrupt Command
+const ICRLO: usize = 0x0300 / 4;
+/// NMI
+const NMI: u32 = 0x0000_0400;
+/// INIT/RESET
+const INIT: u32 = 0x0000_0500;
+/// Startup IPI
+const STARTUP: u32 = 0x0000_0600;
+/// Delivery status
+const DELIVS: u32 = 0x0000_1000;
+/// Assert interrupt (vs deassert)
+const ASSERT: u32 = 0x0000_4000;
+/// Level triggered
+const LEVEL: u32 = 0x0000_8000;
+/// Send to all APICs, including self.
+const BCAST: u32 = 0x0008_0000;
+/// Send to all APICs, excluding self.
+const OTHERS: u32 = 0x000C_0000;
+/// Interrupt Command [63:32]
+const ICRHI: usize = 0x0310 / 4;
+/// Local Vector Table 0 (TIMER)
+const TIMER: usize = 0x0320 / 4;
+/// divide counts by 1
+const X1: u32 = 0x0000_000B;
+/// Periodic
+const PERIODIC: u32 = 0x0002_0000;
+/// Performance Counter LVT
+const PCINT: usize = 0x0340 / 4;
+/// Local Vector Table 1 (LINT0)
+const LINT0: usize = 0x0350 / 4;
+/// Local Vector Table 2 (LINT1)
+const LINT1: usize = 0x0360 / 4;
+/// Local Vector Table 3 (ERROR)
+const ERROR: usize = 0x0370 / 4;
+/// Interrupt masked
+const MASKED: u32 = 0x0001_0000;
+/// Timer Initial Count
+const TICR: usize = 0x0380 / 4;
+/// Timer Current Count
+const TCCR: usize = 0x0390 / 4;
+/// Timer Divide Configuration
+const TDCR: usize = 0x03E0 / 4;
+
+/// CMOS RTC index port
+const CMOS_PORT: u16 = 0x70;
+/// CMOS RTC data port
+const CMOS_RETURN: u16 = 0x71;
+/// Warm reset vector (real mode far pointer at 40:67)
+const WARM_RESET_VECTOR: usize = (0x40 << 4) | 0x67;
+
+/// How long the timer is measured against the PIT.
+const CALIBRATION_MS: u32 = 10;
+
+/// The local APIC registers, or null before [`init`].
+static LAPIC: AtomicPtr<u32> = AtomicPtr::new(ptr::null_mut());
+
+/// The timer's initial count, or 0 until the boot CPU has measured it. The
+/// other CPUs reuse it rather than use the PIT while the boot CPU times the
+/// startup IPIs of [`startap`] with it.
+static TIMER_COUNT: AtomicU32 = AtomicU32::new(0);
+
+fn lapic() -> Option<*mut u32> {
+    let lapic = LAPIC.load(Ordering::Acquire);
+    (!lapic.is_null()).then_some(lapic)
+}
+
+/// Whether [`init`] has mapped the local APIC.
+pub fn mapped() -> bool {
+    lapic().is_some()
+}
+
+fn lapicw(lapic: *mut u32, index: usize, value: u32) {
+    unsafe {
+        ptr::write_volatile(lapic.add(index), value);
+        // wait for write to finish, by reading
+        ptr::read_volatile(lapic.add(ID));
+    }
+}
+
+fn lapicr(lapic: *mut u32, index: usize) -> u32 {
+    unsafe { ptr::read_volatile(lapic.add(index)) }
+}
+
+/// Records that the local APICs are at physical address `base`, then sets
+/// up the boot CPU's one (see [`lapicinit`]).
+///
+/// # Safety
+/// `base` must be the address of the local APIC.
+pub unsafe fn init(base: usize) {
+    LAPIC.store(ptr::with_exposed_provenance_mut(base), Ordering::Release);
+    lapicinit();
+}
+
+/// Enables this CPU's local APIC and starts its timer, calibrating it first
+/// on the boot CPU. Does nothing without a local APIC.
+pub fn lapicinit() {
+    let Some(lapic) = lapic() else {
+        return;
+    };
+
+    // Enable local APIC; set spurious interrupt vector.
+    lapicw(lapic, SVR, ENABLE | (T_IRQ0 + IRQ_SPURIOUS));
+
+    // The timer repeatedly counts down at bus frequency
+    // from lapic[TICR] and then issues an interrupt.
+    lapicw(lapic, TDCR, X1);
+    let count = match TIMER_COUNT.load(Ordering::Relaxed) {
+        0 => {
+            let count = calibrate(lapic);
+            TIMER_COUNT.store(count, Ordering::Relaxed);
+            count
+        }
+        count => count,
+    };
+    lapicw(lapic, TIMER, PERIODIC | (T_IRQ0 + IRQ_TIMER));
+    lapicw(lapic, TICR, count);
+
+    // Disable logical interrupt lines.
+    lapicw(lapic, LINT0, MASKED);
+    lapicw(lapic, LINT1, MASKED);
+
+    // Disable performance counter overflow interrupts
+    // on machines that provide that interrupt entry.
+    if (lapicr(lapic, VER) >> 16) & 0xFF >= 4 {
+        lapicw(lapic, PCINT, MASKED);
+    }
+
+    // Map error interrupt to IRQ_ERROR.
+    lapicw(lapic, ERROR, T_IRQ0 + IRQ_ERROR);
+
+    // Clear error status register (requires back-to-back writes).
+    lapicw(lapic, ESR, 0);
+    lapicw(lapic, ESR, 0);
+
+    // Ack any outstanding interrupts.
+    lapicw(lapic, EOI, 0);
+
+    // Send an Init Level De-Assert to synchronise arbitration ID's.
+    lapicw(lapic, ICRHI, 0);
+    lapicw(lapic, ICRLO, BCAST | INIT | LEVEL);
+    while lapicr(lapic, ICRLO) & DELIVS != 0 {
+        core::hint::spin_loop();
+    }
+
+    // Enable interrupts on the APIC (but not on the processor).
+    lapicw(lapic, TPR, 0);
+}
+
+/// Returns the timer's initial count for [`TICK_HZ`] interrupts a second.
+fn calibrate(lapic: *mut u32) -> u32 {
+    // One-shot (masked) count down from the top while the PIT measures.
+    lapicw(lapic, TIMER, MASKED);
+    lapicw(lapic, TICR, u32::MAX);
+    pit::delay_ms(CALIBRATION_MS);
+    let elapsed = u32::MAX - lapicr(lapic, TCCR);
+    lapicw(lapic, TICR, 0);
+
+    let per_second = u64::from(elapsed) * u64::from(1000 / CALIBRATION_MS);
+    (per_second / u64::from(TICK_HZ)).clamp(1, u64::from(u32::MAX)) as u32
+}
+
+/// APIC ID of this CPU, or 0 without a local APIC.
+pub fn lapicid() -> u32 {
+    lapic().map_or(0, |lapic| lapicr(lapic, ID) >> 24)
+}
+
+/// Acknowledge interrupt.
+pub fn eoi() {
+    if let Some(lapic) = lapic() {
+        lapicw(lapic, EOI, 0);
+    }
+}
+
+/// Sends an NMI to every other CPU, which gets through even with interrupts
+/// disabled. Does nothing without a local APIC.
+pub fn nmi_others() {
+    if let Some(lapic) = lapic() {
+        lapicw(lapic, ICRHI, 0);
+        lapicw(lapic, ICRLO, OTHERS | NMI);
+    }
+}
+
+/// Start additional processor running entry code at `addr`.
+/// See Appendix B of MultiProcessor Specification.
+///
+/// `addr` is a physical address below 1 MiB, on a page boundary.
+pub fn startap(apicid: u8, addr: usize) {
+    let Some(lapic) = lapic() else {
+        return;
+    };
+    let apicid = u32::from(apicid);
+
+    // "The BSP must initialize CMOS shutdown code to 0AH
+    // and the warm reset vector (DWORD based at 40:67) to point at
+    // the AP startup code prior to the [universal startup algorithm]."
+    unsafe {
+        outb(CMOS_PORT, 0xF); // offset 0xF is shutdown code
+        outb(CMOS_RETURN, 0x0A);
+        let wrv = ptr::with_exposed_provenance_mut::<u16>(p2v(WARM_RESET_VECTOR));
+        ptr::write_volatile(wrv, 0);
+        ptr::write_volatile(wrv.add(1), (addr >> 4) as u16);
+    }
+
+    // "Universal startup algorithm."
+    // Send INIT (level-triggered) interrupt to reset other CPU.
+    lapicw(lapic, ICRHI, apicid << 24);
+    lapicw(lapic, ICRLO, INIT | LEVEL | ASSERT);
+    pit::delay_us(200);
+    lapicw(lapic, ICRLO, INIT | LEVEL);
+    pit::delay_us(100); // should be 10ms, but too slow in Bochs!
+
+    // Send startup IPI (twice!) to enter code.
+    // Regular hardware is supposed to only accept a STARTUP
+    // when it is in the halted state due to an INIT.  So the second
+    // should be ignored, but it is part of the official Intel algorithm.
+    // Bochs complains about the second one.  Too bad for Bochs.
+    for _ in 0..2 {
+        lapicw(lapic, ICRHI, apicid << 24);
+        lapicw(lapic, ICRLO, STARTUP | (addr >> 12) as u32);
+        pit::delay_us(200);
+    }
+}
@@ -2,46 +2,175 @@
 #![no_main]
 // #![feature(lang_items)]
 
+#[macro_use]
+mod console;
+mod acpi;
+mod backtrace;
 mod entry;
+mod entryother;
+mod exec;
+mod file;
+mod ioapic;
+mod kalloc;
+mod kbd;
+mod ksym;
+mod lapic;
+mod memlayout;
+mod mp;
+mod panic;
 mod params;
+mod picirq;
+mod pit;
+mod proc;
+mod programs;
+mod qemu;
+mod sleeplock;
+mod spinlock;
+mod swtch;
+mod syscalls;
+mod sysfile;
+mod sysproc;
+mod traps;
+mod uart;
+mod vm;
+mod x86;
 
-use core::fmt::{self, Write};
-
-struct SerialPort;
+/// Bootstrap processor starts running Rust code here.
+#[unsafe(no_mangle)]
+pub extern "C" fn main() -> ! {
+    // serial port
+    uart::init();
+    println!("Hello, xv6 Rust!");
 
-impl Write for SerialPort {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        for byte in s.bytes() {
-            unsafe {
-                // Data register (0x3F8) of QEMU's standard COM1 serial port
-                let com1: *mut u8 = core::ptr::without_provenance_mut(0x3F8);
-                core::ptr::write_volatile(com1, byte);
-            }
-        }
-        Ok(())
+    // phys page allocator
+    unsafe {
+        kalloc::kinit1(
+            kalloc::kernel_end(),
+            core::ptr::with_exposed_provenance_mut(memlayout::p2v(4 * 1024 * 1024)),
+        );
+    }
+    // kernel page table
+    vm::kvmalloc();
+    // detect other processors
+    let lapic_base = acpi::init().or_else(mp::mpinit).unwrap_or_else(|| {
+        warn!("no ACPI or MP tables; assuming a single CPU");
+        lapic::DEFAULT_BASE
+    });
+    // interrupt controllers
+    picirq::init();
+    if x86::has_apic() {
+        unsafe { lapic::init(lapic_base) };
+        ioapic::init();
+    } else {
+        info!("no APIC: using the 8259 PIC and 8253 PIT");
+        pit::init_tick();
     }
+    // segment descriptors
+    vm::seginit();
+    // trap vectors
+    unsafe {
+        trap::tvinit();
+        trap::idtinit();
+    }
+    traps::init();
+    // device interrupts
+    uart::enable_intr();
+    kbd::init();
+    picirq::enable(trap::IRQ_IDE);
+    ioapic::enable(trap::IRQ_IDE, lapic::lapicid());
+    sleeplock::selftest();
+    // start other processors
+    startothers();
+    // must come after startothers()
+    unsafe {
+        kalloc::kinit2(
+            core::ptr::with_exposed_provenance_mut(memlayout::p2v(4 * 1024 * 1024)),
+            core::ptr::with_exposed_provenance_mut(memlayout::p2v(memlayout::PHYSTOP)),
+        );
+    }
+    info!("kalloc: {} pages free", kalloc::free_pages());
+    // first user process
+    proc::userinit();
+    // finish this processor's setup
+    mpmain();
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn main() -> ! {
-    let mut serial = SerialPort;
-    writeln!(serial, "Hello, xv6 Rust!").unwrap();
+/// Other CPUs jump here from entryother.
+extern "C" fn mpenter() -> ! {
+    vm::switchkvm();
+    vm::seginit();
+    lapic::lapicinit();
+    unsafe { trap::idtinit() };
+    mpmain();
+}
 
-    loop {}
-    // qemu_exit(0);
+/// Common CPU setup code.
+fn mpmain() -> ! {
+    info!(
+        "cpu{}: starting (apicid {})",
+        proc::cpuid(),
+        lapic::lapicid()
+    );
+    // tell startothers() we're up
+    proc::set_started();
+    // start running processes
+    proc::scheduler();
 }
 
-fn qemu_exit(code: u8) -> ! {
-    unsafe {
-        core::arch::asm!("out dx, al", in("dx") 0x501u16, in("al") code);
+/// How long an AP gets to reach `mpmain` before it is given up on.
+const AP_START_TIMEOUT_MS: u32 = 1000;
+
+/// Start the non-boot (AP) processors.
+fn startothers() {
+    const _: () = assert!(params::K_STACK_SIZE <= page::PG_SIZE);
+
+    // The startup IPIs are sent through the local APIC.
+    if !lapic::mapped() {
+        if proc::ncpu() > 1 {
+            warn!(
+                "no local APIC: not starting the other {} cpus",
+                proc::ncpu() - 1
+            );
+        }
+        return;
     }
-    loop {}
-}
 
-#[cfg(not(test))]
-#[panic_handler]
-pub fn panic(_info: &core::panic::PanicInfo) -> ! {
-    loop {}
+    // Write entry code to unused memory at 0x7000.
+    let code = core::ptr::with_exposed_provenance_mut::<u8>(memlayout::p2v(entryother::START));
+    let entry = entryother::code();
+    unsafe { core::ptr::copy_nonoverlapping(entry.as_ptr(), code, entry.len()) };
+
+    let me = proc::cpuid();
+    for cpu in (0..proc::ncpu()).filter(|&cpu| cpu != me) {
+        // Tell entryother what stack to use, where to enter, and what
+        // pgdir to use. We cannot use kpgdir yet, because the AP processor
+        // is running in low  memory, so we use ENTRY_PG_DIR for the APs too.
+        // kinit2 has not run, so the stack is in the low 4 MiB it maps.
+        let Some(stack) = kalloc::kalloc() else {
+            panic!("startothers: out of memory");
+        };
+        let pgdir = memlayout::v2p((&raw const entry::ENTRY_PG_DIR).addr());
+        unsafe {
+            let args = code.cast::<u32>();
+            args.sub(1)
+                .write(stack.as_ptr().add(params::K_STACK_SIZE).addr() as u32);
+            args.sub(2).write(mpenter as usize as u32);
+            args.sub(3).write(pgdir as u32);
+        }
+
+        lapic::startap(proc::apicid(cpu), entryother::START);
+
+        // wait for cpu to finish mpmain()
+        let mut waited_ms = 0;
+        while !proc::started(cpu) {
+            if waited_ms == AP_START_TIMEOUT_MS {
+                warn!("cpu{cpu}: did not start; going on without it");
+                break;
+            }
+            pit::delay_ms(1);
+            waited_ms += 1;
+        }
+    }
 }
 
 // #[cfg(not(test))]
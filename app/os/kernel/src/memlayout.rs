@@ -0,0 +1,27 @@
+//! inherited xv6 memlayout.h
+
+/// Start of extended memory
+pub const EXTMEM: usize = 0x10_0000;
+/// Top physical memory
+///
+/// Must not exceed the RAM given to QEMU (`-m`).
+pub const PHYSTOP: usize = 0xE00_0000;
+/// Other devices are at high addresses
+pub const DEVSPACE: usize = 0xFE00_0000;
+
+/// First kernel virtual address
+pub const KERNEL_BASE: usize = 0x8000_0000;
+/// Address where kernel is linked
+pub const KERNEL_LINK: usize = KERNEL_BASE + EXTMEM;
+
+/// Kernel virtual address to physical address.
+#[inline]
+pub const fn v2p(addr: usize) -> usize {
+    addr - KERNEL_BASE
+}
+
+/// Physical address to kernel virtual address.
+#[inline]
+pub const fn p2v(addr: usize) -> usize {
+    addr + KERNEL_BASE
+}
@@ -0,0 +1,256 @@
+//! Multiprocessor support (inherited xv6 mp.c/mp.h)
+//!
+//! Search memory for MP description structures.
+//! <http://developer.intel.com/design/pentium/datashts/24201606.pdf>
+//!
+//! The BIOS leaves a floating pointer in the EBDA, the last KiB of base
+//! memory or the BIOS ROM. It points at the configuration table, whose
+//! entries list the processors (by local APIC ID) and the I/O APIC.
+
+use crate::memlayout::{PHYSTOP, p2v};
+use crate::params::NCPU;
+use crate::x86::{inb, outb};
+use crate::{ioapic, proc};
+use core::ptr;
+
+/// floating pointer
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Mp {
+    /// "_MP_"
+    _signature: [u8; 4],
+    /// phys addr of MP config table
+    physaddr: u32,
+    /// 1
+    _length: u8,
+    /// [14]
+    _specrev: u8,
+    /// all bytes must add up to 0
+    _checksum: u8,
+    /// MP system config type
+    _ty: u8,
+    imcrp: u8,
+    _reserved: [u8; 3],
+}
+
+/// configuration table header
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct MpConf {
+    /// "PCMP"
+    signature: [u8; 4],
+    /// total table length
+    length: u16,
+    /// [14]
+    version: u8,
+    /// all bytes must add up to 0
+    _checksum: u8,
+    /// product id
+    _product: [u8; 20],
+    /// OEM table pointer
+    _oemtable: u32,
+    /// OEM table length
+    _oemlength: u16,
+    /// entry count
+    _entry: u16,
+    /// address of local APIC
+    lapicaddr: u32,
+    /// extended table length
+    _xlength: u16,
+    /// extended table checksum
+    _xchecksum: u8,
+    _reserved: u8,
+}
+
+/// processor table entry
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct MpProc {
+    /// entry type (0)
+    _ty: u8,
+    /// local APIC id
+    apicid: u8,
+    /// local APIC version
+    _version: u8,
+    /// CPU flags
+    _flags: u8,
+    /// CPU signature
+    _signature: [u8; 4],
+    /// feature flags from CPUID instruction
+    _feature: u32,
+    _reserved: [u8; 8],
+}
+
+/// I/O APIC table entry
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct MpIoapic {
+    /// entry type (2)
+    _ty: u8,
+    /// I/O APIC id
+    apicno: u8,
+    /// I/O APIC version
+    _version: u8,
+    /// I/O APIC flags
+    _flags: u8,
+    /// I/O APIC address
+    _addr: u32,
+}
+
+const _: () = assert!(size_of::<Mp>() == 16);
+const _: () = assert!(size_of::<MpConf>() == 44);
+const _: () = assert!(size_of::<MpProc>() == 20);
+const _: () = assert!(size_of::<MpIoapic>() == 8);
+
+// Table entry types
+/// One per processor
+const MPPROC: u8 = 0x00;
+/// One per bus
+const MPBUS: u8 = 0x01;
+/// One per I/O APIC
+const MPIOAPIC: u8 = 0x02;
+/// One per bus interrupt source
+const MPIOINTR: u8 = 0x03;
+/// One per system interrupt source
+const MPLINTR: u8 = 0x04;
+/// Size of every entry but [`MPPROC`]
+const ENTRY_SIZE: usize = 8;
+
+/// Bios Data Area
+const BDA: usize = 0x400;
+
+/// Bytes of physical memory at `[pa, pa + len)`, which must be mapped.
+fn phys_bytes(pa: usize, len: usize) -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts(ptr::with_exposed_provenance(p2v(pa)), len) }
+}
+
+/// Reads a `T` from the start of `bytes`, if there are enough.
+fn read<T: Copy>(bytes: &[u8]) -> Option<T> {
+    (bytes.len() >= size_of::<T>()).then(|| unsafe { ptr::read_unaligned(bytes.as_ptr().cast()) })
+}
+
+fn sum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, &b| sum.wrapping_add(b))
+}
+
+/// Look for an MP structure in the len bytes at physical address `a`.
+fn mpsearch1(a: usize, len: usize) -> Option<Mp> {
+    phys_bytes(a, len)
+        .chunks_exact(size_of::<Mp>())
+        .find(|mp| mp.starts_with(b"_MP_") && sum(mp) == 0)
+        .and_then(read)
+}
+
+/// Search for the MP Floating Pointer Structure, which according to the
+/// spec is in one of the following three locations:
+/// 1) in the first KB of the EBDA;
+/// 2) in the last KB of system base memory;
+/// 3) in the BIOS ROM between 0xF0000 and 0xFFFFF.
+fn mpsearch() -> Option<Mp> {
+    let bda = phys_bytes(BDA, 0x20);
+    let ebda = usize::from(u16::from_le_bytes([bda[0x0E], bda[0x0F]])) << 4;
+    if ebda != 0 {
+        if let Some(mp) = mpsearch1(ebda, 1024) {
+            return Some(mp);
+        }
+    } else {
+        let base_kb = usize::from(u16::from_le_bytes([bda[0x13], bda[0x14]]));
+        // Firmware that reports no base memory has no last KB to search.
+        if let Some(mp) = (base_kb * 1024)
+            .checked_sub(1024)
+            .and_then(|pa| mpsearch1(pa, 1024))
+        {
+            return Some(mp);
+        }
+    }
+    mpsearch1(0xF0000, 0x10000)
+}
+
+/// Search for an MP configuration table. For now,
+/// don't accept the default configurations (physaddr == 0).
+/// Check for correct signature, calculate the checksum and,
+/// if correct, check the version.
+/// To do: check extended table checksum.
+fn mpconfig() -> Option<(Mp, MpConf, &'static [u8])> {
+    let mp = mpsearch()?;
+    let pa = mp.physaddr as usize;
+    if pa == 0 || pa + size_of::<MpConf>() > PHYSTOP {
+        return None;
+    }
+    let conf: MpConf = read(phys_bytes(pa, size_of::<MpConf>()))?;
+    if &conf.signature != b"PCMP" || !matches!(conf.version, 1 | 4) {
+        return None;
+    }
+    let len = usize::from(conf.length);
+    if len < size_of::<MpConf>() || pa + len > PHYSTOP {
+        return None;
+    }
+    let table = phys_bytes(pa, len);
+    if sum(table) != 0 {
+        return None;
+    }
+    Some((mp, conf, &table[size_of::<MpConf>()..]))
+}
+
+/// Finds the CPUs and the I/O APIC, and returns the physical address of the
+/// local APICs.
+///
+/// Returns `None`, leaving the boot CPU the only one, without a usable
+/// configuration table.
+pub fn mpinit() -> Option<usize> {
+    let Some((mp, conf, entries)) = mpconfig() else {
+        debug!("mp: no MP configuration table");
+        return None;
+    };
+
+    let mut apicids = [0u8; NCPU];
+    let mut ncpu = 0;
+    let mut p = 0;
+    while let Some(&ty) = entries.get(p) {
+        match ty {
+            MPPROC => {
+                let Some(proc) = read::<MpProc>(&entries[p..]) else {
+                    break;
+                };
+                if ncpu < NCPU {
+                    // apicid may differ from ncpu
+                    apicids[ncpu] = proc.apicid;
+                    ncpu += 1;
+                } else {
+                    warn!("mp: ignoring cpu with apicid {}", proc.apicid);
+                }
+                p += size_of::<MpProc>();
+            }
+            MPIOAPIC => {
+                let Some(ioapic) = read::<MpIoapic>(&entries[p..]) else {
+                    break;
+                };
+                ioapic::set_id(ioapic.apicno);
+                p += size_of::<MpIoapic>();
+            }
+            MPBUS | MPIOINTR | MPLINTR => p += ENTRY_SIZE,
+            _ => {
+                warn!("mp: unknown config entry type {ty}");
+                return None;
+            }
+        }
+    }
+    if ncpu == 0 {
+        warn!("mp: no processor entries");
+        return None;
+    }
+
+    // SAFETY: the other CPUs have not been started.
+    unsafe { proc::set_cpus(&apicids[..ncpu]) };
+    info!("mp: {ncpu} cpus, lapic at {:#x}", conf.lapicaddr);
+
+    if mp.imcrp != 0 {
+        // Bochs doesn't support IMCR, so this doesn't run on Bochs.
+        // But it would on real hardware.
+        unsafe {
+            outb(0x22, 0x70); // Select IMCR
+            outb(0x23, inb(0x23) | 1); // Mask external interrupts.
+        }
+    }
+    Some(conf.lapicaddr as usize)
+}
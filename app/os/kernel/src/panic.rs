@@ -0,0 +1,74 @@
+//! Kernel panic handler (inherited xv6 console.c `panic`)
+//!
+//! Stops the other CPUs with an NMI, prints the message, its location, a
+//! symbolized backtrace and the spin locks this CPU holds over the serial
+//! line, then either halts or, with the `panic-exit-qemu` feature, exits QEMU
+//! with [`ExitCode::Panic`].
+
+use core::fmt::Write as _;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::backtrace::Backtrace;
+use crate::ksym::ReturnAddress;
+use crate::lapic;
+use crate::qemu::{ExitCode, qemu_exit};
+use crate::spinlock;
+use crate::uart::Uart;
+use crate::x86::{cli, hlt};
+
+/// Maximum number of frames printed.
+const MAX_FRAMES: usize = 16;
+
+/// Set by the first CPU to panic. Other CPUs freeze when its NMI arrives, or
+/// in the console as soon as they try to print.
+static PANICKED: AtomicBool = AtomicBool::new(false);
+
+pub fn panicked() -> bool {
+    PANICKED.load(Ordering::Relaxed)
+}
+
+/// Stops this CPU for good.
+pub fn freeze() -> ! {
+    unsafe { cli() };
+    loop {
+        hlt();
+    }
+}
+
+#[cfg(not(test))]
+#[panic_handler]
+pub fn panic(info: &core::panic::PanicInfo) -> ! {
+    unsafe { cli() };
+    if PANICKED.swap(true, Ordering::SeqCst) {
+        // Panicked while printing a panic; don't make it worse.
+        finish();
+    }
+    // Stop the others from running processes, or printing over the report.
+    lapic::nmi_others();
+
+    // Bypass the console lock: this CPU may hold it already.
+    let mut out = Uart;
+    let _ = writeln!(out, "\npanic: {}", info.message());
+    if let Some(location) = info.location() {
+        let _ = writeln!(out, "  at {location}");
+    }
+    let _ = writeln!(out, "backtrace:");
+    for (i, pc) in Backtrace::current().take(MAX_FRAMES).enumerate() {
+        let _ = writeln!(out, "  #{i:<2} {}", ReturnAddress(pc));
+    }
+    spinlock::for_each_held(|lock| {
+        let _ = writeln!(out, "holding {}, acquired at:", lock.name());
+        for (i, pc) in lock.pcs().enumerate() {
+            let _ = writeln!(out, "  #{i:<2} {}", ReturnAddress(pc));
+        }
+    });
+
+    finish();
+}
+
+fn finish() -> ! {
+    if cfg!(feature = "panic-exit-qemu") {
+        qemu_exit(ExitCode::Panic);
+    }
+    freeze();
+}
@@ -1,3 +1,14 @@
 //! inherited xv6 param.h
 
+/// Maximum number of processes
+pub const NPROC: usize = 64;
+/// Size of per-process kernel stack
 pub const K_STACK_SIZE: usize = 4096;
+/// Maximum number of CPUs
+pub const NCPU: usize = 8;
+/// Timer interrupts per second
+pub const TICK_HZ: u32 = 100;
+/// Open files per process
+pub const NOFILE: usize = 16;
+/// Maximum file path name
+pub const MAXPATH: usize = 128;
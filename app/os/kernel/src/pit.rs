@@ -0,0 +1,72 @@
+//! Intel 8253/8254 programmable interval timer
+//!
+//! Channel 0 drives the tick on machines without a local APIC timer; its
+//! output is IRQ 0. Channel 2 (the PC speaker channel) serves as a known
+//! clock to calibrate other timers against. Its output is gated through
+//! port 0x61 and the speaker stays off.
+
+use trap::IRQ_TIMER;
+
+use crate::params::TICK_HZ;
+use crate::picirq;
+use crate::x86::{inb, outb};
+
+/// Input clock of the counters.
+pub const PIT_HZ: u32 = 1_193_182;
+
+/// Counter 0 data port
+const CHANNEL0: u16 = 0x40;
+/// Counter 2 data port
+const CHANNEL2: u16 = 0x42;
+/// Mode/command port
+const MODE: u16 = 0x43;
+/// Keyboard controller port B: speaker gate and channel 2 output
+const PORT_B: u16 = 0x61;
+
+/// MODE: channel 0, low then high byte, mode 2 (rate generator)
+const MODE_CH0_RATEGEN: u8 = 0x34;
+/// MODE: channel 2, low then high byte, mode 0 (interrupt on terminal count)
+const MODE_CH2_ONESHOT: u8 = 0xB0;
+/// PORT_B: channel 2 gate
+const PORT_B_GATE2: u8 = 0x01;
+/// PORT_B: speaker data enable
+const PORT_B_SPEAKER: u8 = 0x02;
+/// PORT_B: channel 2 output (read only)
+const PORT_B_OUT2: u8 = 0x20;
+
+/// Makes channel 0 interrupt [`TICK_HZ`] times a second through the PIC.
+pub fn init_tick() {
+    let divisor = (PIT_HZ + TICK_HZ / 2) / TICK_HZ;
+    let [lo, hi] = (divisor as u16).to_le_bytes();
+    unsafe {
+        outb(MODE, MODE_CH0_RATEGEN);
+        outb(CHANNEL0, lo);
+        outb(CHANNEL0, hi);
+    }
+    picirq::enable(IRQ_TIMER);
+}
+
+/// Busy-waits for `ms` milliseconds (at most 54).
+pub fn delay_ms(ms: u32) {
+    delay_us(ms * 1000);
+}
+
+/// Busy-waits for `us` microseconds (at most 54925).
+pub fn delay_us(us: u32) {
+    let count = (u64::from(PIT_HZ) * u64::from(us) / 1_000_000).clamp(1, 0xFFFF) as u16;
+    let [lo, hi] = count.to_le_bytes();
+    unsafe {
+        // Gate low and speaker off while programming.
+        let port_b = inb(PORT_B) & !(PORT_B_GATE2 | PORT_B_SPEAKER);
+        outb(PORT_B, port_b);
+        outb(MODE, MODE_CH2_ONESHOT);
+        outb(CHANNEL2, lo);
+        outb(CHANNEL2, hi);
+        // Counting starts when the gate rises; OUT2 goes high at zero.
+        outb(PORT_B, port_b | PORT_B_GATE2);
+        while inb(PORT_B) & PORT_B_OUT2 == 0 {
+            core::hint::spin_loop();
+        }
+        outb(PORT_B, port_b);
+    }
+}
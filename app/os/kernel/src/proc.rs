@@ -0,0 +1,748 @@
+//! Per-CPU state, processes and scheduling (inherited xv6 proc.h/proc.c)
+//!
+//! Each CPU runs [`scheduler`] once set up. It picks a RUNNABLE process from
+//! the process table and [`swtch`]es to its kernel stack; the process
+//! switches back by calling [`sched`] (from [`yield_`] on a timer tick, from
+//! [`sleep`], or when it [`exit`]s). The process table lock is held across
+//! each switch, in both directions.
+//!
+//! The first process is made by [`userinit`]; every other one is created by
+//! [`fork`]. A process stays a ZOMBIE after [`exit`] until
+//! its parent collects its exit status with [`wait`].
+
+use core::cell::UnsafeCell;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicUsize, Ordering};
+
+use page::{PG_SIZE, PageDirectory};
+use segment::{NSEGS, SegmentDescriptor, SegmentSelector, TaskState};
+use trap::TrapFrame;
+
+use crate::file::File;
+use crate::kalloc::{kalloc, kfree};
+use crate::params::{K_STACK_SIZE, NCPU, NOFILE, NPROC};
+use crate::spinlock::{HeldLocks, RawSpinlock, SpinlockGuard, popcli, pushcli};
+use crate::swtch::swtch;
+use crate::x86::{FL_IF, hlt, read_eflags, sti};
+use crate::{lapic, vm};
+
+/// Per-CPU state
+pub struct Cpu {
+    /// Local APIC ID
+    pub apicid: u8,
+    /// Used by x86 to find stack for interrupt
+    pub ts: TaskState,
+    /// x86 global descriptor table
+    pub gdt: [SegmentDescriptor; NSEGS],
+    /// Depth of pushcli nesting.
+    pub ncli: u32,
+    /// Were interrupts enabled before pushcli?
+    pub intena: bool,
+    /// Spin locks this CPU holds, for the panic report
+    pub held: HeldLocks,
+    /// swtch() here to enter scheduler
+    pub scheduler: *mut Context,
+    /// The process running on this cpu or null
+    pub proc: *mut Proc,
+}
+
+impl Cpu {
+    const fn new() -> Self {
+        Self {
+            apicid: 0,
+            ts: TaskState::new(),
+            gdt: [SegmentDescriptor::NULL; NSEGS],
+            ncli: 0,
+            intena: false,
+            held: HeldLocks::new(),
+            scheduler: core::ptr::null_mut(),
+            proc: core::ptr::null_mut(),
+        }
+    }
+}
+
+/// Each entry is only touched by its own CPU, with interrupts disabled.
+struct Cpus([UnsafeCell<Cpu>; NCPU]);
+
+unsafe impl Sync for Cpus {}
+
+static CPUS: Cpus = Cpus([const { UnsafeCell::new(Cpu::new()) }; NCPU]);
+
+/// Number of entries of [`CPUS`] in use.
+static NCPUS: AtomicUsize = AtomicUsize::new(1);
+
+/// Whether each CPU is up, for the boot CPU to wait on in `startothers`.
+static STARTED: [AtomicBool; NCPU] = [const { AtomicBool::new(false) }; NCPU];
+
+/// Number of CPUs in the machine (at most [`NCPU`]).
+pub fn ncpu() -> usize {
+    NCPUS.load(Ordering::Relaxed)
+}
+
+/// Fills the CPU table with one CPU per local APIC ID, the boot CPU first.
+///
+/// # Safety
+/// Must be called before the other CPUs are started, and before any
+/// reference from [`mycpu`] is taken.
+pub unsafe fn set_cpus(apicids: &[u8]) {
+    assert!(matches!(apicids.len(), 1..=NCPU), "set_cpus: bad cpu count");
+    for (cpu, &apicid) in CPUS.0.iter().zip(apicids) {
+        unsafe { (*cpu.get()).apicid = apicid };
+    }
+    NCPUS.store(apicids.len(), Ordering::Relaxed);
+}
+
+/// Local APIC ID of CPU `cpu`.
+pub fn apicid(cpu: usize) -> u8 {
+    // SAFETY: `apicid` is only written before the other CPUs start.
+    unsafe { (&raw const (*CPUS.0[cpu].get()).apicid).read() }
+}
+
+/// Tells `startothers` this CPU is up.
+pub fn set_started() {
+    STARTED[cpuid()].store(true, Ordering::Release);
+}
+
+/// Whether CPU `cpu` has called [`set_started`].
+pub fn started(cpu: usize) -> bool {
+    STARTED[cpu].load(Ordering::Acquire)
+}
+
+/// Returns this CPU's state.
+///
+/// # Safety
+/// Interrupts must stay disabled while the returned reference is used, so the
+/// caller cannot be rescheduled to another CPU, and the caller must not hold
+/// another reference obtained from `mycpu`.
+///
+/// # Panics
+/// Panics if interrupts are enabled.
+pub unsafe fn mycpu() -> &'static mut Cpu {
+    unsafe { &mut *this_cpu() }
+}
+
+/// The process running on this CPU, if any.
+///
+/// Disable interrupts so that we are not rescheduled while reading proc
+/// from the cpu structure.
+///
+/// # Safety
+/// The fields of a process that are not protected by the process table lock
+/// belong to the process itself: the caller must be running as that process
+/// and must not hold another reference obtained from `myproc`.
+pub unsafe fn myproc() -> Option<&'static mut Proc> {
+    pushcli();
+    // SAFETY: interrupts are disabled.
+    let p = unsafe { mycpu() }.proc;
+    popcli();
+    unsafe { p.as_mut() }
+}
+
+/// ID of the process running on this CPU, if any.
+pub fn mypid() -> Option<u32> {
+    // SAFETY: `pid` does not change while the process runs.
+    unsafe { myproc() }.map(|p| p.pid)
+}
+
+/// Index of this CPU in the CPU table.
+///
+/// # Panics
+/// Panics if interrupts are enabled.
+pub fn cpuid() -> usize {
+    let base = CPUS.0.as_ptr().addr();
+    (this_cpu().addr() - base) / size_of::<UnsafeCell<Cpu>>()
+}
+
+fn this_cpu() -> *mut Cpu {
+    if read_eflags() & FL_IF != 0 {
+        panic!("mycpu called with interrupts enabled");
+    }
+    let cpus = &CPUS.0[..ncpu()];
+    // Only the boot CPU runs until the local APIC is mapped.
+    if cpus.len() == 1 || !lapic::mapped() {
+        return cpus[0].get();
+    }
+    // APIC IDs are not guaranteed to be contiguous. Maybe we should have
+    // a reverse map, or reserve a register to store &cpus[i].
+    let apicid = lapic::lapicid();
+    (0..cpus.len())
+        .find(|&cpu| u32::from(self::apicid(cpu)) == apicid)
+        .map(|cpu| cpus[cpu].get())
+        .unwrap_or_else(|| panic!("unknown apicid {apicid}"))
+}
+
+/// Saved registers for kernel context switches.
+///
+/// Don't need to save all the segment registers (%cs, etc), because they
+/// are constant across kernel contexts. Don't need to save %eax, %ecx,
+/// %edx, because the x86 convention is that the caller has saved them.
+/// Contexts are stored at the bottom of the stack they describe; the stack
+/// pointer is the address of the context. The layout of the context
+/// matches the layout of the stack in [`swtch`] at the "Switch stacks"
+/// comment. `eip` is not saved explicitly, but it is on the stack and
+/// allocproc() manipulates it.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct Context {
+    pub edi: u32,
+    pub esi: u32,
+    pub ebx: u32,
+    pub ebp: u32,
+    pub eip: u32,
+}
+
+/// Length of a process name, including the terminating NUL.
+const NAME_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcState {
+    Unused,
+    Embryo,
+    Sleeping,
+    Runnable,
+    Running,
+    Zombie,
+}
+
+/// Per-process state
+///
+/// `state`, `parent`, `chan` and `xstate` are protected by the process
+/// table lock; the other fields belong to the process itself once it is past
+/// EMBRYO, except `killed` which anyone may set.
+pub struct Proc {
+    /// Size of process memory (bytes)
+    pub sz: usize,
+    /// Page table
+    pub pgdir: Option<NonNull<PageDirectory>>,
+    /// Bottom of kernel stack for this process
+    pub kstack: Option<NonNull<u8>>,
+    /// Process state
+    pub state: ProcState,
+    /// Process ID
+    pub pid: u32,
+    /// Parent process, or null
+    pub parent: *mut Proc,
+    /// Trap frame for current syscall
+    pub tf: *mut TrapFrame,
+    /// swtch() here to run process
+    pub context: *mut Context,
+    /// If non-zero, sleeping on chan
+    pub chan: usize,
+    /// If set, have been killed
+    pub killed: AtomicBool,
+    /// Exit status to be returned to parent's wait
+    pub xstate: i32,
+    /// Open files
+    pub ofile: [Option<File>; NOFILE],
+    /// Process name (debugging), NUL-terminated
+    name: [u8; NAME_LEN],
+}
+
+impl Proc {
+    const fn new() -> Self {
+        Self {
+            sz: 0,
+            pgdir: None,
+            kstack: None,
+            state: ProcState::Unused,
+            pid: 0,
+            parent: core::ptr::null_mut(),
+            tf: core::ptr::null_mut(),
+            context: core::ptr::null_mut(),
+            chan: 0,
+            killed: AtomicBool::new(false),
+            xstate: 0,
+            ofile: [None; NOFILE],
+            name: [0; NAME_LEN],
+        }
+    }
+
+    /// Process name, for debugging.
+    pub fn name(&self) -> &str {
+        let len = self.name.iter().position(|&c| c == 0).unwrap_or(NAME_LEN);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+
+    /// Sets the name, truncated to fit (xv6 `safestrcpy`).
+    pub fn set_name(&mut self, name: &[u8]) {
+        let len = name.len().min(NAME_LEN - 1);
+        self.name = [0; NAME_LEN];
+        self.name[..len].copy_from_slice(&name[..len]);
+    }
+}
+
+/// The process table.
+struct Ptable {
+    lock: RawSpinlock,
+    proc: [UnsafeCell<Proc>; NPROC],
+}
+
+// SAFETY: see `Proc` for what `lock` protects.
+unsafe impl Sync for Ptable {}
+
+static PTABLE: Ptable = Ptable {
+    lock: RawSpinlock::new("ptable"),
+    proc: [const { UnsafeCell::new(Proc::new()) }; NPROC],
+};
+
+static NEXT_PID: AtomicU32 = AtomicU32::new(1);
+
+/// The first user process, which adopts orphans.
+static INITPROC: AtomicPtr<Proc> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Look in the process table for an UNUSED proc.
+/// If found, change state to EMBRYO and initialize
+/// state required to run in the kernel.
+/// Otherwise return `None`.
+fn allocproc() -> Option<&'static mut Proc> {
+    PTABLE.lock.acquire();
+    // SAFETY: the process table lock is held; an UNUSED proc belongs to no
+    // one else.
+    let Some(p) = PTABLE
+        .proc
+        .iter()
+        .map(|p| unsafe { &mut *p.get() })
+        .find(|p| p.state == ProcState::Unused)
+    else {
+        PTABLE.lock.release();
+        return None;
+    };
+    p.state = ProcState::Embryo;
+    p.pid = NEXT_PID.fetch_add(1, Ordering::Relaxed);
+    PTABLE.lock.release();
+
+    // Allocate kernel stack.
+    let Some(kstack) = kalloc() else {
+        p.state = ProcState::Unused;
+        return None;
+    };
+    p.killed.store(false, Ordering::Relaxed);
+    p.kstack = Some(kstack);
+    let mut sp = unsafe { kstack.as_ptr().add(K_STACK_SIZE) };
+
+    // Leave room for trap frame.
+    sp = unsafe { sp.sub(size_of::<TrapFrame>()) };
+    p.tf = sp.cast();
+
+    // Set up new context to start executing at forkret,
+    // which returns to trapret.
+    sp = unsafe { sp.sub(size_of::<u32>()) };
+    unsafe { sp.cast::<u32>().write(trap::trapret as usize as u32) };
+
+    sp = unsafe { sp.sub(size_of::<Context>()) };
+    p.context = sp.cast();
+    unsafe {
+        p.context.write(Context {
+            eip: forkret as usize as u32,
+            ..Context::default()
+        });
+    }
+
+    Some(p)
+}
+
+/// Set up first user process.
+///
+/// # Panics
+/// Panics if memory runs out.
+pub fn userinit() {
+    let Some(p) = allocproc() else {
+        panic!("userinit: out of memory");
+    };
+
+    INITPROC.store(p, Ordering::Relaxed);
+    let Some(pgdir) = vm::setupkvm() else {
+        panic!("userinit: out of memory?");
+    };
+    p.pgdir = Some(pgdir);
+    vm::inituvm(pgdir, user::initcode::code());
+    p.sz = PG_SIZE;
+
+    // SAFETY: the trap frame is at the top of the new kernel stack.
+    let tf = unsafe { &mut *p.tf };
+    *tf = TrapFrame::default();
+    tf.cs = SegmentSelector::USER_CODE.bits();
+    tf.ds = SegmentSelector::USER_DATA.bits();
+    tf.es = tf.ds;
+    tf.ss = tf.ds;
+    tf.eflags = FL_IF;
+    tf.esp = PG_SIZE as u32;
+    tf.eip = 0; // beginning of initcode
+
+    p.set_name(b"initcode");
+    // There is no console device file to open: init starts with it open.
+    p.ofile[..3].fill(Some(File::Console));
+
+    // this assignment to p->state lets other cores
+    // run this process. the acquire forces the above
+    // writes to be visible, and the lock is also needed
+    // because the assignment might not be atomic.
+    PTABLE.lock.acquire();
+    p.state = ProcState::Runnable;
+    PTABLE.lock.release();
+}
+
+/// Grow current process's memory by n bytes.
+/// Returns `None` on failure.
+///
+/// # Panics
+/// Panics if no process is running.
+pub fn growproc(n: i32) -> Option<()> {
+    // SAFETY: the running process's own fields.
+    let Some(curproc) = (unsafe { myproc() }) else {
+        panic!("growproc: no process");
+    };
+    let pgdir = curproc.pgdir?;
+
+    let mut sz = curproc.sz;
+    let delta = n.unsigned_abs() as usize;
+    if n > 0 {
+        sz = vm::allocuvm(pgdir, sz, sz.checked_add(delta)?)?;
+    } else if n < 0 {
+        sz = vm::deallocuvm(pgdir, sz, sz.checked_sub(delta)?);
+    }
+    curproc.sz = sz;
+    vm::switchuvm(curproc);
+    Some(())
+}
+
+/// Create a new process copying the current one as the parent.
+/// Sets up stack to return as if from system call.
+/// The child's fork returns 0; the parent's returns the child's pid, or
+/// `None` if the child could not be created.
+///
+/// # Panics
+/// Panics if no process is running.
+pub fn fork() -> Option<u32> {
+    // SAFETY: the running process's own fields.
+    let Some(curproc) = (unsafe { myproc() }) else {
+        panic!("fork: no process");
+    };
+
+    // Allocate process.
+    let np = allocproc()?;
+
+    // Copy process state from proc.
+    let Some(pgdir) = curproc
+        .pgdir
+        .and_then(|pgdir| vm::copyuvm(pgdir, curproc.sz))
+    else {
+        if let Some(kstack) = np.kstack.take() {
+            unsafe { kfree(kstack) };
+        }
+        np.state = ProcState::Unused;
+        return None;
+    };
+    np.pgdir = Some(pgdir);
+    np.sz = curproc.sz;
+    np.parent = curproc;
+    np.name = curproc.name;
+    np.ofile = curproc.ofile;
+    // SAFETY: both trap frames are at the top of their process's kernel stack.
+    unsafe {
+        *np.tf = *curproc.tf;
+        // Clear %eax so that fork returns 0 in the child.
+        (*np.tf).eax = 0;
+    }
+
+    let pid = np.pid;
+
+    PTABLE.lock.acquire();
+    np.state = ProcState::Runnable;
+    PTABLE.lock.release();
+
+    Some(pid)
+}
+
+/// Exit the current process with exit status `status`. Does not return.
+/// An exited process remains in the zombie state
+/// until its parent calls wait() to find out it exited.
+///
+/// # Panics
+/// Panics if no process is running, or if it is the init process.
+pub fn exit(status: i32) -> ! {
+    // SAFETY: the running process's own fields.
+    let Some(curproc) = (unsafe { myproc() }) else {
+        panic!("exit: no process");
+    };
+    let initproc = INITPROC.load(Ordering::Relaxed);
+    if core::ptr::eq(curproc, initproc) {
+        panic!("init exiting");
+    }
+
+    // Close all open files.
+    curproc.ofile = [None; NOFILE];
+
+    PTABLE.lock.acquire();
+
+    // Parent might be sleeping in wait().
+    wakeup1(curproc.parent.addr());
+
+    // Pass abandoned children to init.
+    for p in &PTABLE.proc {
+        // SAFETY: the process table lock is held.
+        let p = unsafe { &mut *p.get() };
+        if core::ptr::eq(p.parent, curproc) {
+            p.parent = initproc;
+            if p.state == ProcState::Zombie {
+                wakeup1(initproc.addr());
+            }
+        }
+    }
+
+    // Jump into the scheduler, never to return.
+    curproc.xstate = status;
+    curproc.state = ProcState::Zombie;
+    sched(curproc);
+    panic!("zombie exit");
+}
+
+/// Wait for a child process to exit and return its pid and exit status.
+/// Returns `None` if this process has no children, or has been killed.
+///
+/// # Panics
+/// Panics if no process is running.
+pub fn wait() -> Option<(u32, i32)> {
+    // SAFETY: the running process's own fields.
+    let Some(curproc) = (unsafe { myproc() }) else {
+        panic!("wait: no process");
+    };
+
+    PTABLE.lock.acquire();
+    loop {
+        // Scan through table looking for exited children.
+        let mut havekids = false;
+        for p in &PTABLE.proc {
+            // SAFETY: the process table lock is held, and a ZOMBIE never
+            // runs again.
+            let p = unsafe { &mut *p.get() };
+            if !core::ptr::eq(p.parent, curproc) {
+                continue;
+            }
+            havekids = true;
+            if p.state == ProcState::Zombie {
+                // Found one.
+                let found = (p.pid, p.xstate);
+                if let Some(kstack) = p.kstack.take() {
+                    unsafe { kfree(kstack) };
+                }
+                if let Some(pgdir) = p.pgdir.take() {
+                    unsafe { vm::freevm(pgdir) };
+                }
+                p.pid = 0;
+                p.parent = core::ptr::null_mut();
+                p.killed.store(false, Ordering::Relaxed);
+                p.state = ProcState::Unused;
+                PTABLE.lock.release();
+                return Some(found);
+            }
+        }
+
+        // No point waiting if we don't have any children.
+        if !havekids || curproc.killed.load(Ordering::Relaxed) {
+            PTABLE.lock.release();
+            return None;
+        }
+
+        // Wait for children to exit. (See wakeup1 call in exit.)
+        let chan = core::ptr::from_mut(curproc).addr();
+        sleep_locked(curproc, chan);
+    }
+}
+
+/// Per-CPU process scheduler.
+/// Each CPU calls scheduler() after setting itself up.
+/// Scheduler never returns. It loops, doing:
+///  - choose a process to run
+///  - swtch to start running that process
+///  - eventually that process transfers control
+///    via swtch back to the scheduler.
+pub fn scheduler() -> ! {
+    // SAFETY: interrupts are still disabled from boot; the scheduler only
+    // touches its CPU state with interrupts disabled from here on.
+    let c: *mut Cpu = unsafe { mycpu() };
+    unsafe { (*c).proc = core::ptr::null_mut() };
+
+    loop {
+        // Enable interrupts on this processor.
+        unsafe { sti() };
+
+        // Loop over process table looking for process to run.
+        PTABLE.lock.acquire();
+        let mut ran = false;
+        for p in &PTABLE.proc {
+            let p = p.get();
+            // SAFETY: the process table lock is held, and a RUNNABLE process
+            // is not running anywhere.
+            unsafe {
+                if (*p).state != ProcState::Runnable {
+                    continue;
+                }
+
+                // Switch to chosen process. It is the process's job
+                // to release ptable.lock and then reacquire it
+                // before jumping back to us.
+                (*c).proc = p;
+                vm::switchuvm(&*p);
+                (*p).state = ProcState::Running;
+
+                swtch(&raw mut (*c).scheduler, (*p).context);
+                vm::switchkvm();
+
+                // Process is done running for now.
+                // It should have changed its p->state before coming back.
+                (*c).proc = core::ptr::null_mut();
+            }
+            ran = true;
+        }
+        PTABLE.lock.release();
+
+        if !ran {
+            // Nothing to run: wait for an interrupt instead of spinning.
+            hlt();
+        }
+    }
+}
+
+/// Enter scheduler. Must hold only ptable.lock
+/// and have changed proc->state. Saves and restores
+/// intena because intena is a property of this
+/// kernel thread, not this CPU. It should
+/// be proc->intena and proc->ncli, but that would
+/// break in the few places where a lock is held but
+/// there's no process.
+fn sched(p: &mut Proc) {
+    if !PTABLE.lock.holding() {
+        panic!("sched ptable.lock");
+    }
+    if unsafe { mycpu() }.ncli != 1 {
+        panic!("sched locks");
+    }
+    if p.state == ProcState::Running {
+        panic!("sched running");
+    }
+    if read_eflags() & FL_IF != 0 {
+        panic!("sched interruptible");
+    }
+    let intena = unsafe { mycpu() }.intena;
+    // SAFETY: the scheduler context was saved by this CPU's `scheduler`.
+    unsafe { swtch(&raw mut p.context, mycpu().scheduler) };
+    // This may be another CPU now.
+    unsafe { mycpu() }.intena = intena;
+}
+
+/// Give up the CPU for one scheduling round.
+pub fn yield_() {
+    PTABLE.lock.acquire();
+    // SAFETY: the process table lock is held.
+    if let Some(p) = unsafe { myproc() } {
+        p.state = ProcState::Runnable;
+        sched(p);
+    }
+    PTABLE.lock.release();
+}
+
+/// A fork child's very first scheduling by scheduler()
+/// will swtch here. "Return" to user space.
+extern "C" fn forkret() {
+    // Still holding ptable.lock from scheduler.
+    PTABLE.lock.release();
+
+    // Return to "caller", actually trapret (see allocproc).
+}
+
+/// Atomically release the lock held by `guard` and sleep on `chan`.
+/// Reacquires the lock when awakened.
+///
+/// `chan` is only compared, never dereferenced: it is usually the address
+/// of whatever the sleeper is waiting for.
+///
+/// # Panics
+/// Panics if no process is running. In debug builds, also panics if this
+/// CPU holds another spin lock than the one passed: it would stay held
+/// while asleep.
+pub fn sleep<'a, C, T>(chan: *const C, mut guard: SpinlockGuard<'a, T>) -> SpinlockGuard<'a, T> {
+    debug_assert!(
+        SpinlockGuard::is_only_lock(&guard),
+        "sleep: holding other locks"
+    );
+    // SAFETY: a lock is held, so interrupts are disabled.
+    let Some(p) = (unsafe { myproc() }) else {
+        panic!("sleep");
+    };
+
+    // Must acquire ptable.lock in order to
+    // change p->state and then call sched.
+    // Once we hold ptable.lock, we can be
+    // guaranteed that we won't miss any wakeup
+    // (wakeup runs with ptable.lock locked),
+    // so it's okay to release lk.
+    PTABLE.lock.acquire();
+    SpinlockGuard::unlocked(&mut guard, || {
+        sleep_locked(p, chan.addr());
+
+        // Reacquire original lock, once this returns.
+        PTABLE.lock.release();
+    });
+    guard
+}
+
+/// [`sleep`] for callers that already hold the ptable lock.
+fn sleep_locked(p: &mut Proc, chan: usize) {
+    // Go to sleep.
+    p.chan = chan;
+    p.state = ProcState::Sleeping;
+
+    sched(p);
+
+    // Tidy up.
+    p.chan = 0;
+}
+
+/// Wake up all processes sleeping on chan.
+/// The ptable lock must be held.
+fn wakeup1(chan: usize) {
+    for p in &PTABLE.proc {
+        // SAFETY: the process table lock is held.
+        let p = unsafe { &mut *p.get() };
+        if p.state == ProcState::Sleeping && p.chan == chan {
+            p.state = ProcState::Runnable;
+        }
+    }
+}
+
+/// Wake up all processes sleeping on `chan`.
+pub fn wakeup<C>(chan: *const C) {
+    PTABLE.lock.acquire();
+    wakeup1(chan.addr());
+    PTABLE.lock.release();
+}
+
+/// Kill the process with the given pid.
+/// Process won't exit until it returns
+/// to user space (see the trap return hook in traps.rs).
+/// Returns whether there was such a process.
+pub fn kill(pid: u32) -> bool {
+    PTABLE.lock.acquire();
+    for p in &PTABLE.proc {
+        // SAFETY: the process table lock is held.
+        let p = unsafe { &mut *p.get() };
+        if p.pid == pid && p.state != ProcState::Unused {
+            p.killed.store(true, Ordering::Relaxed);
+            // Wake process from sleep if necessary.
+            if p.state == ProcState::Sleeping {
+                p.state = ProcState::Runnable;
+            }
+            PTABLE.lock.release();
+            return true;
+        }
+    }
+    PTABLE.lock.release();
+    false
+}
+
+/// Whether the running process has been killed.
+pub fn killed() -> bool {
+    // SAFETY: only reads the atomic `killed`.
+    unsafe { myproc() }.is_some_and(|p| p.killed.load(Ordering::Relaxed))
+}
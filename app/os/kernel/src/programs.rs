@@ -0,0 +1,17 @@
+//! Programs built into the kernel
+//!
+//! There is no file system, so [`exec`](crate::exec::exec) finds programs
+//! here by path. The build script embeds every file of the directory named by
+//! the `XV6_USER_PROGRAMS` environment variable as `/<file name>`; without it
+//! there are none.
+
+/// `(path, ELF image)` of each program.
+static PROGRAMS: &[(&str, &[u8])] = include!(concat!(env!("OUT_DIR"), "/programs.rs"));
+
+/// The ELF image of the program at `path`.
+pub fn lookup(path: &[u8]) -> Option<&'static [u8]> {
+    PROGRAMS
+        .iter()
+        .find(|(name, _)| name.as_bytes() == path)
+        .map(|&(_, image)| image)
+}
@@ -0,0 +1,35 @@
+//! QEMU `isa-debug-exit` device.
+//!
+//! Run QEMU with `-device isa-debug-exit,iobase=0x501,iosize=0x02`. Writing
+//! `code` to the port terminates QEMU with exit status `(code << 1) | 1`, so
+//! a kernel run can report its result to scripts.
+//!
+//! Without the device, QEMU is turned off through ACPI instead, which it
+//! reports as a normal exit.
+
+use crate::acpi;
+use crate::x86::{cli, hlt, outb};
+
+/// I/O port of the `isa-debug-exit` device.
+const DEBUG_EXIT_PORT: u16 = 0x501;
+
+/// Value written to [`DEBUG_EXIT_PORT`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ExitCode {
+    /// QEMU exits with status 35.
+    Panic = 0x11,
+}
+
+/// Terminates QEMU. Falls back to an ACPI power-off if the debug exit device
+/// is absent, and halts forever if that fails too.
+pub fn qemu_exit(code: ExitCode) -> ! {
+    unsafe {
+        outb(DEBUG_EXIT_PORT, code as u8);
+        cli();
+    }
+    acpi::poweroff();
+    loop {
+        hlt();
+    }
+}
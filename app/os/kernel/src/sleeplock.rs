@@ -0,0 +1,105 @@
+//! Long-term locks for processes (inherited xv6 sleeplock.c)
+//!
+//! A [`SleepLock<T>`] may be held across disk I/O: waiting for it puts the
+//! caller to [`sleep`] instead of spinning, and holding it keeps interrupts
+//! enabled. It can only be used where sleeping is allowed, so never from an
+//! interrupt handler or while holding a spin lock.
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+use crate::proc::{self, sleep, wakeup};
+use crate::spinlock::Spinlock;
+
+/// Who holds a [`SleepLock`].
+struct Holder {
+    /// Is the lock held?
+    locked: bool,
+    /// Process holding lock, or `None` outside of any process
+    pid: Option<u32>,
+}
+
+/// Mutual exclusion lock that sleeps until the lock is free.
+pub struct SleepLock<T> {
+    /// Spinlock protecting this sleep lock
+    lk: Spinlock<Holder>,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: Access to `data` is serialized by `lk.locked`.
+unsafe impl<T: Send> Sync for SleepLock<T> {}
+
+impl<T> SleepLock<T> {
+    pub const fn new(name: &'static str, data: T) -> Self {
+        Self {
+            lk: Spinlock::new(
+                name,
+                Holder {
+                    locked: false,
+                    pid: None,
+                },
+            ),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Acquires the lock, sleeping until it is free.
+    pub fn lock(&self) -> SleepLockGuard<'_, T> {
+        let mut holder = self.lk.lock();
+        while holder.locked {
+            holder = sleep(self, holder);
+        }
+        holder.locked = true;
+        holder.pid = proc::mypid();
+        SleepLockGuard { lock: self }
+    }
+
+    /// Whether the calling process holds the lock. Never true outside of a
+    /// process.
+    pub fn holding(&self) -> bool {
+        let holder = self.lk.lock();
+        holder.locked && proc::mypid().is_some_and(|pid| holder.pid == Some(pid))
+    }
+}
+
+/// Checks [`SleepLock::holding`] outside of any process, where nobody can
+/// hold a sleep lock: the boot CPU before the first process runs.
+pub fn selftest() {
+    assert!(proc::mypid().is_none(), "sleeplock selftest: in a process");
+    let lock = SleepLock::new("selftest", 0);
+    let mut guard = lock.lock();
+    *guard += 1;
+    assert!(!lock.holding(), "held outside of any process");
+    drop(guard);
+    assert!(!lock.holding());
+    assert!(!lock.lk.lock().locked, "not released");
+    assert_eq!(*lock.lock(), 1);
+}
+
+/// Releases the lock on drop.
+pub struct SleepLockGuard<'a, T> {
+    lock: &'a SleepLock<T>,
+}
+
+impl<T> Deref for SleepLockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for SleepLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for SleepLockGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut holder = self.lock.lk.lock();
+        holder.locked = false;
+        holder.pid = None;
+        wakeup(self.lock);
+    }
+}
@@ -0,0 +1,277 @@
+//! Mutual exclusion spin locks (inherited xv6 spinlock.c)
+//!
+//! [`Spinlock<T>`] wraps the data it protects and hands out a guard that
+//! releases it on drop. The lock itself, [`RawSpinlock`], can also be used
+//! alone where a lock is acquired and released by different code paths.
+//!
+//! Each lock has a name and remembers the CPU and call stack that acquired
+//! it. Acquiring a lock this CPU already holds, or releasing one it does not,
+//! panics; the panic report lists the locks the panicking CPU holds.
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::backtrace::Backtrace;
+use crate::proc::{cpuid, mycpu};
+use crate::x86::{FL_IF, cli, read_eflags, sti};
+
+/// Number of return addresses recorded when a lock is acquired.
+const NPCS: usize = 10;
+
+/// Locks of one CPU listed by the panic report. Further ones still work,
+/// they just do not show up there.
+const NHELD: usize = 8;
+
+/// [`RawSpinlock::cpu`] of a free lock.
+const NO_CPU: usize = usize::MAX;
+
+/// Mutual exclusion lock, without the data it protects.
+pub struct RawSpinlock {
+    /// Is the lock held?
+    locked: AtomicBool,
+
+    // For debugging:
+    /// Name of lock.
+    name: &'static str,
+    /// Index of the CPU holding the lock, or [`NO_CPU`].
+    cpu: AtomicUsize,
+    /// The call stack (an array of program counters) that locked the lock.
+    pcs: UnsafeCell<[usize; NPCS]>,
+}
+
+// SAFETY: `pcs` is only written by the CPU holding the lock.
+unsafe impl Sync for RawSpinlock {}
+
+impl RawSpinlock {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            name,
+            cpu: AtomicUsize::new(NO_CPU),
+            pcs: UnsafeCell::new([0; NPCS]),
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Acquires the lock, spinning until it is free.
+    ///
+    /// Interrupts stay disabled on this CPU until the lock is released, so
+    /// an interrupt handler taking the same lock cannot deadlock against us.
+    ///
+    /// # Panics
+    /// Panics if this CPU already holds the lock.
+    pub fn acquire(&self) {
+        pushcli(); // disable interrupts to avoid deadlock.
+        if self.holding() {
+            panic!("acquire {}", self.name);
+        }
+
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+
+        // Record info about lock acquisition for debugging.
+        self.cpu.store(cpuid(), Ordering::Relaxed);
+        let mut pcs = [0; NPCS];
+        for (slot, pc) in pcs.iter_mut().zip(Backtrace::current()) {
+            *slot = pc;
+        }
+        unsafe { *self.pcs.get() = pcs };
+        // SAFETY: interrupts are disabled, and no other reference to this
+        // CPU's state is live in this function.
+        unsafe { mycpu() }.held.push(self);
+    }
+
+    /// Releases the lock.
+    ///
+    /// # Panics
+    /// Panics if this CPU does not hold the lock.
+    pub fn release(&self) {
+        if !self.holding() {
+            panic!("release {}", self.name);
+        }
+
+        unsafe { mycpu() }.held.remove(self);
+        unsafe { *self.pcs.get() = [0; NPCS] };
+        self.cpu.store(NO_CPU, Ordering::Relaxed);
+
+        self.locked.store(false, Ordering::Release);
+        popcli();
+    }
+
+    /// Whether this CPU is holding the lock.
+    pub fn holding(&self) -> bool {
+        pushcli();
+        let r = self.locked.load(Ordering::Relaxed) && self.cpu.load(Ordering::Relaxed) == cpuid();
+        popcli();
+        r
+    }
+
+    /// Return addresses of the call stack that acquired the lock, innermost
+    /// first. Only meaningful on the CPU holding it.
+    pub fn pcs(&self) -> impl Iterator<Item = usize> {
+        let pcs = unsafe { *self.pcs.get() };
+        pcs.into_iter().take_while(|&pc| pc != 0)
+    }
+}
+
+/// Mutual exclusion lock that busy-waits until the lock is free.
+pub struct Spinlock<T> {
+    lock: RawSpinlock,
+    data: UnsafeCell<T>,
+}
+
+// SAFETY: Access to `data` is serialized by `lock`.
+unsafe impl<T: Send> Sync for Spinlock<T> {}
+
+impl<T> Spinlock<T> {
+    pub const fn new(name: &'static str, data: T) -> Self {
+        Self {
+            lock: RawSpinlock::new(name),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Acquires the lock, spinning until it is free. See
+    /// [`RawSpinlock::acquire`].
+    pub fn lock(&self) -> SpinlockGuard<'_, T> {
+        self.lock.acquire();
+        SpinlockGuard {
+            lock: self,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+/// Releases the lock on drop.
+pub struct SpinlockGuard<'a, T> {
+    lock: &'a Spinlock<T>,
+    /// The lock belongs to the CPU that acquired it.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T> SpinlockGuard<'_, T> {
+    /// Releases the lock while `f` runs, and acquires it again.
+    pub fn unlocked<U>(guard: &mut Self, f: impl FnOnce() -> U) -> U {
+        guard.lock.lock.release();
+        let r = f();
+        guard.lock.lock.acquire();
+        r
+    }
+
+    /// Whether the guarded lock is the only one this CPU holds, with no
+    /// other [`pushcli`] in effect.
+    pub fn is_only_lock(guard: &Self) -> bool {
+        // SAFETY: interrupts are disabled while a lock is held.
+        unsafe { mycpu() }.ncli == 1 && guard.lock.lock.holding()
+    }
+}
+
+impl<T> Deref for SpinlockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinlockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for SpinlockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.lock.release();
+    }
+}
+
+/// Locks held by one CPU, oldest first.
+#[derive(Clone, Copy)]
+pub struct HeldLocks {
+    locks: [*const RawSpinlock; NHELD],
+    len: usize,
+}
+
+impl HeldLocks {
+    pub const fn new() -> Self {
+        Self {
+            locks: [core::ptr::null(); NHELD],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, lock: &RawSpinlock) {
+        if let Some(slot) = self.locks.get_mut(self.len) {
+            *slot = lock;
+            self.len += 1;
+        }
+    }
+
+    /// Locks need not be released in the reverse order of acquisition.
+    fn remove(&mut self, lock: &RawSpinlock) {
+        let held = &mut self.locks[..self.len];
+        if let Some(i) = held.iter().rposition(|&held| core::ptr::eq(held, lock)) {
+            held[i..].rotate_left(1);
+            self.len -= 1;
+        }
+    }
+}
+
+/// Calls `f` with each lock this CPU holds, oldest first. For the panic
+/// report.
+pub fn for_each_held(mut f: impl FnMut(&RawSpinlock)) {
+    pushcli();
+    // SAFETY: interrupts are disabled; the list is copied out at once.
+    let held = unsafe { mycpu() }.held;
+    for &lock in &held.locks[..held.len] {
+        // SAFETY: a lock stays in the list only while it is held, so it is
+        // still alive.
+        f(unsafe { &*lock });
+    }
+    popcli();
+}
+
+/// Disables interrupts, like `cli`, but nested: it takes as many [`popcli`]
+/// as `pushcli` to undo it, and if interrupts were off to begin with they
+/// stay off.
+pub fn pushcli() {
+    let eflags = read_eflags();
+    unsafe { cli() };
+    // SAFETY: interrupts are disabled.
+    let cpu = unsafe { mycpu() };
+    if cpu.ncli == 0 {
+        cpu.intena = eflags & FL_IF != 0;
+    }
+    cpu.ncli += 1;
+}
+
+/// Undoes one [`pushcli`].
+///
+/// # Panics
+/// Panics if interrupts are enabled, or on a `popcli` without a matching
+/// `pushcli`.
+pub fn popcli() {
+    if read_eflags() & FL_IF != 0 {
+        panic!("popcli - interruptible");
+    }
+    // SAFETY: interrupts are disabled.
+    let cpu = unsafe { mycpu() };
+    let Some(ncli) = cpu.ncli.checked_sub(1) else {
+        panic!("popcli");
+    };
+    cpu.ncli = ncli;
+    if ncli == 0 && cpu.intena {
+        unsafe { sti() };
+    }
+}
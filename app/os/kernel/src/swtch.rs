@@ -0,0 +1,49 @@
+//! Kernel context switch (inherited xv6 swtch.S)
+
+use core::arch::global_asm;
+
+use crate::proc::Context;
+
+unsafe extern "C" {
+    /// Saves the current registers on the stack, creating a [`Context`], and
+    /// saves its address in `*old`. Switches stacks to `new` and pops
+    /// previously saved registers.
+    ///
+    /// # Safety
+    /// `new` must be a context saved by `swtch`, or built to look like one,
+    /// on a stack that stays valid.
+    pub fn swtch(old: *mut *mut Context, new: *mut Context);
+}
+
+// Only the callee-saved registers are saved; the caller-saved ones are
+// already on the stack of whoever called swtch, and %eip is the return
+// address pushed by that call.
+global_asm!(
+    r#"
+    .text
+    .globl swtch
+    .type swtch, @function
+swtch:
+    movl 4(%esp), %eax
+    movl 8(%esp), %edx
+
+    # Save old callee-saved registers
+    pushl %ebp
+    pushl %ebx
+    pushl %esi
+    pushl %edi
+
+    # Switch stacks
+    movl %esp, (%eax)
+    movl %edx, %esp
+
+    # Load new callee-saved registers
+    popl %edi
+    popl %esi
+    popl %ebx
+    popl %ebp
+    ret
+    .size swtch, . - swtch
+    "#,
+    options(att_syntax)
+);
@@ -0,0 +1,85 @@
+//! System call entry (inherited xv6 syscall.c)
+//!
+//! [`syscall`] handles `int $T_SYSCALL` from user space: it runs the handler
+//! the [`Table`] has for the number in `%eax`, and returns its result there.
+
+use syscall::{Args, Syscall, Table, UserMemory};
+use trap::TrapFrame;
+
+use crate::proc::{Proc, exit, killed, myproc};
+use crate::{sysfile, sysproc, vm};
+
+static SYSCALLS: Table<Proc> = Table::new()
+    .with(Syscall::Fork, sysproc::sys_fork)
+    .with(Syscall::Exit, sysproc::sys_exit)
+    .with(Syscall::Wait, sysproc::sys_wait)
+    .with(Syscall::Pipe, sysfile::sys_unsupported)
+    .with(Syscall::Read, sysfile::sys_read)
+    .with(Syscall::Kill, sysproc::sys_kill)
+    .with(Syscall::Exec, sysfile::sys_exec)
+    .with(Syscall::Fstat, sysfile::sys_unsupported)
+    .with(Syscall::Chdir, sysfile::sys_unsupported)
+    .with(Syscall::Dup, sysfile::sys_dup)
+    .with(Syscall::Getpid, sysproc::sys_getpid)
+    .with(Syscall::Sbrk, sysproc::sys_sbrk)
+    .with(Syscall::Sleep, sysproc::sys_sleep)
+    .with(Syscall::Uptime, sysproc::sys_uptime)
+    .with(Syscall::Open, sysfile::sys_unsupported)
+    .with(Syscall::Write, sysfile::sys_write)
+    .with(Syscall::Mknod, sysfile::sys_unsupported)
+    .with(Syscall::Unlink, sysfile::sys_unsupported)
+    .with(Syscall::Link, sysfile::sys_unsupported)
+    .with(Syscall::Mkdir, sysfile::sys_unsupported)
+    .with(Syscall::Close, sysfile::sys_close);
+
+/// User memory is reached through the process's page table.
+impl UserMemory for Proc {
+    fn size(&self) -> usize {
+        self.sz
+    }
+
+    fn read(&self, addr: usize, buf: &mut [u8]) {
+        // Every page below `sz` is mapped.
+        if self
+            .pgdir
+            .and_then(|pgdir| vm::copyin(pgdir, addr, buf))
+            .is_none()
+        {
+            panic!("copyin: {addr:#x} is not mapped");
+        }
+    }
+}
+
+/// Arguments of the system call `p` is making.
+pub fn args(p: &Proc) -> Args<'_, Proc> {
+    // SAFETY: `tf` is the frame of the running system call.
+    Args::new(p, unsafe { (*p.tf).esp } as usize)
+}
+
+/// The `int $T_SYSCALL` handler.
+///
+/// # Panics
+/// Panics if no process is running.
+pub fn syscall(tf: &mut TrapFrame) {
+    if killed() {
+        exit(-1);
+    }
+    // SAFETY: system calls run as the calling process.
+    let Some(curproc) = (unsafe { myproc() }) else {
+        panic!("syscall: no process");
+    };
+
+    let num = tf.eax;
+    tf.eax = match SYSCALLS.dispatch(num, curproc) {
+        Some(ret) => ret as u32,
+        None => {
+            println!(
+                "{} {}: unknown sys call {}",
+                curproc.pid,
+                curproc.name(),
+                num
+            );
+            u32::MAX
+        }
+    };
+}
@@ -0,0 +1,104 @@
+//! File system calls (inherited xv6 sysfile.c)
+//!
+//! There is no file system: descriptors only refer to the console (see
+//! file.rs), and `exec` only finds the programs built into the kernel. The
+//! calls on paths and pipes fail.
+
+use syscall::MAXARG;
+
+use crate::exec::{UserStr, exec};
+use crate::file::File;
+use crate::params::MAXPATH;
+use crate::proc::Proc;
+use crate::syscalls::args;
+
+/// Allocate a file descriptor for the given file.
+fn fdalloc(p: &mut Proc, f: File) -> Option<usize> {
+    let fd = p.ofile.iter().position(Option::is_none)?;
+    p.ofile[fd] = Some(f);
+    Some(fd)
+}
+
+pub fn sys_dup(p: &mut Proc) -> i32 {
+    let Ok((_, &f)) = args(p).argfd(0, &p.ofile) else {
+        return -1;
+    };
+    fdalloc(p, f).map_or(-1, |fd| fd as i32)
+}
+
+pub fn sys_read(p: &mut Proc) -> i32 {
+    let args = args(p);
+    let Ok((_, &f)) = args.argfd(0, &p.ofile) else {
+        return -1;
+    };
+    let Ok(Ok(n)) = args.argint(2).map(usize::try_from) else {
+        return -1;
+    };
+    let Ok(addr) = args.argptr(1, n) else {
+        return -1;
+    };
+    let Some(pgdir) = p.pgdir else {
+        return -1;
+    };
+    f.read(pgdir, addr, n).map_or(-1, |n| n as i32)
+}
+
+pub fn sys_write(p: &mut Proc) -> i32 {
+    let args = args(p);
+    let Ok((_, &f)) = args.argfd(0, &p.ofile) else {
+        return -1;
+    };
+    let Ok(Ok(n)) = args.argint(2).map(usize::try_from) else {
+        return -1;
+    };
+    let Ok(addr) = args.argptr(1, n) else {
+        return -1;
+    };
+    f.write(p, addr, n).map_or(-1, |n| n as i32)
+}
+
+pub fn sys_close(p: &mut Proc) -> i32 {
+    let Ok((fd, _)) = args(p).argfd(0, &p.ofile) else {
+        return -1;
+    };
+    p.ofile[fd] = None;
+    0
+}
+
+pub fn sys_exec(p: &mut Proc) -> i32 {
+    let args = args(p);
+    let mut path = [0; MAXPATH];
+    let Ok(path) = args.argstr(0, &mut path) else {
+        return -1;
+    };
+    let Ok(uargv) = args.argint(1) else {
+        return -1;
+    };
+    let uargv = uargv as u32 as usize;
+
+    let mut argv = [UserStr { addr: 0, len: 0 }; MAXARG];
+    let mut argc = 0;
+    loop {
+        if argc >= argv.len() {
+            return -1;
+        }
+        let Ok(uarg) = args.fetchint(uargv.wrapping_add(4 * argc)) else {
+            return -1;
+        };
+        if uarg == 0 {
+            break;
+        }
+        let addr = uarg as u32 as usize;
+        let Ok(len) = args.fetchstrlen(addr) else {
+            return -1;
+        };
+        argv[argc] = UserStr { addr, len };
+        argc += 1;
+    }
+    exec(path, &argv[..argc]).map_or(-1, |()| 0)
+}
+
+/// Pipes and the calls on paths: there is no file system to work on.
+pub fn sys_unsupported(_p: &mut Proc) -> i32 {
+    -1
+}
@@ -0,0 +1,89 @@
+//! Process system calls (inherited xv6 sysproc.c)
+
+use core::sync::atomic::Ordering;
+
+use crate::proc::{self, Proc, sleep};
+use crate::syscalls::args;
+use crate::traps::TICKS;
+use crate::vm;
+
+pub fn sys_fork(_p: &mut Proc) -> i32 {
+    proc::fork().map_or(-1, |pid| pid as i32)
+}
+
+pub fn sys_exit(p: &mut Proc) -> i32 {
+    let Ok(status) = args(p).argint(0) else {
+        return -1;
+    };
+    proc::exit(status);
+}
+
+/// `wait(int *status)`: the exit status is stored unless `status` is null.
+pub fn sys_wait(p: &mut Proc) -> i32 {
+    let args = args(p);
+    let Ok(addr) = args.argint(0) else {
+        return -1;
+    };
+    if addr != 0 && args.argptr(0, size_of::<i32>()).is_err() {
+        return -1;
+    }
+    let Some((pid, xstate)) = proc::wait() else {
+        return -1;
+    };
+    if addr != 0 {
+        let stored = p
+            .pgdir
+            .and_then(|pgdir| vm::copyout(pgdir, addr as u32 as usize, &xstate.to_le_bytes()));
+        if stored.is_none() {
+            return -1;
+        }
+    }
+    pid as i32
+}
+
+pub fn sys_kill(p: &mut Proc) -> i32 {
+    let Ok(pid) = args(p).argint(0) else {
+        return -1;
+    };
+    if u32::try_from(pid).is_ok_and(proc::kill) {
+        0
+    } else {
+        -1
+    }
+}
+
+pub fn sys_getpid(p: &mut Proc) -> i32 {
+    p.pid as i32
+}
+
+pub fn sys_sbrk(p: &mut Proc) -> i32 {
+    let Ok(n) = args(p).argint(0) else {
+        return -1;
+    };
+    let addr = p.sz;
+    if proc::growproc(n).is_none() {
+        return -1;
+    }
+    addr as i32
+}
+
+pub fn sys_sleep(p: &mut Proc) -> i32 {
+    let Ok(Ok(n)) = args(p).argint(0).map(u32::try_from) else {
+        return -1;
+    };
+    let mut ticks = TICKS.lock();
+    let ticks0 = *ticks;
+    while ticks.wrapping_sub(ticks0) < n {
+        if p.killed.load(Ordering::Relaxed) {
+            return -1;
+        }
+        ticks = sleep(&raw const TICKS, ticks);
+    }
+    0
+}
+
+/// Returns how many clock tick interrupts have occurred
+/// since start.
+pub fn sys_uptime(_p: &mut Proc) -> i32 {
+    *TICKS.lock() as i32
+}
@@ -0,0 +1,138 @@
+//! Kernel trap handlers (inherited xv6 trap.c)
+
+use core::sync::atomic::Ordering;
+
+use trap::{
+    IRQ_COM1, IRQ_IDE, IRQ_KBD, IRQ_SPURIOUS, IRQ_TIMER, PageFault, T_IRQ0, T_NMI, T_SYSCALL,
+    TrapFrame,
+};
+
+use crate::proc::{ProcState, cpuid, exit, killed, myproc, wakeup, yield_};
+use crate::spinlock::Spinlock;
+use crate::x86::inb;
+use crate::{kbd, lapic, panic, syscalls, uart};
+
+/// Timer interrupts since boot, counted on CPU 0.
+pub static TICKS: Spinlock<u32> = Spinlock::new("time", 0);
+
+/// IDE status register of the primary channel
+const IDE_STATUS: u16 = 0x1F7;
+
+/// Vector of hardware interrupt `irq`.
+const fn irq_vector(irq: u32) -> u8 {
+    (T_IRQ0 + irq) as u8
+}
+
+pub fn init() {
+    trap::set_return_hook(trap_return);
+    trap::set_page_fault_handler(page_fault);
+    trap::set_user_handler(|tf| misbehaved(tf, trap::rcr2()));
+    trap::register(T_SYSCALL as u8, syscalls::syscall);
+    trap::register(T_NMI as u8, nmi);
+    trap::register(irq_vector(IRQ_TIMER), timer);
+    trap::register(irq_vector(IRQ_IDE), ide);
+    // Bochs generates spurious IDE1 interrupts.
+    trap::register(irq_vector(IRQ_IDE + 1), |_| {});
+    trap::register(irq_vector(IRQ_KBD), |_| {
+        kbd::intr();
+        lapic::eoi();
+    });
+    trap::register(irq_vector(IRQ_COM1), |_| {
+        uart::intr();
+        lapic::eoi();
+    });
+    trap::register(irq_vector(7), spurious);
+    trap::register(irq_vector(IRQ_SPURIOUS), spurious);
+}
+
+fn timer(_tf: &mut TrapFrame) {
+    if cpuid() == 0 {
+        let mut ticks = TICKS.lock();
+        *ticks += 1;
+        wakeup(&raw const TICKS);
+    }
+    lapic::eoi();
+}
+
+/// Runs after every trap handler.
+fn trap_return(tf: &mut TrapFrame) {
+    // Force process exit if it has been killed and is in user space.
+    // (If it is still executing in the kernel, let it keep running
+    // until it gets to the regular system call return.)
+    if killed() && tf.from_user() {
+        exit(-1);
+    }
+
+    // Force process to give up CPU on clock tick.
+    // SAFETY: interrupts are disabled until the trap returns.
+    let running = unsafe { myproc() }.is_some_and(|p| p.state == ProcState::Running);
+    if running && tf.trapno == T_IRQ0 + IRQ_TIMER {
+        yield_();
+    }
+
+    // Check if the process has been killed since we yielded
+    if killed() && tf.from_user() {
+        exit(-1);
+    }
+}
+
+/// There is no disk driver; reading the status register acknowledges the
+/// interrupt.
+fn ide(_tf: &mut TrapFrame) {
+    unsafe { inb(IDE_STATUS) };
+    lapic::eoi();
+}
+
+/// Sent by a CPU that panicked (see `panic`), or by the hardware.
+fn nmi(tf: &mut TrapFrame) {
+    if panic::panicked() {
+        panic::freeze();
+    }
+    warn!("cpu{}: NMI at {:#x}:{:#x}", cpuid(), tf.cs, tf.eip);
+}
+
+fn spurious(tf: &mut TrapFrame) {
+    warn!(
+        "cpu{}: spurious interrupt at {:#x}:{:#x}",
+        cpuid(),
+        tf.cs,
+        tf.eip
+    );
+    lapic::eoi();
+}
+
+/// In user space, assume process misbehaved: kill it. `addr` is the value
+/// of `%cr2`, which only means something for page faults.
+fn misbehaved(tf: &TrapFrame, addr: usize) {
+    // SAFETY: exceptions arrive through interrupt gates, with interrupts
+    // disabled.
+    let Some(p) = (unsafe { myproc() }) else {
+        panic!("trap from user mode without a process\n{tf}");
+    };
+    println!(
+        "pid {} {}: trap {} err {} on cpu {} eip {:#x} addr {:#x}--kill proc",
+        p.pid,
+        p.name(),
+        tf.trapno,
+        tf.err,
+        cpuid(),
+        tf.eip,
+        addr
+    );
+    p.killed.store(true, Ordering::Relaxed);
+}
+
+/// Nothing is paged in lazily yet, so a page fault is either a kernel bug
+/// or a misbehaving user process.
+fn page_fault(fault: &PageFault, tf: &mut TrapFrame) {
+    if tf.from_user() {
+        misbehaved(tf, fault.addr);
+    } else {
+        // In kernel, it must be our mistake.
+        panic!(
+            "page fault from cpu {} eip {:#010x}: {fault}\n{tf}",
+            cpuid(),
+            tf.eip
+        );
+    }
+}
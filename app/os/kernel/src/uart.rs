@@ -0,0 +1,137 @@
+//! Intel 8250/16550 serial port (UART) driver (inherited xv6 uart.c)
+//!
+//! Only COM1 is supported. QEMU connects it to the host terminal with
+//! `-serial mon:stdio`.
+
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use trap::IRQ_COM1;
+
+use crate::x86::{inb, outb};
+use crate::{console, ioapic, lapic, picirq};
+
+/// I/O base port of the first serial port.
+pub const COM1: u16 = 0x3F8;
+
+/// Line speed. The UART's clock runs at 115200 * 16 Hz.
+const BAUD_RATE: u32 = 115_200;
+const UART_CLOCK: u32 = 115_200;
+
+// Register offsets from the base port.
+/// Receive buffer (read) / Transmit holding (write). Divisor low byte with DLAB.
+const RBR_THR: u16 = 0;
+/// Interrupt enable. Divisor high byte with DLAB.
+const IER: u16 = 1;
+/// Interrupt identification (read) / FIFO control (write)
+const IIR_FCR: u16 = 2;
+/// Line control
+const LCR: u16 = 3;
+/// Modem control
+const MCR: u16 = 4;
+/// Line status
+const LSR: u16 = 5;
+
+/// IER: interrupt when received data is available
+const IER_RX_AVAILABLE: u8 = 0x01;
+/// FCR: enable and clear both FIFOs, interrupt at 14 bytes
+const FCR_FIFO_ENABLE_CLEAR_14: u8 = 0xC7;
+/// LCR: Divisor Latch Access Bit
+const LCR_DLAB: u8 = 0x80;
+/// LCR: 8 data bits, no parity, 1 stop bit
+const LCR_8N1: u8 = 0x03;
+/// MCR: DTR | RTS | OUT2 (OUT2 gates the IRQ line on PCs)
+const MCR_DTR_RTS_OUT2: u8 = 0x0B;
+/// LSR: a received byte is ready
+const LSR_DATA_READY: u8 = 0x01;
+/// LSR: transmit holding register is empty
+const LSR_THR_EMPTY: u8 = 0x20;
+
+/// How many times to poll LSR before writing a byte anyway.
+const TX_SPIN_LIMIT: u32 = 12_800;
+
+/// Set once [`init`] found a UART behind [`COM1`].
+static PRESENT: AtomicBool = AtomicBool::new(false);
+
+/// Programs COM1 for [`BAUD_RATE`] 8N1 with FIFOs and receive interrupts.
+///
+/// Returns `false` if there is no serial port; output is dropped then.
+pub fn init() -> bool {
+    let divisor = (UART_CLOCK / BAUD_RATE) as u16;
+    let [divisor_lo, divisor_hi] = divisor.to_le_bytes();
+
+    unsafe {
+        // Disable interrupts while programming the port.
+        outb(COM1 + IER, 0);
+
+        outb(COM1 + LCR, LCR_DLAB);
+        outb(COM1 + RBR_THR, divisor_lo);
+        outb(COM1 + IER, divisor_hi);
+        outb(COM1 + LCR, LCR_8N1);
+
+        outb(COM1 + IIR_FCR, FCR_FIFO_ENABLE_CLEAR_14);
+        outb(COM1 + MCR, MCR_DTR_RTS_OUT2);
+
+        // If status is 0xFF, no serial port.
+        if inb(COM1 + LSR) == 0xFF {
+            return false;
+        }
+
+        // Acknowledge pre-existing interrupt conditions;
+        // enable interrupts.
+        inb(COM1 + IIR_FCR);
+        inb(COM1 + RBR_THR);
+        outb(COM1 + IER, IER_RX_AVAILABLE);
+    }
+
+    PRESENT.store(true, Ordering::Release);
+    true
+}
+
+/// Routes the COM1 interrupt to this CPU.
+pub fn enable_intr() {
+    if PRESENT.load(Ordering::Acquire) {
+        picirq::enable(IRQ_COM1);
+        ioapic::enable(IRQ_COM1, lapic::lapicid());
+    }
+}
+
+/// COM1 interrupt: hands every received byte to the console.
+pub fn intr() {
+    console::intr(getc);
+}
+
+/// Writes one byte, waiting for the transmitter to drain first.
+pub fn putc(c: u8) {
+    if !PRESENT.load(Ordering::Acquire) {
+        return;
+    }
+    for _ in 0..TX_SPIN_LIMIT {
+        if unsafe { inb(COM1 + LSR) } & LSR_THR_EMPTY != 0 {
+            break;
+        }
+        core::hint::spin_loop();
+    }
+    unsafe { outb(COM1 + RBR_THR, c) };
+}
+
+/// Reads one received byte, if any.
+pub fn getc() -> Option<u8> {
+    if !PRESENT.load(Ordering::Acquire) {
+        return None;
+    }
+    if unsafe { inb(COM1 + LSR) } & LSR_DATA_READY == 0 {
+        return None;
+    }
+    Some(unsafe { inb(COM1 + RBR_THR) })
+}
+
+/// [`fmt::Write`] adapter over [`putc`].
+pub struct Uart;
+
+impl fmt::Write for Uart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.bytes().for_each(putc);
+        Ok(())
+    }
+}
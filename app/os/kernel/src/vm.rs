@@ -0,0 +1,426 @@
+//! Kernel page tables (inherited xv6 vm.c)
+
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use page::{
+    FrameAllocator, MapError, PG_SIZE, PageDirectory, PhysMapper, PteFlags, pg_round_down,
+    pg_round_up,
+};
+use segment::{
+    Ring, SEG_KCODE, SEG_KDATA, SEG_TSS, SEG_UCODE, SEG_UDATA, SegmentDescriptor, SegmentSelector,
+    TaskState,
+};
+
+use crate::kalloc::{kalloc, kfree};
+use crate::memlayout::{DEVSPACE, EXTMEM, KERNEL_BASE, KERNEL_LINK, PHYSTOP, p2v, v2p};
+use crate::params::K_STACK_SIZE;
+use crate::proc::{Proc, mycpu};
+use crate::spinlock::{popcli, pushcli};
+use crate::x86::{lcr3, lgdt, ltr};
+
+const _: () = assert!(p2v(PHYSTOP) <= DEVSPACE, "PHYSTOP too high");
+
+unsafe extern "C" {
+    /// Start of the kernel data segment (page aligned).
+    /// Defined by the kernel linker script.
+    #[link_name = "data"]
+    static KERNEL_DATA: [u8; 0];
+}
+
+/// Kernel page directory used by the scheduler, set by [`kvmalloc`].
+static KPGDIR: AtomicPtr<PageDirectory> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Page table pages come from [`kalloc`] and are reached through the
+/// kernel's mapping of all physical memory at [`KERNEL_BASE`].
+pub struct KernelFrames;
+
+unsafe impl PhysMapper for KernelFrames {
+    fn phys_to_virt(&self, pa: usize) -> *mut u8 {
+        core::ptr::with_exposed_provenance_mut(p2v(pa))
+    }
+}
+
+impl FrameAllocator for KernelFrames {
+    fn allocate_frame(&mut self) -> Option<usize> {
+        kalloc().map(|page| v2p(page.as_ptr().expose_provenance()))
+    }
+
+    unsafe fn deallocate_frame(&mut self, pa: usize) {
+        if let Some(page) = NonNull::new(self.phys_to_virt(pa)) {
+            unsafe { kfree(page) };
+        }
+    }
+}
+
+/// Set up CPU's kernel segment descriptors and task state.
+/// Run once on entry on each CPU.
+pub fn seginit() {
+    // SAFETY: interrupts are still disabled from boot.
+    let c = unsafe { mycpu() };
+
+    // Map "logical" addresses to virtual addresses using identity map.
+    // Cannot share a CODE descriptor for both kernel and user
+    // because it would have to have DPL_USR, but the CPU forbids
+    // an interrupt from CPL=0 to DPL=3.
+    c.gdt[SEG_KCODE] = SegmentDescriptor::code(Ring::Kernel);
+    c.gdt[SEG_KDATA] = SegmentDescriptor::data(Ring::Kernel);
+    c.gdt[SEG_UCODE] = SegmentDescriptor::code(Ring::User);
+    c.gdt[SEG_UDATA] = SegmentDescriptor::data(Ring::User);
+
+    // Interrupts from user mode switch to ss0:esp0. esp0 is the running
+    // process's kernel stack; nothing runs in user mode before it is set.
+    c.ts.ss0 = SegmentSelector::KERNEL_DATA.bits();
+    c.gdt[SEG_TSS] = SegmentDescriptor::tss(
+        (&raw const c.ts).addr() as u32,
+        size_of::<TaskState>() as u32 - 1,
+    );
+
+    // SAFETY: the kernel selectors keep their meaning from the boot GDT, and
+    // `c` lives for the lifetime of the kernel.
+    unsafe {
+        lgdt(&c.gdt);
+        ltr(SegmentSelector::TSS.bits());
+    }
+}
+
+/// One region of the kernel's part of every address space.
+struct Kmap {
+    virt: usize,
+    phys_start: usize,
+    /// Exclusive. `0` stands for the top of the 32-bit address space.
+    phys_end: usize,
+    perm: PteFlags,
+}
+
+/// This table defines the kernel's mappings, which are present in
+/// every process's page table.
+fn kmap() -> [Kmap; 4] {
+    let data = (&raw const KERNEL_DATA).addr();
+    [
+        // I/O space
+        Kmap {
+            virt: KERNEL_BASE,
+            phys_start: 0,
+            phys_end: EXTMEM,
+            perm: PteFlags::WRITABLE,
+        },
+        // kern text+rodata
+        Kmap {
+            virt: KERNEL_LINK,
+            phys_start: v2p(KERNEL_LINK),
+            phys_end: v2p(data),
+            perm: PteFlags::empty(),
+        },
+        // kern data+memory
+        Kmap {
+            virt: data,
+            phys_start: v2p(data),
+            phys_end: PHYSTOP,
+            perm: PteFlags::WRITABLE,
+        },
+        // more devices
+        Kmap {
+            virt: DEVSPACE,
+            phys_start: DEVSPACE,
+            phys_end: 0,
+            perm: PteFlags::WRITABLE,
+        },
+    ]
+}
+
+/// Set up kernel part of a page table.
+///
+/// Returns `None` if memory for the page table ran out.
+pub fn setupkvm() -> Option<NonNull<PageDirectory>> {
+    let page = kalloc()?;
+    // An all-zero directory maps nothing.
+    unsafe { page.write_bytes(0, PG_SIZE) };
+    let pgdir = page.cast::<PageDirectory>();
+
+    for k in kmap() {
+        let size = k.phys_end.wrapping_sub(k.phys_start);
+        let mapped = unsafe { &mut *pgdir.as_ptr() }.map_pages(
+            k.virt,
+            size,
+            k.phys_start,
+            k.perm,
+            &mut KernelFrames,
+        );
+        if mapped.is_err() {
+            unsafe { freevm(pgdir) };
+            return None;
+        }
+    }
+    Some(pgdir)
+}
+
+/// Allocate one page table for the machine for the kernel address
+/// space for scheduler processes.
+///
+/// # Panics
+/// Panics if there is not enough memory for the kernel page table.
+pub fn kvmalloc() {
+    let Some(pgdir) = setupkvm() else {
+        panic!("kvmalloc: out of memory");
+    };
+    KPGDIR.store(pgdir.as_ptr(), Ordering::Release);
+    switchkvm();
+}
+
+/// Switch h/w page table register to the kernel-only page table,
+/// for when no process is running.
+pub fn switchkvm() {
+    let pgdir = KPGDIR.load(Ordering::Acquire);
+    // SAFETY: KPGDIR maps the whole kernel (see kmap).
+    unsafe { lcr3(v2p(pgdir.addr())) };
+}
+
+/// Switch TSS and h/w page table to correspond to process `p`.
+///
+/// # Panics
+/// Panics if `p` has no kernel stack or no page table.
+pub fn switchuvm(p: &Proc) {
+    let (Some(kstack), Some(pgdir)) = (p.kstack, p.pgdir) else {
+        panic!("switchuvm: no kstack or pgdir");
+    };
+
+    pushcli();
+    // SAFETY: interrupts are disabled.
+    let c = unsafe { mycpu() };
+    // The TSS descriptor loaded by seginit stays; only the stack changes.
+    c.ts.esp0 = (kstack.as_ptr().addr() + K_STACK_SIZE) as u32;
+    // SAFETY: every process's page table maps the kernel (see setupkvm).
+    unsafe { lcr3(v2p(pgdir.as_ptr().addr())) };
+    popcli();
+}
+
+/// Load the initcode into address 0 of pgdir.
+/// `init` must be less than a page.
+///
+/// # Panics
+/// Panics if `init` is too big, or if memory runs out.
+pub fn inituvm(pgdir: NonNull<PageDirectory>, init: &[u8]) {
+    if init.len() >= PG_SIZE {
+        panic!("inituvm: more than a page");
+    }
+    let Some(mem) = kalloc() else {
+        panic!("inituvm: out of memory");
+    };
+    unsafe {
+        mem.write_bytes(0, PG_SIZE);
+        core::ptr::copy_nonoverlapping(init.as_ptr(), mem.as_ptr(), init.len());
+    }
+    // SAFETY: `pgdir` is a new page table, not loaded anywhere yet.
+    let mapped = unsafe { &mut *pgdir.as_ptr() }.map_pages(
+        0,
+        PG_SIZE,
+        v2p(mem.as_ptr().expose_provenance()),
+        PteFlags::WRITABLE | PteFlags::USER,
+        &mut KernelFrames,
+    );
+    if let Err(err) = mapped {
+        panic!("inituvm: {err}");
+    }
+}
+
+/// Load a program segment into pgdir. `va` must be page-aligned
+/// and the pages from `va` to `va + data.len()` must already be mapped.
+///
+/// # Panics
+/// Panics if `va` is not page-aligned or a page is not mapped.
+pub fn loaduvm(pgdir: NonNull<PageDirectory>, va: usize, data: &[u8]) {
+    if va % PG_SIZE != 0 {
+        panic!("loaduvm: addr must be page aligned");
+    }
+    for (i, chunk) in data.chunks(PG_SIZE).enumerate() {
+        let Some(pa) = user_page(pgdir, va + i * PG_SIZE, PteFlags::PRESENT) else {
+            panic!("loaduvm: address should exist");
+        };
+        unsafe { core::ptr::copy_nonoverlapping(chunk.as_ptr(), pa, chunk.len()) };
+    }
+}
+
+/// Allocate page tables and physical memory to grow process from oldsz to
+/// newsz, which need not be page aligned. Returns new size or `None` on
+/// error.
+pub fn allocuvm(pgdir: NonNull<PageDirectory>, oldsz: usize, newsz: usize) -> Option<usize> {
+    if newsz >= KERNEL_BASE {
+        return None;
+    }
+    if newsz < oldsz {
+        return Some(oldsz);
+    }
+
+    let mut a = pg_round_up(oldsz);
+    while a < newsz {
+        let Some(mem) = kalloc() else {
+            warn!("allocuvm out of memory");
+            deallocuvm(pgdir, newsz, oldsz);
+            return None;
+        };
+        unsafe { mem.write_bytes(0, PG_SIZE) };
+        // SAFETY: the caller owns `pgdir`.
+        let mapped = unsafe { &mut *pgdir.as_ptr() }.map_pages(
+            a,
+            PG_SIZE,
+            v2p(mem.as_ptr().expose_provenance()),
+            PteFlags::WRITABLE | PteFlags::USER,
+            &mut KernelFrames,
+        );
+        if mapped.is_err() {
+            warn!("allocuvm out of memory (2)");
+            deallocuvm(pgdir, newsz, oldsz);
+            unsafe { kfree(mem) };
+            return None;
+        }
+        a += PG_SIZE;
+    }
+    Some(newsz)
+}
+
+/// Deallocate user pages to bring the process size from oldsz to
+/// newsz. oldsz and newsz need not be page-aligned, nor does newsz
+/// need to be less than oldsz. oldsz can be larger than the actual
+/// process size. Returns the new process size.
+pub fn deallocuvm(pgdir: NonNull<PageDirectory>, oldsz: usize, newsz: usize) -> usize {
+    if newsz >= oldsz {
+        return oldsz;
+    }
+
+    let a = pg_round_up(newsz);
+    if a < oldsz {
+        // SAFETY: the caller owns `pgdir`, and the user pages of a page
+        // table all come from kalloc.
+        unsafe { (*pgdir.as_ptr()).unmap_pages(a, oldsz - a, &mut KernelFrames, true) };
+    }
+    newsz
+}
+
+/// Clear PTE_U on a page. Used to create an inaccessible
+/// page beneath the user stack.
+///
+/// # Panics
+/// Panics if the page is not mapped.
+pub fn clearpteu(pgdir: NonNull<PageDirectory>, uva: usize) {
+    // SAFETY: the caller owns `pgdir`.
+    let Some(pte) = unsafe { &mut *pgdir.as_ptr() }.walk(uva, &KernelFrames) else {
+        panic!("clearpteu");
+    };
+    pte.set_flags(pte.flags().difference(PteFlags::USER));
+}
+
+/// Kernel address of the page mapped at user address `uva` in pgdir, if it
+/// is mapped with (at least) `perm` (xv6 `uva2ka` with `perm` `PTE_P|PTE_U`).
+fn user_page(pgdir: NonNull<PageDirectory>, uva: usize, perm: PteFlags) -> Option<*mut u8> {
+    // SAFETY: the caller owns `pgdir`, or it is the running process's.
+    let pte = unsafe { &mut *pgdir.as_ptr() }.walk(uva, &KernelFrames)?;
+    pte.flags()
+        .contains(perm | PteFlags::PRESENT)
+        .then(|| KernelFrames.phys_to_virt(pte.addr()))
+}
+
+/// Copy `data` to user address `va` in page table pgdir.
+/// Most useful when pgdir is not the current page table.
+/// Returns `None` if a page is not mapped for user access.
+pub fn copyout(pgdir: NonNull<PageDirectory>, va: usize, data: &[u8]) -> Option<()> {
+    let mut va = va;
+    let mut data = data;
+    while !data.is_empty() {
+        let va0 = pg_round_down(va);
+        let pa0 = user_page(pgdir, va0, PteFlags::USER)?;
+        let n = (PG_SIZE - (va - va0)).min(data.len());
+        unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), pa0.add(va - va0), n) };
+        data = &data[n..];
+        va = va0 + PG_SIZE;
+    }
+    Some(())
+}
+
+/// Copy to `buf` from user address `va` in page table pgdir. Like the
+/// kernel reading user memory directly, this includes pages without
+/// PTE_U, such as the guard page below the user stack.
+/// Returns `None` if a page is not mapped.
+pub fn copyin(pgdir: NonNull<PageDirectory>, va: usize, buf: &mut [u8]) -> Option<()> {
+    let mut va = va;
+    let mut buf = buf;
+    while !buf.is_empty() {
+        let va0 = pg_round_down(va);
+        let pa0 = user_page(pgdir, va0, PteFlags::PRESENT)?;
+        let n = (PG_SIZE - (va - va0)).min(buf.len());
+        unsafe { core::ptr::copy_nonoverlapping(pa0.add(va - va0), buf.as_mut_ptr(), n) };
+        buf = &mut buf[n..];
+        va = va0 + PG_SIZE;
+    }
+    Some(())
+}
+
+/// Returns the `len` bytes of firmware memory (e.g. ACPI tables) at physical
+/// address `pa`, read-only.
+///
+/// Memory above [`PHYSTOP`] is mapped on demand where it would be if it were
+/// below, in the kernel page table only: the bytes must not be used once
+/// processes run. Returns `None` if `pa` cannot be mapped there.
+pub fn firmware_bytes(pa: usize, len: usize) -> Option<&'static [u8]> {
+    let end = pa.checked_add(len)?;
+    if end > v2p(DEVSPACE) {
+        return None;
+    }
+    if end > PHYSTOP {
+        // SAFETY: only the boot CPU runs, on the kernel page table.
+        let pgdir = unsafe { &mut *KPGDIR.load(Ordering::Acquire) };
+        let mut a = pg_round_down(pa.max(PHYSTOP));
+        while a < end {
+            let mapped = pgdir
+                .walk(p2v(a), &KernelFrames)
+                .is_some_and(|pte| pte.is_present());
+            if !mapped {
+                pgdir
+                    .map_pages(p2v(a), PG_SIZE, a, PteFlags::empty(), &mut KernelFrames)
+                    .ok()?;
+            }
+            a += PG_SIZE;
+        }
+    }
+    Some(unsafe { core::slice::from_raw_parts(core::ptr::with_exposed_provenance(p2v(pa)), len) })
+}
+
+/// Given a parent process's page table, create a copy
+/// of it for a child.
+///
+/// Returns `None` if memory ran out.
+///
+/// # Panics
+/// Panics if a page below `sz` is not mapped in `pgdir`.
+pub fn copyuvm(pgdir: NonNull<PageDirectory>, sz: usize) -> Option<NonNull<PageDirectory>> {
+    let d = setupkvm()?;
+    // SAFETY: `pgdir` is the caller's own page table; `d` is new.
+    let copied = unsafe { &mut *pgdir.as_ptr() }.copy_pages(
+        unsafe { &mut *d.as_ptr() },
+        0,
+        sz,
+        &mut KernelFrames,
+    );
+    match copied {
+        Ok(()) => Some(d),
+        Err(MapError::OutOfMemory) => {
+            unsafe { freevm(d) };
+            None
+        }
+        Err(err) => panic!("copyuvm: {err}"),
+    }
+}
+
+/// Free a page table and all the physical memory pages in the user part.
+///
+/// # Safety
+/// `pgdir` must come from [`setupkvm`] and must not be loaded on any CPU.
+pub unsafe fn freevm(pgdir: NonNull<PageDirectory>) {
+    let frames = &mut KernelFrames;
+    let dir = unsafe { &mut *pgdir.as_ptr() };
+    unsafe {
+        dir.unmap_pages(0, KERNEL_BASE, frames, true);
+        dir.free_tables(frames);
+        kfree(pgdir.cast());
+    }
+}
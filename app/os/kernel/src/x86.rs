@@ -0,0 +1,159 @@
+//! Routines to let Rust code use special x86 instructions (inherited xv6 x86.h)
+
+use core::arch::asm;
+use core::arch::x86::__cpuid;
+
+use segment::SegmentDescriptor;
+
+/// Loads `val` (a physical address) into `%cr3`, switching page directory.
+///
+/// # Safety
+/// `val` must point to a page directory that maps the running kernel.
+#[inline]
+pub unsafe fn lcr3(val: usize) {
+    unsafe { asm!("mov cr3, {}", in(reg) val, options(nostack, preserves_flags)) };
+}
+
+/// Loads `gdt` into the global descriptor table register.
+///
+/// # Safety
+/// `gdt` must stay in place while loaded, and must keep the selectors in the
+/// segment registers valid.
+#[inline]
+pub unsafe fn lgdt(gdt: &[SegmentDescriptor]) {
+    let base = gdt.as_ptr().addr();
+    let pd: [u16; 3] = [
+        (size_of_val(gdt) - 1) as u16,
+        base as u16,
+        (base >> 16) as u16,
+    ];
+    unsafe { asm!("lgdt [{}]", in(reg) &pd, options(readonly, nostack, preserves_flags)) };
+}
+
+/// Loads the task register with the TSS selected by `sel`.
+///
+/// # Safety
+/// `sel` must select an available TSS descriptor in the loaded GDT.
+#[inline]
+pub unsafe fn ltr(sel: u16) {
+    unsafe { asm!("ltr {0:x}", in(reg) sel, options(nostack, preserves_flags)) };
+}
+
+/// Reads a byte from the specified I/O port.
+///
+/// # Safety
+/// Reading some ports has side effects on the device behind them.
+#[inline]
+pub unsafe fn inb(port: u16) -> u8 {
+    let data: u8;
+    unsafe { asm!("in al, dx", in("dx") port, out("al") data, options(nomem, nostack, preserves_flags)) };
+    data
+}
+
+/// Writes a byte to the specified I/O port.
+///
+/// # Safety
+/// Writing to an I/O port can reconfigure arbitrary hardware.
+#[inline]
+pub unsafe fn outb(port: u16, data: u8) {
+    unsafe { asm!("out dx, al", in("dx") port, in("al") data, options(nomem, nostack, preserves_flags)) };
+}
+
+/// Reads a 16-bit word from the specified I/O port.
+///
+/// # Safety
+/// Reading some ports has side effects on the device behind them.
+#[inline]
+pub unsafe fn inw(port: u16) -> u16 {
+    let data: u16;
+    unsafe {
+        asm!("in ax, dx", in("dx") port, out("ax") data, options(nomem, nostack, preserves_flags))
+    };
+    data
+}
+
+/// Writes a 16-bit word to the specified I/O port.
+///
+/// # Safety
+/// Writing to an I/O port can reconfigure arbitrary hardware.
+#[inline]
+pub unsafe fn outw(port: u16, data: u16) {
+    unsafe {
+        asm!("out dx, ax", in("dx") port, in("ax") data, options(nomem, nostack, preserves_flags))
+    };
+}
+
+/// Interrupt Enable flag in `%eflags`
+pub const FL_IF: u32 = 0x0000_0200;
+
+#[inline]
+pub fn read_eflags() -> u32 {
+    let eflags: u32;
+    unsafe { asm!("pushfd", "pop {}", out(reg) eflags, options(nomem, preserves_flags)) };
+    eflags
+}
+
+/// Disables interrupts on this CPU.
+///
+/// # Safety
+/// The caller is responsible for re-enabling interrupts.
+#[inline]
+pub unsafe fn cli() {
+    unsafe { asm!("cli", options(nomem, nostack)) };
+}
+
+/// Enables interrupts on this CPU.
+///
+/// # Safety
+/// An interrupt handler may run right after this call.
+#[inline]
+pub unsafe fn sti() {
+    unsafe { asm!("sti", options(nomem, nostack)) };
+}
+
+/// Halts the CPU until the next interrupt.
+#[inline]
+pub fn hlt() {
+    unsafe { asm!("hlt", options(nomem, nostack, preserves_flags)) };
+}
+
+/// Current frame pointer.
+#[inline(always)]
+pub fn read_ebp() -> usize {
+    let ebp: usize;
+    unsafe { asm!("mov {}, ebp", out(reg) ebp, options(nomem, nostack, preserves_flags)) };
+    ebp
+}
+
+/// ID flag in `%eflags`: can be toggled iff the CPU has `cpuid`
+const FL_ID: u32 = 0x0020_0000;
+
+fn has_cpuid() -> bool {
+    let (orig, flipped): (u32, u32);
+    unsafe {
+        asm!(
+            "pushfd",
+            "pop {orig}",
+            "mov {flipped}, {orig}",
+            "xor {flipped}, {id}",
+            "push {flipped}",
+            "popfd",
+            "pushfd",
+            "pop {flipped}",
+            "push {orig}",
+            "popfd",
+            orig = out(reg) orig,
+            flipped = out(reg) flipped,
+            id = const FL_ID,
+        );
+    }
+    (orig ^ flipped) & FL_ID != 0
+}
+
+/// CPUID.01H:EDX: on-chip APIC
+const CPUID_APIC: u32 = 1 << 9;
+
+/// Whether this CPU has a local APIC.
+pub fn has_apic() -> bool {
+    has_cpuid() && unsafe { __cpuid(1) }.edx & CPUID_APIC != 0
+}
@@ -0,0 +1,11 @@
+use std::path::PathBuf;
+
+fn main() {
+    let linker_script = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("user.ld");
+
+    // Notification: Rebuild if link script has changed.
+    println!("cargo:rerun-if-changed=user.ld");
+
+    // User programs are loaded at address 0 (see user.ld).
+    println!("cargo:rustc-link-arg-bins=-T{}", linker_script.display());
+}
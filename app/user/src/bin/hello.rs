@@ -0,0 +1,16 @@
+//! Prints its pid and arguments.
+
+#![no_std]
+#![no_main]
+
+use user::{Args, print, println};
+
+#[unsafe(no_mangle)]
+fn main(args: Args) -> i32 {
+    print!("hello: pid {}, args", user::getpid());
+    for arg in args.iter() {
+        print!(" {arg:?}");
+    }
+    println!();
+    0
+}
@@ -0,0 +1,32 @@
+//! The first program (inherited xv6 init.c)
+//!
+//! Runs `/hello` as a stand-in for the shell, then reaps orphaned processes
+//! for as long as the system is up.
+
+#![no_std]
+#![no_main]
+
+use user::{Args, eprintln, println};
+
+#[unsafe(no_mangle)]
+fn main(_args: Args) -> i32 {
+    println!("init: starting hello");
+    match user::fork() {
+        Ok(0) => {
+            let err = user::exec(c"/hello", &[c"hello", c"from", c"init"]);
+            eprintln!("init: exec hello failed: {err}");
+            user::exit(1)
+        }
+        Ok(pid) => println!("init: started hello as pid {pid}"),
+        Err(err) => eprintln!("init: fork failed: {err}"),
+    }
+    loop {
+        match user::wait() {
+            Ok((pid, status)) => println!("init: pid {pid} exited with status {status}"),
+            // No children left: nothing to do but wait for orphans.
+            Err(_) => {
+                let _ = user::sleep(100);
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! The first user program (inherited xv6 initcode.S)
+//!
+//! The kernel's `userinit` copies [`code`] to address 0 of the first
+//! process, and returns to user mode at its start. It runs
+//! `exec("/init", ["/init"])`, and keeps calling `exit` if that fails.
+//!
+//! The code is assembled into the kernel's read-only data and only ever runs
+//! at address 0, so every address in it is computed from there. Its labels
+//! are prefixed, as they share the assembly of a program with its `usys`
+//! stubs (`exit`, `exec`, ...).
+
+use core::arch::global_asm;
+
+use syscall::{Syscall, T_SYSCALL};
+
+unsafe extern "C" {
+    #[link_name = "initcode_start"]
+    static INITCODE_START: [u8; 0];
+    #[link_name = "initcode_end"]
+    static INITCODE_END: [u8; 0];
+}
+
+global_asm!(
+    r#"
+.section .rodata.initcode, "a"
+.global initcode_start
+initcode_start:
+    # exec(init, argv)
+    pushl   $(initcode_argv - initcode_start)
+    pushl   $(initcode_init - initcode_start)
+    pushl   $0  # where caller pc would be
+    movl    ${sys_exec}, %eax
+    int     ${t_syscall}
+
+    # for(;;) exit();
+initcode_exit:
+    movl    ${sys_exit}, %eax
+    int     ${t_syscall}
+    jmp     initcode_exit
+
+    # char init[] = "/init\0";
+initcode_init:
+    .string "/init"
+
+    # char *argv[] = {{ init, 0 }};
+.p2align 2
+initcode_argv:
+    .long   (initcode_init - initcode_start)
+    .long   0
+
+.global initcode_end
+initcode_end:
+"#,
+    t_syscall = const T_SYSCALL,
+    sys_exec = const Syscall::Exec.number(),
+    sys_exit = const Syscall::Exit.number(),
+    options(att_syntax),
+);
+
+/// The program, to be copied to address 0 of the first process.
+pub fn code() -> &'static [u8] {
+    let start = (&raw const INITCODE_START).cast::<u8>();
+    let len = (&raw const INITCODE_END).addr() - start.addr();
+    unsafe { core::slice::from_raw_parts(start, len) }
+}
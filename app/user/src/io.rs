@@ -0,0 +1,70 @@
+//! Formatted output to file descriptors (inherited xv6 printf.c)
+
+use core::fmt;
+
+use crate::sys::{self, STDERR, STDOUT};
+
+/// A file descriptor, written to with `write!`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fd(pub i32);
+
+impl fmt::Write for Fd {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut buf = s.as_bytes();
+        while !buf.is_empty() {
+            match sys::write(self.0, buf) {
+                Ok(0) | Err(_) => return Err(fmt::Error),
+                Ok(n) => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments<'_>) {
+    let _ = fmt::Write::write_fmt(&mut Fd(STDOUT), args);
+}
+
+#[doc(hidden)]
+pub fn _eprint(args: fmt::Arguments<'_>) {
+    let _ = fmt::Write::write_fmt(&mut Fd(STDERR), args);
+}
+
+/// Prints to the standard output.
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::io::_print(format_args!($($arg)*))
+    };
+}
+
+/// Prints to the standard output, with a newline.
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::print!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::io::_print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+/// Prints to the standard error.
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => {
+        $crate::io::_eprint(format_args!($($arg)*))
+    };
+}
+
+/// Prints to the standard error, with a newline.
+#[macro_export]
+macro_rules! eprintln {
+    () => {
+        $crate::eprint!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::io::_eprint(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
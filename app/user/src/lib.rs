@@ -0,0 +1,42 @@
+#![cfg_attr(not(test), no_std)]
+//! User space: the code that runs in ring 3.
+//!
+//! [`initcode`] is the first user program, which the kernel embeds and
+//! copies into the first process.
+//!
+//! With the `rt` feature (the default), this is also the runtime library user
+//! programs are built on (xv6 usys.S, ulib.c and printf.c):
+//!
+//! - [`usys`]: the raw `int $T_SYSCALL` stubs, one per system call
+//! - safe wrappers around them, returning `Result<_, Errno>`
+//! - [`print!`], [`println!`], [`eprint!`] and [`eprintln!`]
+//! - `_start`, which calls the program's `main` with its [`Args`] and exits
+//!   with the status it returns, and a panic handler that prints the panic
+//!   and exits with -1
+//!
+//! A program is a `#![no_std]`, `#![no_main]` binary of this crate that
+//! defines
+//!
+//! ```ignore
+//! #[unsafe(no_mangle)]
+//! fn main(args: user::Args) -> i32
+//! ```
+//!
+//! It is linked at address 0 by `user.ld`, and `xtask image` embeds it in the
+//! kernel as `/<name>`.
+
+#[cfg(target_arch = "x86")]
+pub mod initcode;
+#[cfg(feature = "rt")]
+pub mod io;
+#[cfg(feature = "rt")]
+mod rt;
+#[cfg(feature = "rt")]
+mod sys;
+#[cfg(feature = "rt")]
+pub mod usys;
+
+#[cfg(feature = "rt")]
+pub use self::rt::Args;
+#[cfg(feature = "rt")]
+pub use self::sys::*;
@@ -0,0 +1,56 @@
+//! Program entry and panics
+
+use core::ffi::{CStr, c_char};
+use core::slice;
+
+use crate::sys;
+
+/// A program's command-line arguments, `argv[0]` first.
+#[derive(Debug, Clone, Copy)]
+pub struct Args {
+    argv: &'static [*const c_char],
+}
+
+impl Args {
+    /// Number of arguments.
+    pub const fn len(&self) -> usize {
+        self.argv.len()
+    }
+
+    /// Whether there are no arguments, not even the program name.
+    pub const fn is_empty(&self) -> bool {
+        self.argv.is_empty()
+    }
+
+    /// Argument `n`, if any.
+    pub fn get(&self, n: usize) -> Option<&'static CStr> {
+        // exec copies every argument to the stack, nul-terminated.
+        self.argv.get(n).map(|&arg| unsafe { CStr::from_ptr(arg) })
+    }
+
+    /// The arguments, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &'static CStr> + use<> {
+        let argv = *self;
+        (0..argv.len()).filter_map(move |n| argv.get(n))
+    }
+}
+
+unsafe extern "Rust" {
+    /// The program, which returns its exit status.
+    safe fn main(args: Args) -> i32;
+}
+
+/// Entry point, which exec returns to with `argc` and `argv` on the stack
+/// as if called.
+#[unsafe(no_mangle)]
+extern "C" fn _start(argc: i32, argv: *const *const c_char) -> ! {
+    let argv = unsafe { slice::from_raw_parts(argv, argc as usize) };
+    sys::exit(main(Args { argv }))
+}
+
+#[cfg(not(test))]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo<'_>) -> ! {
+    crate::eprintln!("panic: {info}");
+    sys::exit(-1)
+}
@@ -0,0 +1,260 @@
+//! Safe system call wrappers (inherited xv6 user.h)
+//!
+//! The kernel reports every failure as -1, which the wrappers turn into an
+//! [`Errno`]. File descriptors are plain `i32`s, as in xv6.
+
+use core::ffi::{CStr, c_char};
+use core::{error, fmt, ptr};
+
+use crate::usys;
+
+pub use syscall::MAXARG;
+
+/// Standard input, output and error, inherited from `init`.
+pub const STDIN: i32 = 0;
+pub const STDOUT: i32 = 1;
+pub const STDERR: i32 = 2;
+
+/// `open` modes (inherited xv6 fcntl.h).
+pub const O_RDONLY: i32 = 0x000;
+pub const O_WRONLY: i32 = 0x001;
+pub const O_RDWR: i32 = 0x002;
+pub const O_CREATE: i32 = 0x200;
+
+/// `Stat::kind` values (inherited xv6 stat.h).
+pub const T_DIR: i16 = 1;
+pub const T_FILE: i16 = 2;
+pub const T_DEV: i16 = 3;
+
+/// A failed system call, holding what the kernel returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Errno(pub i32);
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "system call failed ({})", self.0)
+    }
+}
+
+impl error::Error for Errno {}
+
+/// File status, filled in by [`fstat`] (inherited xv6 stat.h).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Stat {
+    /// Type of file: [`T_DIR`], [`T_FILE`] or [`T_DEV`]
+    pub kind: i16,
+    /// File system's disk device
+    pub dev: i32,
+    /// Inode number
+    pub ino: u32,
+    /// Number of links to file
+    pub nlink: i16,
+    /// Size of file in bytes
+    pub size: u32,
+}
+
+const fn check(ret: i32) -> Result<i32, Errno> {
+    if ret < 0 { Err(Errno(ret)) } else { Ok(ret) }
+}
+
+fn unit(ret: i32) -> Result<(), Errno> {
+    check(ret).map(|_| ())
+}
+
+/// Creates a process. Returns the child's pid in the parent and 0 in the
+/// child.
+///
+/// # Errors
+///
+/// If there is no free process slot or memory.
+pub fn fork() -> Result<u32, Errno> {
+    check(unsafe { usys::fork() }).map(|pid| pid as u32)
+}
+
+/// Terminates the current process with `status`.
+pub fn exit(status: i32) -> ! {
+    unsafe { usys::exit(status) }
+}
+
+/// Waits for a child process to exit. Returns its pid and exit status.
+///
+/// # Errors
+///
+/// If the process has no children, or is killed while waiting.
+pub fn wait() -> Result<(u32, i32), Errno> {
+    let mut status = 0;
+    let pid = check(unsafe { usys::wait(&mut status) })?;
+    Ok((pid as u32, status))
+}
+
+/// Creates a pipe. Returns its read and write ends.
+///
+/// # Errors
+///
+/// Always, as there are no pipes yet.
+pub fn pipe() -> Result<[i32; 2], Errno> {
+    let mut fds = [0; 2];
+    unit(unsafe { usys::pipe(fds.as_mut_ptr()) })?;
+    Ok(fds)
+}
+
+/// Reads up to `buf.len()` bytes from `fd`. Returns how many were read, 0 at
+/// end of file.
+///
+/// # Errors
+///
+/// If `fd` is not open for reading, or the process is killed while
+/// waiting for console input.
+pub fn read(fd: i32, buf: &mut [u8]) -> Result<usize, Errno> {
+    let n = buf.len().min(i32::MAX as usize) as i32;
+    check(unsafe { usys::read(fd, buf.as_mut_ptr(), n) }).map(|n| n as usize)
+}
+
+/// Writes up to `buf.len()` bytes to `fd`. Returns how many were written.
+///
+/// # Errors
+///
+/// If `fd` is not open for writing.
+pub fn write(fd: i32, buf: &[u8]) -> Result<usize, Errno> {
+    let n = buf.len().min(i32::MAX as usize) as i32;
+    check(unsafe { usys::write(fd, buf.as_ptr(), n) }).map(|n| n as usize)
+}
+
+/// Closes `fd`.
+///
+/// # Errors
+///
+/// If `fd` is not open.
+pub fn close(fd: i32) -> Result<(), Errno> {
+    unit(unsafe { usys::close(fd) })
+}
+
+/// Kills process `pid`: it exits the next time it returns to user space.
+///
+/// # Errors
+///
+/// If there is no process `pid`.
+pub fn kill(pid: u32) -> Result<(), Errno> {
+    unit(unsafe { usys::kill(pid as i32) })
+}
+
+/// Replaces the current program with the one at `path`, called with `argv`.
+/// Only returns if that fails, with the error.
+pub fn exec(path: &CStr, argv: &[&CStr]) -> Errno {
+    if argv.len() > MAXARG {
+        return Errno(-1);
+    }
+    let mut ptrs = [ptr::null::<c_char>(); MAXARG + 1];
+    for (p, arg) in ptrs.iter_mut().zip(argv) {
+        *p = arg.as_ptr();
+    }
+    Errno(unsafe { usys::exec(path.as_ptr(), ptrs.as_ptr()) })
+}
+
+/// Returns the status of the file open as `fd`.
+///
+/// # Errors
+///
+/// Always, as there is no file system yet.
+pub fn fstat(fd: i32) -> Result<Stat, Errno> {
+    let mut st = Stat::default();
+    unit(unsafe { usys::fstat(fd, &mut st) })?;
+    Ok(st)
+}
+
+/// Changes the current directory.
+///
+/// # Errors
+///
+/// Always, as there is no file system yet.
+pub fn chdir(path: &CStr) -> Result<(), Errno> {
+    unit(unsafe { usys::chdir(path.as_ptr()) })
+}
+
+/// Duplicates `fd` onto the lowest free descriptor, and returns it.
+///
+/// # Errors
+///
+/// If `fd` is not open, or no descriptor is free.
+pub fn dup(fd: i32) -> Result<i32, Errno> {
+    check(unsafe { usys::dup(fd) })
+}
+
+/// Returns the current process's pid.
+pub fn getpid() -> u32 {
+    (unsafe { usys::getpid() }) as u32
+}
+
+/// Grows (or shrinks) the process's memory by `n` bytes. Returns the start
+/// of the new memory, the previous end.
+///
+/// # Errors
+///
+/// If there is not enough memory.
+pub fn sbrk(n: i32) -> Result<*mut u8, Errno> {
+    match unsafe { usys::sbrk(n) } {
+        -1 => Err(Errno(-1)),
+        addr => Ok(ptr::without_provenance_mut(addr as u32 as usize)),
+    }
+}
+
+/// Sleeps for `ticks` clock ticks. Fails if the process is killed meanwhile.
+///
+/// # Errors
+///
+/// If the process is killed while sleeping.
+pub fn sleep(ticks: u32) -> Result<(), Errno> {
+    unit(unsafe { usys::sleep(ticks.min(i32::MAX as u32) as i32) })
+}
+
+/// Returns the number of clock ticks since boot.
+pub fn uptime() -> u32 {
+    (unsafe { usys::uptime() }) as u32
+}
+
+/// Opens the file at `path` with mode `omode` (`O_*`), and returns its
+/// descriptor.
+///
+/// # Errors
+///
+/// Always, as there is no file system yet.
+pub fn open(path: &CStr, omode: i32) -> Result<i32, Errno> {
+    check(unsafe { usys::open(path.as_ptr(), omode) })
+}
+
+/// Creates the device file `path`.
+///
+/// # Errors
+///
+/// Always, as there is no file system yet.
+pub fn mknod(path: &CStr, major: i16, minor: i16) -> Result<(), Errno> {
+    unit(unsafe { usys::mknod(path.as_ptr(), major, minor) })
+}
+
+/// Removes the link `path`.
+///
+/// # Errors
+///
+/// Always, as there is no file system yet.
+pub fn unlink(path: &CStr) -> Result<(), Errno> {
+    unit(unsafe { usys::unlink(path.as_ptr()) })
+}
+
+/// Creates the link `new` to the file `old`.
+///
+/// # Errors
+///
+/// Always, as there is no file system yet.
+pub fn link(old: &CStr, new: &CStr) -> Result<(), Errno> {
+    unit(unsafe { usys::link(old.as_ptr(), new.as_ptr()) })
+}
+
+/// Creates the directory `path`.
+///
+/// # Errors
+///
+/// Always, as there is no file system yet.
+pub fn mkdir(path: &CStr) -> Result<(), Errno> {
+    unit(unsafe { usys::mkdir(path.as_ptr()) })
+}
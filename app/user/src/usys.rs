@@ -0,0 +1,57 @@
+//! System call stubs (inherited xv6 usys.S)
+//!
+//! Each stub puts its [`Syscall`] number in `%eax` and traps; the kernel
+//! reads the arguments from the caller's stack and returns the result in
+//! `%eax`, -1 on failure. The stubs are generated from the system call table
+//! of the `syscall` crate; the signatures are those of xv6 user.h.
+
+use core::arch::global_asm;
+use core::ffi::c_char;
+
+use syscall::{Syscall, T_SYSCALL};
+
+use crate::sys::Stat;
+
+macro_rules! stubs {
+    ($($variant:ident $name:ident = $num:literal,)*) => {
+        $(
+            global_asm!(
+                ".section .text.usys, \"ax\"",
+                concat!(".global ", stringify!($name)),
+                concat!(stringify!($name), ":"),
+                "    movl ${num}, %eax",
+                "    int ${t_syscall}",
+                "    ret",
+                num = const Syscall::$variant.number(),
+                t_syscall = const T_SYSCALL,
+                options(att_syntax),
+            );
+        )*
+    };
+}
+
+syscall::syscall_table!(stubs);
+
+unsafe extern "C" {
+    pub fn fork() -> i32;
+    pub fn exit(status: i32) -> !;
+    pub fn wait(status: *mut i32) -> i32;
+    pub fn pipe(fds: *mut i32) -> i32;
+    pub fn read(fd: i32, buf: *mut u8, n: i32) -> i32;
+    pub fn kill(pid: i32) -> i32;
+    pub fn exec(path: *const c_char, argv: *const *const c_char) -> i32;
+    pub fn fstat(fd: i32, st: *mut Stat) -> i32;
+    pub fn chdir(path: *const c_char) -> i32;
+    pub fn dup(fd: i32) -> i32;
+    pub fn getpid() -> i32;
+    pub fn sbrk(n: i32) -> i32;
+    pub fn sleep(n: i32) -> i32;
+    pub fn uptime() -> i32;
+    pub fn open(path: *const c_char, omode: i32) -> i32;
+    pub fn write(fd: i32, buf: *const u8, n: i32) -> i32;
+    pub fn mknod(path: *const c_char, major: i16, minor: i16) -> i32;
+    pub fn unlink(path: *const c_char) -> i32;
+    pub fn link(old: *const c_char, new: *const c_char) -> i32;
+    pub fn mkdir(path: *const c_char) -> i32;
+    pub fn close(fd: i32) -> i32;
+}
@@ -0,0 +1,151 @@
+//! Just enough AML to find the `\_S5` object.
+//!
+//! Entering a sleep state takes values only the DSDT's AML knows: `_S5` is a
+//! package whose first two elements are the `SLP_TYP` values for the PM1a and
+//! PM1b control registers. Instead of interpreting AML, the definition is
+//! found by its bytes:
+//!
+//! ```txt
+//! NameOp ('\')  "_S5_"  PackageOp  PkgLength  NumElements  SLP_TYPa  SLP_TYPb ...
+//!  0x08   0x5C           0x12       1-4 bytes    byte       data      data
+//! ```
+
+/// `NameOp`
+const NAME_OP: u8 = 0x08;
+/// `RootChar`
+const ROOT_CHAR: u8 = b'\\';
+/// `PackageOp`
+const PACKAGE_OP: u8 = 0x12;
+/// `ZeroOp`
+const ZERO_OP: u8 = 0x00;
+/// `OneOp`
+const ONE_OP: u8 = 0x01;
+/// `BytePrefix`: an 8-bit constant follows
+const BYTE_PREFIX: u8 = 0x0A;
+
+/// `SLP_TYP` values of a sleep state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SleepType {
+    /// For the PM1a control register
+    pub a: u8,
+    /// For the PM1b control register
+    pub b: u8,
+}
+
+impl SleepType {
+    /// Value to write to the PM1a control register to enter the state.
+    pub const fn pm1a_cnt(self) -> u16 {
+        Self::pm1_cnt(self.a)
+    }
+
+    /// Value to write to the PM1b control register to enter the state.
+    pub const fn pm1b_cnt(self) -> u16 {
+        Self::pm1_cnt(self.b)
+    }
+
+    const fn pm1_cnt(slp_typ: u8) -> u16 {
+        ((slp_typ as u16 & 0b111) << 10) | crate::SLP_EN
+    }
+}
+
+/// Finds the definition of `\_S5` (soft off) in `aml`, the body of a DSDT,
+/// and returns its sleep type.
+pub fn find_s5(aml: &[u8]) -> Option<SleepType> {
+    aml.windows(4)
+        .enumerate()
+        .filter(|(_, name)| *name == b"_S5_")
+        .find_map(|(at, _)| s5_package(aml, at))
+}
+
+/// Parses the package named by the `_S5_` at `at`, if it is one.
+fn s5_package(aml: &[u8], at: usize) -> Option<SleepType> {
+    let before = &aml[..at];
+    if !(before.ends_with(&[NAME_OP]) || before.ends_with(&[NAME_OP, ROOT_CHAR])) {
+        return None;
+    }
+    let mut p = at + 4;
+    if *aml.get(p)? != PACKAGE_OP {
+        return None;
+    }
+    p += 1;
+    // PkgLength: bits 7-6 of the lead byte count the bytes that follow.
+    p += 1 + usize::from(*aml.get(p)? >> 6);
+    // NumElements
+    p += 1;
+    let a = data(aml, &mut p)?;
+    let b = data(aml, &mut p)?;
+    Some(SleepType { a, b })
+}
+
+/// Reads an integer constant of at most 8 bits at `*p`, and moves past it.
+fn data(aml: &[u8], p: &mut usize) -> Option<u8> {
+    let (value, len) = match *aml.get(*p)? {
+        ZERO_OP => (0, 1),
+        ONE_OP => (1, 1),
+        BYTE_PREFIX => (*aml.get(*p + 1)?, 2),
+        _ => return None,
+    };
+    *p += len;
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sdt;
+    use crate::testdata::{firecracker, qemu};
+
+    #[test]
+    fn test_qemu() {
+        // Name (_S5, Package (0x04) { Zero, Zero, Zero, Zero }), after the
+        // whole of the machine's devices.
+        for capture in qemu() {
+            let dsdt = Sdt::parse_as(&capture.dsdt, *b"DSDT").unwrap();
+            let s5 = find_s5(dsdt.data).unwrap_or_else(|| panic!("{}", capture.machine));
+            assert_eq!(s5, SleepType { a: 0, b: 0 }, "{}", capture.machine);
+            assert_eq!(s5.pm1a_cnt(), 0x2000);
+        }
+    }
+
+    #[test]
+    fn test_find_s5() {
+        // Name (\_S5, Package (0x04) { 0x07, 0x07, Zero, Zero })
+        let aml = [
+            0x08, b'\\', b'_', b'S', b'5', b'_', 0x12, 0x0A, 0x04, 0x0A, 0x07, 0x0A, 0x07, 0x00,
+            0x00,
+        ];
+        let s5 = find_s5(&aml).unwrap();
+        assert_eq!(s5, SleepType { a: 7, b: 7 });
+        assert_eq!(s5.pm1a_cnt(), 0x3C00);
+    }
+
+    #[test]
+    fn test_not_a_definition() {
+        // A reference to _S5_ (e.g. in a method) is skipped for the definition.
+        let aml = [
+            0x70, b'_', b'S', b'5', b'_', 0x60, //
+            0x08, b'_', b'S', b'5', b'_', 0x12, 0x06, 0x04, 0x01, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(find_s5(&aml), Some(SleepType { a: 1, b: 0 }));
+
+        assert_eq!(find_s5(&aml[..6]), None);
+        // Cut before SLP_TYPb
+        assert_eq!(find_s5(&aml[..15]), None);
+        // Not a constant
+        assert_eq!(
+            find_s5(&[0x08, b'_', b'S', b'5', b'_', 0x12, 0x06, 0x04, 0x70]),
+            None
+        );
+    }
+    #[test]
+    fn test_firecracker() {
+        // A whole DSDT, with devices, methods and buffers but no \_S5.
+        let dsdt = Sdt::parse_as(firecracker::DSDT, *b"DSDT").unwrap();
+        assert_eq!(dsdt.header.size(), firecracker::DSDT.len());
+        assert_eq!(find_s5(dsdt.data), None);
+        // Nor does any prefix, cut anywhere in the middle of an object.
+        for len in 0..dsdt.data.len() {
+            assert_eq!(find_s5(&dsdt.data[..len]), None);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{AcpiError, Sdt, SdtHeader, u8_at, u16_at, u32_at};
+
+/// PM1 control register: the SCI interrupt is enabled, i.e. the machine is in
+/// ACPI mode (set by the firmware after a write of
+/// [`Fadt::acpi_enable`] to [`Fadt::smi_cmd`]).
+pub const SCI_EN: u16 = 1 << 0;
+/// PM1 control register: enter the sleep state in `SLP_TYP` (bits 10-12).
+pub const SLP_EN: u16 = 1 << 13;
+
+/// Fixed ACPI Description Table (signature `FACP`): the fixed hardware
+/// registers and where the DSDT is.
+///
+/// Only the ACPI 1.0 fields up to the PM timer block are read; the 64-bit
+/// `X_` fields of later revisions are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fadt {
+    pub header: SdtHeader,
+    /// Physical address of the FACS
+    pub firmware_ctrl: u32,
+    /// Physical address of the DSDT
+    pub dsdt: u32,
+    /// ISA IRQ of the SCI interrupt
+    pub sci_int: u16,
+    /// I/O port of the SMI command register, or 0 if the machine is always
+    /// in ACPI mode
+    pub smi_cmd: u32,
+    /// Value to write to [`Self::smi_cmd`] to enter ACPI mode
+    pub acpi_enable: u8,
+    /// Value to write to [`Self::smi_cmd`] to leave ACPI mode
+    pub acpi_disable: u8,
+    /// I/O port of the PM1a event register block
+    pub pm1a_evt_blk: u32,
+    /// I/O port of the PM1b event register block, or 0
+    pub pm1b_evt_blk: u32,
+    /// I/O port of the PM1a control register block
+    pub pm1a_cnt_blk: u32,
+    /// I/O port of the PM1b control register block, or 0
+    pub pm1b_cnt_blk: u32,
+    /// I/O port of the power management timer, or 0
+    pub pm_tmr_blk: u32,
+}
+
+impl Fadt {
+    pub const SIGNATURE: [u8; 4] = *b"FACP";
+    /// Length of the table up to and including `PM_TMR_BLK`
+    const MIN_SIZE: usize = 80;
+
+    /// Parses the FADT at the start of `bytes`.
+    ///
+    /// # Errors
+    /// Same as [`Sdt::parse_as`], plus [`AcpiError::Length`] if the table is
+    /// too short for the fields above.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AcpiError> {
+        let Sdt { header, data } = Sdt::parse_as(bytes, Self::SIGNATURE)?;
+        if SdtHeader::SIZE + data.len() < Self::MIN_SIZE {
+            return Err(AcpiError::Length);
+        }
+        // Offsets are from the start of the table.
+        let at = |offset: usize| offset - SdtHeader::SIZE;
+        Ok(Self {
+            header,
+            firmware_ctrl: u32_at(data, at(36))?,
+            dsdt: u32_at(data, at(40))?,
+            sci_int: u16_at(data, at(46))?,
+            smi_cmd: u32_at(data, at(48))?,
+            acpi_enable: u8_at(data, at(52))?,
+            acpi_disable: u8_at(data, at(53))?,
+            pm1a_evt_blk: u32_at(data, at(56))?,
+            pm1b_evt_blk: u32_at(data, at(60))?,
+            pm1a_cnt_blk: u32_at(data, at(64))?,
+            pm1b_cnt_blk: u32_at(data, at(68))?,
+            pm_tmr_blk: u32_at(data, at(76))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::{firecracker, qemu, table};
+
+    #[test]
+    fn test_qemu() {
+        // Power management at 0x600, on the PIIX4 (pc) or the ICH9 (q35),
+        // and ACPI mode entered through the APM control port.
+        for capture in qemu() {
+            let fadt = Fadt::parse(&capture.facp).unwrap();
+            assert_ne!(fadt.firmware_ctrl, 0);
+            assert_ne!(fadt.dsdt, 0);
+            assert_eq!(fadt.sci_int, 9);
+            assert_eq!(fadt.smi_cmd, 0xB2);
+            let enable = match capture.machine {
+                "pc" => (0xF1, 0xF0),
+                _ => (0x02, 0x03),
+            };
+            assert_eq!((fadt.acpi_enable, fadt.acpi_disable), enable);
+            assert_eq!(fadt.pm1a_evt_blk, 0x600);
+            assert_eq!(fadt.pm1b_evt_blk, 0);
+            assert_eq!(fadt.pm1a_cnt_blk, 0x604);
+            assert_eq!(fadt.pm1b_cnt_blk, 0);
+            assert_eq!(fadt.pm_tmr_blk, 0x608);
+        }
+    }
+
+    #[test]
+    fn test_short() {
+        assert_eq!(
+            Fadt::parse(&table(*b"FACP", &[0; 40])),
+            Err(AcpiError::Length)
+        );
+        assert_eq!(
+            Fadt::parse(&table(*b"FACP", &[0; 44])).map(|fadt| fadt.pm_tmr_blk),
+            Ok(0)
+        );
+    }
+    #[test]
+    fn test_firecracker() {
+        // Hardware-reduced: no fixed registers, and the DSDT only in X_DSDT.
+        let fadt = Fadt::parse(firecracker::FACP).unwrap();
+        assert_eq!(fadt.header.length, 276);
+        assert_eq!(fadt.header.revision, 6);
+        assert_eq!(&fadt.header.oem_id, b"FIRECK");
+        assert_eq!(fadt.dsdt, 0);
+        assert_eq!(fadt.smi_cmd, 0);
+        assert_eq!(fadt.pm1a_cnt_blk, 0);
+        assert_eq!(fadt.pm1b_cnt_blk, 0);
+        assert_eq!(fadt.pm_tmr_blk, 0);
+    }
+}
@@ -0,0 +1,118 @@
+#![cfg_attr(not(test), no_std)]
+//! ACPI tables
+//!
+//! The firmware describes the machine in tables in physical memory. The
+//! [`Rsdp`] points at the [`Rsdt`], which lists the physical addresses of the
+//! other tables. Each of those starts with an [`SdtHeader`] giving its
+//! signature and length.
+//!
+//! ```txt
+//!  RSDP            RSDT               "APIC" (MADT): CPUs, I/O APICs, IRQ overrides
+//! +--------+      +-----------+  +-> +----------------+
+//! | "RSD   |      | header    |  |   | header | ...   |
+//! |  PTR " |      +-----------+  |   +----------------+
+//! | rsdt --+----> | entry[0] -+--+
+//! +--------+      | entry[1] -+--+-> "FACP" (FADT): PM1 control blocks, DSDT
+//!                 +-----------+      +----------------+
+//!                                    | header | ...   | --> "DSDT": AML, `\_S5`
+//!                                    +----------------+
+//! ```
+//!
+//! The parsers only look at byte slices; finding and mapping the memory is up
+//! to the caller. Every table is checked against its length and checksum.
+//!
+//! <https://uefi.org/specs/ACPI/6.5/05_ACPI_Software_Programming_Model.html>
+
+mod aml;
+mod fadt;
+mod madt;
+mod rsdp;
+mod sdt;
+#[cfg(test)]
+mod testdata;
+
+use core::fmt;
+
+pub use self::aml::{SleepType, find_s5};
+pub use self::fadt::{Fadt, SCI_EN, SLP_EN};
+pub use self::madt::{
+    InterruptOverride, IoApic, LocalApic, Madt, MadtEntries, MadtEntry, PCAT_COMPAT,
+};
+pub use self::rsdp::Rsdp;
+pub use self::sdt::{Rsdt, Sdt, SdtHeader};
+
+/// Error returned when a table cannot be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcpiError {
+    /// The bytes end before the structure does.
+    Truncated,
+    /// The structure does not start with the expected signature.
+    Signature,
+    /// The bytes of the structure do not add up to 0.
+    Checksum,
+    /// The length field is smaller than the structure's fixed part.
+    Length,
+}
+
+impl fmt::Display for AcpiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Truncated => "table truncated",
+            Self::Signature => "bad signature",
+            Self::Checksum => "bad checksum",
+            Self::Length => "bad length",
+        })
+    }
+}
+
+impl core::error::Error for AcpiError {}
+
+/// Whether all bytes add up to 0 (mod 256), as every ACPI structure must.
+pub fn checksum_ok(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) == 0
+}
+
+/// Reads the `N` bytes at `offset`.
+fn bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N], AcpiError> {
+    let end = offset.checked_add(N).ok_or(AcpiError::Truncated)?;
+    let bytes = data.get(offset..end).ok_or(AcpiError::Truncated)?;
+    bytes.try_into().map_err(|_| AcpiError::Truncated)
+}
+
+fn u8_at(data: &[u8], offset: usize) -> Result<u8, AcpiError> {
+    data.get(offset).copied().ok_or(AcpiError::Truncated)
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, AcpiError> {
+    bytes(data, offset).map(u16::from_le_bytes)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, AcpiError> {
+    bytes(data, offset).map(u32::from_le_bytes)
+}
+
+fn u64_at(data: &[u8], offset: usize) -> Result<u64, AcpiError> {
+    bytes(data, offset).map(u64::from_le_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        assert!(checksum_ok(&[]));
+        assert!(checksum_ok(&[0x01, 0xFF]));
+        assert!(!checksum_ok(&[0x01, 0xFE]));
+    }
+
+    #[test]
+    fn test_read() {
+        let data = [0x78, 0x56, 0x34, 0x12, 0xAA];
+        assert_eq!(u32_at(&data, 0), Ok(0x1234_5678));
+        assert_eq!(u16_at(&data, 3), Ok(0xAA12));
+        assert_eq!(u32_at(&data, 2), Err(AcpiError::Truncated));
+        assert_eq!(u8_at(&data, usize::MAX), Err(AcpiError::Truncated));
+        assert_eq!(bytes::<2>(&data, usize::MAX), Err(AcpiError::Truncated));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{AcpiError, Sdt, SdtHeader, u8_at, u16_at, u32_at};
+
+/// [`Madt::flags`]: the machine also has the dual 8259 PICs.
+pub const PCAT_COMPAT: u32 = 1;
+
+/// Multiple APIC Description Table (signature `APIC`): the interrupt
+/// controllers, and with them the processors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Madt<'a> {
+    pub header: SdtHeader,
+    /// Physical address of every CPU's local APIC
+    pub local_apic_address: u32,
+    pub flags: u32,
+    entries: &'a [u8],
+}
+
+impl<'a> Madt<'a> {
+    pub const SIGNATURE: [u8; 4] = *b"APIC";
+
+    /// Parses the MADT at the start of `bytes`.
+    ///
+    /// # Errors
+    /// Same as [`Sdt::parse_as`], plus [`AcpiError::Length`] if the table is
+    /// too short for the MADT fields.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, AcpiError> {
+        let Sdt { header, data } = Sdt::parse_as(bytes, Self::SIGNATURE)?;
+        let fields = |_| AcpiError::Length;
+        Ok(Self {
+            header,
+            local_apic_address: u32_at(data, 0).map_err(fields)?,
+            flags: u32_at(data, 4).map_err(fields)?,
+            entries: &data[8..],
+        })
+    }
+
+    /// The interrupt controller structures, in table order.
+    pub const fn entries(&self) -> MadtEntries<'a> {
+        MadtEntries {
+            entries: self.entries,
+        }
+    }
+}
+
+/// Processor Local APIC structure (type 0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalApic {
+    /// ACPI processor UID
+    pub processor_id: u8,
+    pub apic_id: u8,
+    pub flags: u32,
+}
+
+impl LocalApic {
+    /// [`Self::flags`]: the processor is usable.
+    pub const ENABLED: u32 = 1;
+    /// [`Self::flags`]: the processor can be enabled at runtime.
+    pub const ONLINE_CAPABLE: u32 = 2;
+
+    pub const fn is_enabled(&self) -> bool {
+        self.flags & Self::ENABLED != 0
+    }
+}
+
+/// I/O APIC structure (type 1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoApic {
+    pub id: u8,
+    /// Physical address of the registers
+    pub address: u32,
+    /// First global system interrupt (input pin 0) of this I/O APIC
+    pub gsi_base: u32,
+}
+
+/// Interrupt Source Override structure (type 2): an ISA IRQ that is not
+/// wired to the same-numbered global system interrupt, or not edge-triggered
+/// active high.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptOverride {
+    /// 0: ISA
+    pub bus: u8,
+    /// ISA IRQ
+    pub source: u8,
+    /// Global system interrupt the IRQ is wired to
+    pub gsi: u32,
+    /// MPS INTI flags: polarity in bits 0-1, trigger mode in bits 2-3
+    pub flags: u16,
+}
+
+impl InterruptOverride {
+    /// Polarity: active low
+    const ACTIVE_LOW: u16 = 0b11;
+    /// Trigger mode: level
+    const LEVEL: u16 = 0b11 << 2;
+
+    /// Whether the line is active low (otherwise the bus default, active
+    /// high for ISA).
+    pub const fn is_active_low(&self) -> bool {
+        self.flags & Self::ACTIVE_LOW == Self::ACTIVE_LOW
+    }
+
+    /// Whether the line is level-triggered (otherwise the bus default,
+    /// edge-triggered for ISA).
+    pub const fn is_level_triggered(&self) -> bool {
+        self.flags & Self::LEVEL == Self::LEVEL
+    }
+}
+
+/// One interrupt controller structure of the [`Madt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MadtEntry {
+    LocalApic(LocalApic),
+    IoApic(IoApic),
+    InterruptOverride(InterruptOverride),
+    /// A structure of another type (NMI sources, x2APICs, ...)
+    Other {
+        ty: u8,
+    },
+}
+
+impl MadtEntry {
+    const LOCAL_APIC: u8 = 0;
+    const IO_APIC: u8 = 1;
+    const INTERRUPT_OVERRIDE: u8 = 2;
+
+    /// Parses one structure: its type, length and body in `entry`.
+    fn parse(entry: &[u8]) -> Result<Self, AcpiError> {
+        Ok(match entry[0] {
+            Self::LOCAL_APIC => Self::LocalApic(LocalApic {
+                processor_id: u8_at(entry, 2)?,
+                apic_id: u8_at(entry, 3)?,
+                flags: u32_at(entry, 4)?,
+            }),
+            Self::IO_APIC => Self::IoApic(IoApic {
+                id: u8_at(entry, 2)?,
+                address: u32_at(entry, 4)?,
+                gsi_base: u32_at(entry, 8)?,
+            }),
+            Self::INTERRUPT_OVERRIDE => Self::InterruptOverride(InterruptOverride {
+                bus: u8_at(entry, 2)?,
+                source: u8_at(entry, 3)?,
+                gsi: u32_at(entry, 4)?,
+                flags: u16_at(entry, 8)?,
+            }),
+            ty => Self::Other { ty },
+        })
+    }
+}
+
+/// Iterator over [`Madt::entries`]. Stops at the end of the table, or at
+/// the first structure that does not fit in it.
+#[derive(Debug, Clone)]
+pub struct MadtEntries<'a> {
+    entries: &'a [u8],
+}
+
+impl Iterator for MadtEntries<'_> {
+    type Item = MadtEntry;
+
+    fn next(&mut self) -> Option<MadtEntry> {
+        let len = usize::from(*self.entries.get(1)?);
+        let Some(entry) = self.entries.get(..len).filter(|_| len >= 2) else {
+            self.entries = &[];
+            return None;
+        };
+        self.entries = &self.entries[len..];
+        match MadtEntry::parse(entry) {
+            Ok(entry) => Some(entry),
+            Err(_) => {
+                self.entries = &[];
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::{firecracker, qemu, table};
+
+    #[test]
+    fn test_qemu() {
+        for capture in qemu() {
+            let madt = Madt::parse(&capture.apic).unwrap();
+            assert_eq!(madt.local_apic_address, 0xFEE0_0000);
+            assert_eq!(madt.flags, PCAT_COMPAT);
+
+            let entries: Vec<_> = madt.entries().collect();
+            // -smp 2
+            assert_eq!(
+                entries[..3],
+                [
+                    MadtEntry::LocalApic(LocalApic {
+                        processor_id: 0,
+                        apic_id: 0,
+                        flags: LocalApic::ENABLED,
+                    }),
+                    MadtEntry::LocalApic(LocalApic {
+                        processor_id: 1,
+                        apic_id: 1,
+                        flags: LocalApic::ENABLED,
+                    }),
+                    MadtEntry::IoApic(IoApic {
+                        id: 0,
+                        address: 0xFEC0_0000,
+                        gsi_base: 0,
+                    }),
+                ],
+                "{}",
+                capture.machine
+            );
+
+            let overrides: Vec<_> = entries
+                .iter()
+                .filter_map(|entry| match entry {
+                    MadtEntry::InterruptOverride(iso) => Some(iso),
+                    _ => None,
+                })
+                .collect();
+            // The PIT's IRQ 0 is wired to pin 2; the SCI is level triggered,
+            // active high.
+            assert_eq!((overrides[0].source, overrides[0].gsi), (0, 2));
+            assert!(!overrides[0].is_level_triggered());
+            let sci = overrides.iter().find(|iso| iso.source == 9).unwrap();
+            assert_eq!(sci.gsi, 9);
+            assert!(sci.is_level_triggered() && !sci.is_active_low());
+        }
+    }
+
+    #[test]
+    fn test_firecracker() {
+        // One vCPU, no 8259s and no interrupt source overrides.
+        let madt = Madt::parse(firecracker::APIC).unwrap();
+        assert_eq!(madt.local_apic_address, 0xFEE0_0000);
+        assert_eq!(madt.flags, 0);
+        assert_eq!(
+            madt.entries().collect::<Vec<_>>(),
+            [
+                MadtEntry::IoApic(IoApic {
+                    id: 0,
+                    address: 0xFEC0_0000,
+                    gsi_base: 0,
+                }),
+                MadtEntry::LocalApic(LocalApic {
+                    processor_id: 0,
+                    apic_id: 0,
+                    flags: LocalApic::ENABLED,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_short() {
+        assert_eq!(
+            Madt::parse(&table(*b"APIC", &[0; 6])),
+            Err(AcpiError::Length)
+        );
+    }
+
+    #[test]
+    fn test_truncated_entries() {
+        // A local APIC structure claiming 20 bytes, in a 16-byte table body.
+        let mut body = vec![0, 0, 0xFE, 0xFE, 0, 0, 0, 0];
+        body.extend_from_slice(&[0, 20, 0, 0, 1, 0, 0, 0]);
+        let bytes = table(*b"APIC", &body);
+        let madt = Madt::parse(&bytes).unwrap();
+        assert_eq!(madt.entries().count(), 0);
+
+        // Length 0 would never advance.
+        let mut body = vec![0; 8];
+        body.extend_from_slice(&[0, 0, 0, 0]);
+        let bytes = table(*b"APIC", &body);
+        assert_eq!(Madt::parse(&bytes).unwrap().entries().count(), 0);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{AcpiError, checksum_ok, u8_at, u32_at, u64_at};
+
+/// Root System Description Pointer: where the firmware put the RSDT (and, from
+/// ACPI 2.0 on, the XSDT).
+///
+/// ```txt
+///  0          8    9        15    16       20       24       32    33
+/// +----------+----+--------+-----+--------+--------+--------+-----+-----+
+/// |"RSD PTR "|csum| OEM ID | rev | RSDT   | length | XSDT   |xcsum| res |
+/// +----------+----+--------+-----+--------+--------+--------+-----+-----+
+///  \------------- ACPI 1.0: 20 bytes -------------/ \---- revision >= 2 ---/
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rsdp {
+    pub oem_id: [u8; 6],
+    /// 0 for ACPI 1.0, 2 from ACPI 2.0 on
+    pub revision: u8,
+    /// Physical address of the [`Rsdt`](crate::Rsdt)
+    pub rsdt_address: u32,
+    /// Physical address of the XSDT, which lists 64-bit table addresses
+    pub xsdt_address: Option<u64>,
+}
+
+impl Rsdp {
+    pub const SIGNATURE: [u8; 8] = *b"RSD PTR ";
+    /// Size of the ACPI 1.0 structure, covered by the first checksum
+    const V1_SIZE: usize = 20;
+    /// Size of the ACPI 2.0 structure
+    const V2_SIZE: usize = 36;
+    /// The RSDP is always on a 16-byte boundary.
+    pub const ALIGN: usize = 16;
+
+    /// Parses the RSDP at the start of `bytes`.
+    ///
+    /// # Errors
+    /// - [`AcpiError::Signature`] if `bytes` does not start with [`Self::SIGNATURE`].
+    /// - [`AcpiError::Truncated`] if the structure does not fit in `bytes`.
+    /// - [`AcpiError::Checksum`] if either checksum is wrong.
+    /// - [`AcpiError::Length`] if an ACPI 2.0 RSDP claims less than 36 bytes.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AcpiError> {
+        if !bytes.starts_with(&Self::SIGNATURE) {
+            return Err(AcpiError::Signature);
+        }
+        let v1 = bytes.get(..Self::V1_SIZE).ok_or(AcpiError::Truncated)?;
+        if !checksum_ok(v1) {
+            return Err(AcpiError::Checksum);
+        }
+        let mut oem_id = [0; 6];
+        oem_id.copy_from_slice(&v1[9..15]);
+        let revision = u8_at(v1, 15)?;
+        let rsdt_address = u32_at(v1, 16)?;
+
+        let xsdt_address = if revision >= 2 {
+            let length = u32_at(bytes, 20)? as usize;
+            if length < Self::V2_SIZE {
+                return Err(AcpiError::Length);
+            }
+            let v2 = bytes.get(..length).ok_or(AcpiError::Truncated)?;
+            if !checksum_ok(v2) {
+                return Err(AcpiError::Checksum);
+            }
+            Some(u64_at(v2, 24)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            oem_id,
+            revision,
+            rsdt_address,
+            xsdt_address,
+        })
+    }
+
+    /// Size of the structure: the ACPI 1.0 part, plus the 2.0 fields if any.
+    pub const fn size(&self) -> usize {
+        if self.xsdt_address.is_some() {
+            Self::V2_SIZE
+        } else {
+            Self::V1_SIZE
+        }
+    }
+
+    /// Scans `region` on 16-byte boundaries for a valid RSDP, and returns its
+    /// offset in `region` along with it.
+    pub fn search(region: &[u8]) -> Option<(usize, Self)> {
+        (0..region.len())
+            .step_by(Self::ALIGN)
+            .find_map(|offset| Some((offset, Self::parse(&region[offset..]).ok()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::{RSDT_ADDR, qemu, rsdp_v1, rsdp_v2};
+
+    #[test]
+    fn test_qemu() {
+        // SeaBIOS: ACPI 1.0, so an RSDT and no XSDT.
+        for capture in qemu() {
+            let rsdp = Rsdp::parse(&capture.rsdp).unwrap();
+            assert_eq!(&rsdp.oem_id, b"BOCHS ");
+            assert_eq!(rsdp.revision, 0);
+            assert_ne!(rsdp.rsdt_address, 0);
+            assert_eq!(rsdp.xsdt_address, None);
+            assert_eq!(rsdp.size(), capture.rsdp.len());
+        }
+    }
+
+    #[test]
+    fn test_parse_v2() {
+        let rsdp = Rsdp::parse(&rsdp_v2()).unwrap();
+        assert_eq!(rsdp.revision, 2);
+        assert_eq!(rsdp.xsdt_address, Some(0x07FE_1A00));
+        assert_eq!(rsdp.size(), 36);
+
+        // The ACPI 1.0 part checks out alone, the extended checksum does not.
+        let mut bytes = rsdp_v2();
+        bytes[32] ^= 1;
+        assert_eq!(Rsdp::parse(&bytes), Err(AcpiError::Checksum));
+        assert_eq!(Rsdp::parse(&rsdp_v2()[..30]), Err(AcpiError::Truncated));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut bytes = rsdp_v1();
+        bytes[8] ^= 1;
+        assert_eq!(Rsdp::parse(&bytes), Err(AcpiError::Checksum));
+        assert_eq!(Rsdp::parse(&rsdp_v1()[..19]), Err(AcpiError::Truncated));
+        assert_eq!(Rsdp::parse(b"RSD PT"), Err(AcpiError::Signature));
+    }
+
+    #[test]
+    fn test_search() {
+        // The BIOS area of a QEMU pc machine, with the RSDP at 0xF58A0.
+        let mut region = vec![0; 0x1000];
+        let rsdp = rsdp_v1();
+        // Off the 16-byte grid: ignored.
+        region[0x108..0x108 + rsdp.len()].copy_from_slice(&rsdp);
+        region[0x8A0..0x8A0 + rsdp.len()].copy_from_slice(&rsdp);
+
+        let (offset, found) = Rsdp::search(&region).unwrap();
+        assert_eq!(offset, 0x8A0);
+        assert_eq!(found.rsdt_address, RSDT_ADDR);
+        assert_eq!(Rsdp::search(&region[..0x8A0]), None);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{AcpiError, bytes, checksum_ok, u8_at, u32_at};
+
+/// Header common to all System Description Tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SdtHeader {
+    /// e.g. `APIC` for the MADT, `FACP` for the FADT
+    pub signature: [u8; 4],
+    /// Length of the whole table, header included
+    pub length: u32,
+    pub revision: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: [u8; 4],
+    pub creator_revision: u32,
+}
+
+impl SdtHeader {
+    pub const SIZE: usize = 36;
+
+    /// Parses the header at the start of `bytes`, without checking the
+    /// table. Used to learn how much memory the whole table takes.
+    ///
+    /// # Errors
+    /// [`AcpiError::Truncated`] if `bytes` is shorter than a header.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AcpiError> {
+        let header = bytes.get(..Self::SIZE).ok_or(AcpiError::Truncated)?;
+        Ok(Self {
+            signature: self::bytes(header, 0)?,
+            length: u32_at(header, 4)?,
+            revision: u8_at(header, 8)?,
+            oem_id: self::bytes(header, 10)?,
+            oem_table_id: self::bytes(header, 16)?,
+            oem_revision: u32_at(header, 24)?,
+            creator_id: self::bytes(header, 28)?,
+            creator_revision: u32_at(header, 32)?,
+        })
+    }
+
+    /// [`Self::length`] as a slice length.
+    pub const fn size(&self) -> usize {
+        self.length as usize
+    }
+}
+
+/// A checked System Description Table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sdt<'a> {
+    pub header: SdtHeader,
+    /// The table after its header
+    pub data: &'a [u8],
+}
+
+impl<'a> Sdt<'a> {
+    /// Parses the table at the start of `bytes`. Bytes past its length are
+    /// ignored.
+    ///
+    /// # Errors
+    /// - [`AcpiError::Truncated`] if the table does not fit in `bytes`.
+    /// - [`AcpiError::Length`] if the length is shorter than the header.
+    /// - [`AcpiError::Checksum`] if the table's bytes do not add up to 0.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, AcpiError> {
+        let header = SdtHeader::parse(bytes)?;
+        if header.size() < SdtHeader::SIZE {
+            return Err(AcpiError::Length);
+        }
+        let table = bytes.get(..header.size()).ok_or(AcpiError::Truncated)?;
+        if !checksum_ok(table) {
+            return Err(AcpiError::Checksum);
+        }
+        Ok(Self {
+            header,
+            data: &table[SdtHeader::SIZE..],
+        })
+    }
+
+    /// Like [`Self::parse`], and checks that the table is a `signature` one.
+    ///
+    /// # Errors
+    /// Same as [`Self::parse`], plus [`AcpiError::Signature`].
+    pub fn parse_as(bytes: &'a [u8], signature: [u8; 4]) -> Result<Self, AcpiError> {
+        if !bytes.starts_with(&signature) {
+            return Err(AcpiError::Signature);
+        }
+        Self::parse(bytes)
+    }
+}
+
+/// Root System Description Table: the physical addresses of all other
+/// tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rsdt<'a> {
+    pub header: SdtHeader,
+    entries: &'a [u8],
+}
+
+impl<'a> Rsdt<'a> {
+    pub const SIGNATURE: [u8; 4] = *b"RSDT";
+
+    /// Parses the RSDT at the start of `bytes`.
+    ///
+    /// # Errors
+    /// Same as [`Sdt::parse_as`].
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, AcpiError> {
+        let Sdt { header, data } = Sdt::parse_as(bytes, Self::SIGNATURE)?;
+        Ok(Self {
+            header,
+            entries: data,
+        })
+    }
+
+    /// Physical addresses of the tables.
+    pub fn entries(&self) -> impl Iterator<Item = u32> + 'a {
+        self.entries
+            .chunks_exact(4)
+            .map(|entry| u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata::{qemu, table};
+
+    #[test]
+    fn test_header() {
+        for capture in qemu() {
+            let header = SdtHeader::parse(&capture.apic).unwrap();
+            assert_eq!(&header.signature, b"APIC");
+            assert_eq!(header.size(), capture.apic.len());
+            assert_eq!(&header.oem_id, b"BOCHS ");
+            assert_eq!(&header.creator_id, b"BXPC");
+            assert_eq!(
+                SdtHeader::parse(&capture.apic[..35]),
+                Err(AcpiError::Truncated)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sdt() {
+        let mut bytes = table(*b"APIC", &[0xA5; 0x5C]);
+        let data_len = Sdt::parse(&bytes).unwrap().data.len();
+        assert_eq!(data_len, 0x5C);
+
+        // Trailing bytes belong to the next table.
+        bytes.extend_from_slice(b"FACP");
+        assert_eq!(Sdt::parse(&bytes).unwrap().data.len(), data_len);
+
+        assert_eq!(Sdt::parse(&bytes[..0x7F]), Err(AcpiError::Truncated));
+        assert_eq!(Sdt::parse_as(&bytes, *b"FACP"), Err(AcpiError::Signature));
+        bytes[0x40] ^= 1;
+        assert_eq!(Sdt::parse(&bytes), Err(AcpiError::Checksum));
+
+        let mut bytes = table(*b"APIC", &[0xA5; 0x5C]);
+        bytes[4..8].copy_from_slice(&8u32.to_le_bytes());
+        assert_eq!(Sdt::parse(&bytes), Err(AcpiError::Length));
+    }
+
+    #[test]
+    fn test_rsdt() {
+        // FACP, APIC, and HPET and WAET or MCFG, depending on the machine.
+        for capture in qemu() {
+            let rsdt = Rsdt::parse(&capture.rsdt).unwrap();
+            assert_eq!(rsdt.header.size(), capture.rsdt.len());
+            assert!(rsdt.entries().count() >= 2, "{}", capture.machine);
+            assert!(rsdt.entries().all(|pa| pa != 0));
+            for bytes in [&capture.apic, &capture.facp, &capture.dsdt] {
+                assert!(Sdt::parse(bytes).is_ok(), "{}", capture.machine);
+            }
+        }
+        assert_eq!(
+            Rsdt::parse(&table(*b"APIC", &[0; 8])),
+            Err(AcpiError::Signature)
+        );
+    }
+}
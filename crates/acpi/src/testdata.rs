@@ -0,0 +1,116 @@
+//! Tables captured from virtual machines, and hand-made ones for the
+//! malformed inputs no firmware produces.
+//!
+//! The QEMU captures are made with `cargo xtask acpi-tables`, which boots
+//! the kernel with its `acpi-dump` feature on each machine of
+//! [`QEMU_MACHINES`] with `-smp 2 -m 128M`.
+
+use std::path::Path;
+
+use crate::SdtHeader;
+
+/// Where the hand-made RSDP says the RSDT is.
+pub const RSDT_ADDR: u32 = 0x07FE_19C0;
+const XSDT_ADDR: u64 = 0x07FE_1A00;
+
+/// Makes the bytes of `bytes` add up to 0 by adjusting the one at `at`.
+fn fix_checksum(bytes: &mut [u8], at: usize) {
+    bytes[at] = 0;
+    let sum = bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+    bytes[at] = sum.wrapping_neg();
+}
+
+/// A table with QEMU's header fields around `body`.
+pub fn table(signature: [u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&signature);
+    bytes.extend_from_slice(&((SdtHeader::SIZE + body.len()) as u32).to_le_bytes());
+    bytes.push(1); // revision
+    bytes.push(0); // checksum
+    bytes.extend_from_slice(b"BOCHS ");
+    bytes.extend_from_slice(b"BXPC    ");
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(b"BXPC");
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(body);
+    fix_checksum(&mut bytes, 9);
+    bytes
+}
+
+pub fn rsdp_v1() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RSD PTR ");
+    bytes.push(0); // checksum
+    bytes.extend_from_slice(b"BOCHS ");
+    bytes.push(0); // revision
+    bytes.extend_from_slice(&RSDT_ADDR.to_le_bytes());
+    fix_checksum(&mut bytes, 8);
+    bytes
+}
+
+/// What the `q35` machine and UEFI firmware provide.
+pub fn rsdp_v2() -> Vec<u8> {
+    let mut bytes = rsdp_v1();
+    bytes[15] = 2;
+    bytes.extend_from_slice(&36u32.to_le_bytes());
+    bytes.extend_from_slice(&XSDT_ADDR.to_le_bytes());
+    bytes.extend_from_slice(&[0; 4]); // extended checksum, reserved
+    fix_checksum(&mut bytes[..20], 8);
+    fix_checksum(&mut bytes, 32);
+    bytes
+}
+
+/// Tables dumped from `/sys/firmware/acpi/tables` in a Firecracker microVM
+/// with one vCPU: a hardware-reduced ACPI 6 machine, reached through an XSDT
+/// only, with no PM1 blocks and no `\_S5`.
+///
+/// QEMU `pc` and `q35` captures belong next to these in `testdata/`.
+pub mod firecracker {
+    pub const APIC: &[u8] = include_bytes!("../testdata/firecracker/APIC");
+    pub const DSDT: &[u8] = include_bytes!("../testdata/firecracker/DSDT");
+    pub const FACP: &[u8] = include_bytes!("../testdata/firecracker/FACP");
+}
+
+/// QEMU machines captured in `testdata/qemu-<machine>`.
+pub const QEMU_MACHINES: [&str; 2] = ["pc", "q35"];
+
+/// The tables of one QEMU machine, each in a file named after its signature.
+pub struct Capture {
+    pub machine: &'static str,
+    pub rsdp: Vec<u8>,
+    pub rsdt: Vec<u8>,
+    pub apic: Vec<u8>,
+    pub facp: Vec<u8>,
+    pub dsdt: Vec<u8>,
+}
+
+/// The QEMU captures in `testdata/`. A machine not captured yet is reported
+/// and left out.
+pub fn qemu() -> Vec<Capture> {
+    QEMU_MACHINES
+        .into_iter()
+        .filter_map(|machine| {
+            let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("testdata")
+                .join(format!("qemu-{machine}"));
+            if !dir.is_dir() {
+                eprintln!(
+                    "no ACPI tables of QEMU `{machine}`; run `cargo xtask acpi-tables` to capture them"
+                );
+                return None;
+            }
+            let read = |name: &str| {
+                std::fs::read(dir.join(name))
+                    .unwrap_or_else(|err| panic!("qemu-{machine}/{name}: {err}"))
+            };
+            Some(Capture {
+                machine,
+                rsdp: read("RSDP"),
+                rsdt: read("RSDT"),
+                apic: read("APIC"),
+                facp: read("FACP"),
+                dsdt: read("DSDT"),
+            })
+        })
+        .collect()
+}
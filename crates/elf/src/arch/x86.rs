@@ -1,11 +1,33 @@
 /// Magic number identifying an ELF file (`0x7F + "ELF"` in little endian).
 pub const ELF_MAGIC: u32 = 0x464C457F;
 
+/// Indices into `ident`, which starts after the magic number: the standard
+/// `EI_CLASS`, `EI_DATA` and `EI_VERSION` minus 4.
+pub const IDENT_CLASS: usize = 0;
+pub const IDENT_DATA: usize = 1;
+pub const IDENT_VERSION: usize = 2;
+
+/// File class (`ident[IDENT_CLASS]`): 32-bit objects.
+pub const ELFCLASS32: u8 = 1;
+/// Data encoding (`ident[IDENT_DATA]`): two's complement, little endian.
+pub const ELFDATA2LSB: u8 = 1;
+/// Current version, of both `ident[IDENT_VERSION]` and `version`.
+pub const EV_CURRENT: u32 = 1;
+
+/// Object file types.
+pub const ET_REL: u16 = 1;
+pub const ET_EXEC: u16 = 2;
+pub const ET_DYN: u16 = 3;
+pub const ET_CORE: u16 = 4;
+
+/// Machine: Intel 80386.
+pub const EM_386: u16 = 3;
+
 /// ELF file header.
 ///
 /// Appears at the beginning of every ELF binary.
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct ElfHeader {
     /// Magic number (must be `ELF_MAGIC`)
     pub magic: u32,
@@ -44,7 +66,7 @@ const _: () = assert!(core::mem::size_of::<ElfHeader>() == 52);
 ///
 /// Describes a single segment to be loaded into memory.
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct ProgramHeader {
     /// Segment type (e.g., 1 = LOAD)
     pub segment_type: u32,
@@ -65,10 +87,109 @@ pub struct ProgramHeader {
 }
 const _: () = assert!(core::mem::size_of::<ProgramHeader>() == 32);
 
-/// Segment type for program headers.
+/// Segment types for program headers.
+pub const ELF_PROG_NULL: u32 = 0;
 pub const ELF_PROG_LOAD: u32 = 1;
+pub const ELF_PROG_DYNAMIC: u32 = 2;
+pub const ELF_PROG_INTERP: u32 = 3;
+pub const ELF_PROG_NOTE: u32 = 4;
+pub const ELF_PROG_PHDR: u32 = 6;
+pub const ELF_PROG_TLS: u32 = 7;
+pub const ELF_PROG_GNU_STACK: u32 = 0x6474_E551;
 
 /// Segment permission flags.
 pub const ELF_PROG_FLAG_EXEC: u32 = 1;
 pub const ELF_PROG_FLAG_WRITE: u32 = 2;
 pub const ELF_PROG_FLAG_READ: u32 = 4;
+
+/// ELF Section Header.
+///
+/// Describes one section (e.g. `.text`, `.symtab`) of the file.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SectionHeader {
+    /// Offset of the section name in the section name string table
+    pub name: u32,
+    /// Section type (e.g., 2 = SYMTAB)
+    pub section_type: u32,
+    /// Section flags (e.g., writable, allocated, executable)
+    pub flags: u32,
+    /// Virtual address of the section in memory (0 if not loaded)
+    pub address: u32,
+    /// Offset of the section in file
+    pub offset: u32,
+    /// Size of the section in bytes
+    pub size: u32,
+    /// Section index link; for a symbol table, its string table
+    pub link: u32,
+    /// Extra information depending on the section type
+    pub info: u32,
+    /// Required alignment of the section
+    pub alignment: u32,
+    /// Size of each entry for sections holding a table (e.g., 16 for SYMTAB)
+    pub entry_size: u32,
+}
+const _: () = assert!(core::mem::size_of::<SectionHeader>() == 40);
+
+/// Section types.
+pub const SHT_NULL: u32 = 0;
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_NOTE: u32 = 7;
+pub const SHT_NOBITS: u32 = 8;
+
+/// Note types of core files.
+pub const NT_PRSTATUS: u32 = 1;
+pub const NT_PRPSINFO: u32 = 3;
+
+/// Section flags.
+pub const SHF_WRITE: u32 = 1;
+pub const SHF_ALLOC: u32 = 2;
+pub const SHF_EXECINSTR: u32 = 4;
+
+/// Section index of undefined symbols.
+pub const SHN_UNDEF: u16 = 0;
+
+/// ELF Symbol table entry.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Symbol {
+    /// Offset of the symbol name in the linked string table
+    pub name: u32,
+    /// Address of the symbol
+    pub value: u32,
+    /// Size of the object or function (0 if unknown)
+    pub size: u32,
+    /// Type (low 4 bits) and binding (high 4 bits)
+    pub info: u8,
+    /// Visibility
+    pub other: u8,
+    /// Index of the section the symbol is defined in (0 = undefined)
+    pub section_index: u16,
+}
+const _: () = assert!(core::mem::size_of::<Symbol>() == 16);
+
+impl Symbol {
+    /// Symbol type (`STT_*`).
+    pub const fn symbol_type(&self) -> u8 {
+        self.info & 0xF
+    }
+
+    /// Symbol binding (`STB_*`).
+    pub const fn binding(&self) -> u8 {
+        self.info >> 4
+    }
+}
+
+/// Symbol types.
+pub const STT_NOTYPE: u8 = 0;
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+pub const STT_SECTION: u8 = 3;
+pub const STT_FILE: u8 = 4;
+
+/// Symbol bindings.
+pub const STB_LOCAL: u8 = 0;
+pub const STB_GLOBAL: u8 = 1;
+pub const STB_WEAK: u8 = 2;
@@ -0,0 +1,563 @@
+//! Validated view of an i386 ELF32 file held in memory.
+//!
+//! [`ElfFile::parse`] checks the file header and that the program and section
+//! header tables, and the file contents of every segment, lie inside the
+//! file. After that, nothing reads out of bounds or panics, whatever the
+//! bytes. Its [`sections`](ElfFile::sections) and
+//! [`symbols`](ElfFile::symbols) are read through [`symtab`](crate::symtab).
+
+use core::{error, fmt};
+
+use crate::arch::x86::{
+    ELF_MAGIC, ELF_PROG_DYNAMIC, ELF_PROG_FLAG_EXEC, ELF_PROG_FLAG_READ, ELF_PROG_FLAG_WRITE,
+    ELF_PROG_GNU_STACK, ELF_PROG_INTERP, ELF_PROG_LOAD, ELF_PROG_NOTE, ELF_PROG_NULL,
+    ELF_PROG_PHDR, ELF_PROG_TLS, ELFCLASS32, ELFDATA2LSB, EM_386, ET_CORE, ET_DYN, ET_EXEC, ET_REL,
+    EV_CURRENT, ElfHeader, IDENT_CLASS, IDENT_DATA, IDENT_VERSION, ProgramHeader, SectionHeader,
+};
+use crate::note::Notes;
+use crate::pod::read;
+use crate::symtab::{Sections, SymbolTable};
+
+/// Why a file was rejected by [`ElfFile::parse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    /// The file is shorter than the ELF header.
+    Truncated,
+    /// The file does not start with [`ELF_MAGIC`].
+    BadMagic,
+    /// Not a 32-bit file.
+    BadClass(u8),
+    /// Not little endian.
+    BadEncoding(u8),
+    /// Unknown ELF version.
+    BadVersion(u32),
+    /// Not for the i386.
+    BadMachine(u16),
+    /// Unknown object file type.
+    BadType(u16),
+    /// `header_size` is not the size of [`ElfHeader`].
+    BadHeaderSize(u16),
+    /// Program header entries are smaller than [`ProgramHeader`].
+    BadProgramHeaderSize(u16),
+    /// Section header entries are smaller than [`SectionHeader`].
+    BadSectionHeaderSize(u16),
+    /// The program header table does not fit in the file.
+    ProgramHeadersOutOfBounds,
+    /// The section header table does not fit in the file.
+    SectionHeadersOutOfBounds,
+    /// The contents of segment `index` do not fit in the file.
+    SegmentOutOfBounds { index: usize },
+    /// Loadable segment `index` is smaller in memory than in the file, or
+    /// ends past the address space.
+    BadSegment { index: usize },
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Truncated => write!(f, "file too short for an ELF header"),
+            Self::BadMagic => write!(f, "not an ELF file"),
+            Self::BadClass(class) => write!(f, "not an ELF32 file (class {class})"),
+            Self::BadEncoding(data) => write!(f, "not little endian (encoding {data})"),
+            Self::BadVersion(version) => write!(f, "unknown ELF version {version}"),
+            Self::BadMachine(machine) => write!(f, "not an i386 file (machine {machine})"),
+            Self::BadType(file_type) => write!(f, "unknown file type {file_type}"),
+            Self::BadHeaderSize(size) => write!(f, "bad ELF header size {size}"),
+            Self::BadProgramHeaderSize(size) => write!(f, "bad program header size {size}"),
+            Self::BadSectionHeaderSize(size) => write!(f, "bad section header size {size}"),
+            Self::ProgramHeadersOutOfBounds => write!(f, "program headers out of bounds"),
+            Self::SectionHeadersOutOfBounds => write!(f, "section headers out of bounds"),
+            Self::SegmentOutOfBounds { index } => write!(f, "segment {index} out of bounds"),
+            Self::BadSegment { index } => write!(f, "bad segment {index}"),
+        }
+    }
+}
+
+impl error::Error for ElfError {}
+
+/// Object file type (`file_type`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Relocatable,
+    Executable,
+    SharedObject,
+    Core,
+}
+
+impl FileType {
+    pub const fn from_raw(raw: u16) -> Option<Self> {
+        match raw {
+            ET_REL => Some(Self::Relocatable),
+            ET_EXEC => Some(Self::Executable),
+            ET_DYN => Some(Self::SharedObject),
+            ET_CORE => Some(Self::Core),
+            _ => None,
+        }
+    }
+}
+
+/// Segment type (`segment_type`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentType {
+    Null,
+    Load,
+    Dynamic,
+    Interp,
+    Note,
+    Phdr,
+    Tls,
+    GnuStack,
+    Other(u32),
+}
+
+impl SegmentType {
+    pub const fn from_raw(raw: u32) -> Self {
+        match raw {
+            ELF_PROG_NULL => Self::Null,
+            ELF_PROG_LOAD => Self::Load,
+            ELF_PROG_DYNAMIC => Self::Dynamic,
+            ELF_PROG_INTERP => Self::Interp,
+            ELF_PROG_NOTE => Self::Note,
+            ELF_PROG_PHDR => Self::Phdr,
+            ELF_PROG_TLS => Self::Tls,
+            ELF_PROG_GNU_STACK => Self::GnuStack,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// Segment permissions (`flags`): `ELF_PROG_FLAG_*` bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentFlags(pub u32);
+
+impl SegmentFlags {
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(self, flag: u32) -> bool {
+        self.0 & flag == flag
+    }
+
+    pub const fn readable(self) -> bool {
+        self.contains(ELF_PROG_FLAG_READ)
+    }
+
+    pub const fn writable(self) -> bool {
+        self.contains(ELF_PROG_FLAG_WRITE)
+    }
+
+    pub const fn executable(self) -> bool {
+        self.contains(ELF_PROG_FLAG_EXEC)
+    }
+}
+
+/// A segment: its program header and its contents in the file.
+#[derive(Debug, Clone, Copy)]
+pub struct Segment<'a> {
+    header: ProgramHeader,
+    data: &'a [u8],
+}
+
+impl<'a> Segment<'a> {
+    pub const fn header(&self) -> &ProgramHeader {
+        &self.header
+    }
+
+    pub const fn segment_type(&self) -> SegmentType {
+        SegmentType::from_raw(self.header.segment_type)
+    }
+
+    pub const fn flags(&self) -> SegmentFlags {
+        SegmentFlags(self.header.flags)
+    }
+
+    pub const fn virtual_address(&self) -> u32 {
+        self.header.virtual_address
+    }
+
+    /// Size in memory, at least `data().len()` for a loadable segment.
+    pub const fn memory_size(&self) -> u32 {
+        self.header.memory_size
+    }
+
+    /// The `file_size` bytes at `offset` in the file.
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Iterates over the notes of a note segment.
+    pub const fn notes(&self) -> Notes<'a> {
+        Notes::new(self.data)
+    }
+}
+
+/// A parsed ELF file.
+#[derive(Debug, Clone, Copy)]
+pub struct ElfFile<'a> {
+    data: &'a [u8],
+    header: ElfHeader,
+}
+
+impl<'a> ElfFile<'a> {
+    /// Validates the i386 ELF32 file in `data`.
+    ///
+    /// # Errors
+    ///
+    /// If the header is not that of a little-endian i386 ELF32 file of a
+    /// known type, or a header table or segment lies outside `data`.
+    pub fn parse(data: &'a [u8]) -> Result<Self, ElfError> {
+        let header: ElfHeader = read(data, 0).ok_or(ElfError::Truncated)?;
+        if header.magic != ELF_MAGIC {
+            return Err(ElfError::BadMagic);
+        }
+        if header.ident[IDENT_CLASS] != ELFCLASS32 {
+            return Err(ElfError::BadClass(header.ident[IDENT_CLASS]));
+        }
+        if header.ident[IDENT_DATA] != ELFDATA2LSB {
+            return Err(ElfError::BadEncoding(header.ident[IDENT_DATA]));
+        }
+        if u32::from(header.ident[IDENT_VERSION]) != EV_CURRENT {
+            return Err(ElfError::BadVersion(header.ident[IDENT_VERSION].into()));
+        }
+        if header.version != EV_CURRENT {
+            return Err(ElfError::BadVersion(header.version));
+        }
+        if header.machine != EM_386 {
+            return Err(ElfError::BadMachine(header.machine));
+        }
+        if FileType::from_raw(header.file_type).is_none() {
+            return Err(ElfError::BadType(header.file_type));
+        }
+        if usize::from(header.header_size) != size_of::<ElfHeader>() {
+            return Err(ElfError::BadHeaderSize(header.header_size));
+        }
+
+        if header.program_header_count != 0 {
+            let size = header.program_header_entry_size;
+            if usize::from(size) < size_of::<ProgramHeader>() {
+                return Err(ElfError::BadProgramHeaderSize(size));
+            }
+            let table = (
+                header.program_header_offset,
+                size,
+                header.program_header_count,
+            );
+            if table_bounds(data, table).is_none() {
+                return Err(ElfError::ProgramHeadersOutOfBounds);
+            }
+        }
+        if header.section_header_count != 0 {
+            let size = header.section_header_entry_size;
+            if usize::from(size) < size_of::<SectionHeader>() {
+                return Err(ElfError::BadSectionHeaderSize(size));
+            }
+            let table = (
+                header.section_header_offset,
+                size,
+                header.section_header_count,
+            );
+            if table_bounds(data, table).is_none() {
+                return Err(ElfError::SectionHeadersOutOfBounds);
+            }
+        }
+
+        let file = Self { data, header };
+        for index in 0..file.program_header_count() {
+            let ph = file
+                .program_header(index)
+                .ok_or(ElfError::ProgramHeadersOutOfBounds)?;
+            if segment_data(data, &ph).is_none() {
+                return Err(ElfError::SegmentOutOfBounds { index });
+            }
+            if ph.segment_type == ELF_PROG_LOAD
+                && (ph.memory_size < ph.file_size
+                    || ph.virtual_address.checked_add(ph.memory_size).is_none())
+            {
+                return Err(ElfError::BadSegment { index });
+            }
+        }
+        Ok(file)
+    }
+
+    /// The whole file.
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub const fn header(&self) -> &ElfHeader {
+        &self.header
+    }
+
+    pub const fn file_type(&self) -> Option<FileType> {
+        FileType::from_raw(self.header.file_type)
+    }
+
+    /// Virtual address of the entry point.
+    pub const fn entry(&self) -> u32 {
+        self.header.entry
+    }
+
+    pub fn program_header_count(&self) -> usize {
+        self.header.program_header_count.into()
+    }
+
+    /// The program header at `index`, if any.
+    pub fn program_header(&self, index: usize) -> Option<ProgramHeader> {
+        if index >= self.program_header_count() {
+            return None;
+        }
+        let entry_size = usize::from(self.header.program_header_entry_size);
+        let offset = (self.header.program_header_offset as usize)
+            .checked_add(index.checked_mul(entry_size)?)?;
+        read(self.data, offset)
+    }
+
+    /// Iterates over the section headers.
+    pub const fn sections(&self) -> Sections<'a> {
+        Sections::from_header(self.data, &self.header)
+    }
+
+    /// Returns the symbol table, or `None` if the file has none, e.g.
+    /// because it was stripped.
+    pub fn symbols(&self) -> Option<SymbolTable<'a>> {
+        SymbolTable::from_sections(&self.sections())
+    }
+
+    /// Iterates over the segments, in program header order.
+    pub fn segments(&self) -> Segments<'a> {
+        Segments {
+            file: *self,
+            index: 0,
+        }
+    }
+}
+
+/// Iterator over the segments of an [`ElfFile`].
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    file: ElfFile<'a>,
+    index: usize,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Segment<'a>> {
+        let header = self.file.program_header(self.index)?;
+        self.index += 1;
+        Some(Segment {
+            header,
+            // Checked by `parse`.
+            data: segment_data(self.file.data, &header).unwrap_or_default(),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.file.program_header_count().saturating_sub(self.index);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Segments<'_> {}
+
+/// Returns the bytes of the table `(offset, entry_size, count)`, if it fits
+/// in `data`.
+fn table_bounds(data: &[u8], (offset, entry_size, count): (u32, u16, u16)) -> Option<&[u8]> {
+    let start = offset as usize;
+    let len = usize::from(entry_size).checked_mul(count.into())?;
+    data.get(start..start.checked_add(len)?)
+}
+
+fn segment_data<'a>(data: &'a [u8], ph: &ProgramHeader) -> Option<&'a [u8]> {
+    let start = ph.offset as usize;
+    data.get(start..start.checked_add(ph.file_size as usize)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pod::bytes;
+    use crate::writer::ElfBuilder;
+
+    const TEXT: &[u8] = &[0x90; 0x20];
+    const DATA: &[u8] = &[0xAA; 0x10];
+    const RX: u32 = ELF_PROG_FLAG_READ | ELF_PROG_FLAG_EXEC;
+    const RW: u32 = ELF_PROG_FLAG_READ | ELF_PROG_FLAG_WRITE;
+
+    /// An executable with a text and a data segment.
+    fn executable() -> ElfBuilder<'static> {
+        ElfBuilder::new(FileType::Executable)
+            .entry(0x10)
+            .load(0, RX, TEXT, 0x20)
+            .load(0x1000, RW, DATA, 0x30)
+    }
+
+    /// Rewrites the file header of `image`, for the layout fields the
+    /// builder fills in.
+    fn patch_header(image: &mut [u8], f: impl FnOnce(&mut ElfHeader)) {
+        let mut header: ElfHeader = read(image, 0).unwrap();
+        f(&mut header);
+        image[..size_of::<ElfHeader>()].copy_from_slice(bytes(&header));
+    }
+
+    /// Rewrites the header of segment `index` of `image`.
+    fn patch_segment(image: &mut [u8], index: usize, f: impl FnOnce(&mut ProgramHeader)) {
+        let header: ElfHeader = read(image, 0).unwrap();
+        let offset = header.program_header_offset as usize + index * size_of::<ProgramHeader>();
+        let mut ph: ProgramHeader = read(image, offset).unwrap();
+        f(&mut ph);
+        image[offset..offset + size_of::<ProgramHeader>()].copy_from_slice(bytes(&ph));
+    }
+
+    #[test]
+    fn test_parse() {
+        let image = executable().build();
+        let file = ElfFile::parse(&image).unwrap();
+        assert_eq!(file.file_type(), Some(FileType::Executable));
+        assert_eq!(file.entry(), 0x10);
+
+        let segments: Vec<_> = file.segments().collect();
+        assert_eq!(segments.len(), 2);
+        let (text, data) = (segments[0], segments[1]);
+        assert_eq!(text.segment_type(), SegmentType::Load);
+        assert!(text.flags().readable() && text.flags().executable());
+        assert!(!text.flags().writable());
+        assert_eq!(text.data(), TEXT);
+        assert_eq!(data.virtual_address(), 0x1000);
+        assert_eq!(data.memory_size(), 0x30);
+        assert!(data.flags().writable() && !data.flags().executable());
+    }
+
+    #[test]
+    fn test_bad_header() {
+        assert_eq!(ElfFile::parse(&[0; 10]).unwrap_err(), ElfError::Truncated);
+        assert_eq!(ElfFile::parse(&[0; 64]).unwrap_err(), ElfError::BadMagic);
+
+        type Patch = fn(&mut ElfHeader);
+        let cases: [(Patch, ElfError); 5] = [
+            (|h| h.ident[IDENT_CLASS] = 2, ElfError::BadClass(2)),
+            (|h| h.ident[IDENT_DATA] = 2, ElfError::BadEncoding(2)),
+            (|h| h.ident[IDENT_VERSION] = 0, ElfError::BadVersion(0)),
+            (|h| h.file_type = 0, ElfError::BadType(0)),
+            (|h| h.machine = 0x3E, ElfError::BadMachine(0x3E)),
+        ];
+        for (patch, err) in cases {
+            let image = executable().header(patch).build();
+            assert_eq!(ElfFile::parse(&image).unwrap_err(), err);
+        }
+
+        let mut image = executable().build();
+        patch_header(&mut image, |h| h.program_header_entry_size = 16);
+        assert_eq!(
+            ElfFile::parse(&image).unwrap_err(),
+            ElfError::BadProgramHeaderSize(16)
+        );
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let mut image = executable().build();
+        let len = image.len() as u32;
+        patch_header(&mut image, |h| h.program_header_offset = len - 0x10);
+        assert_eq!(
+            ElfFile::parse(&image).unwrap_err(),
+            ElfError::ProgramHeadersOutOfBounds
+        );
+
+        let mut image = executable().build();
+        patch_header(&mut image, |h| h.program_header_count = u16::MAX);
+        assert_eq!(
+            ElfFile::parse(&image).unwrap_err(),
+            ElfError::ProgramHeadersOutOfBounds
+        );
+
+        let mut image = executable().build();
+        patch_header(&mut image, |h| {
+            h.section_header_count = 1;
+            h.section_header_offset = u32::MAX;
+            h.section_header_entry_size = size_of::<SectionHeader>() as u16;
+        });
+        assert_eq!(
+            ElfFile::parse(&image).unwrap_err(),
+            ElfError::SectionHeadersOutOfBounds
+        );
+
+        let mut image = executable().build();
+        patch_segment(&mut image, 1, |ph| ph.file_size = 0x20);
+        assert_eq!(
+            ElfFile::parse(&image).unwrap_err(),
+            ElfError::SegmentOutOfBounds { index: 1 }
+        );
+
+        let mut image = executable().build();
+        patch_segment(&mut image, 1, |ph| ph.offset = u32::MAX);
+        assert_eq!(
+            ElfFile::parse(&image).unwrap_err(),
+            ElfError::SegmentOutOfBounds { index: 1 }
+        );
+
+        let image = executable().build();
+        assert!(
+            ElfFile::parse(&image[..image.len() - 1]).is_err(),
+            "truncated"
+        );
+    }
+
+    #[test]
+    fn test_bad_segment() {
+        let image = ElfBuilder::new(FileType::Executable)
+            .load(0, RX, TEXT, 0x10)
+            .build();
+        assert_eq!(
+            ElfFile::parse(&image).unwrap_err(),
+            ElfError::BadSegment { index: 0 }
+        );
+
+        let image = executable().load(0xFFFF_F000, RW, DATA, 0x1000).build();
+        assert_eq!(
+            ElfFile::parse(&image).unwrap_err(),
+            ElfError::BadSegment { index: 2 }
+        );
+
+        // Only loadable segments have a memory image.
+        let mut image = executable().build();
+        patch_segment(&mut image, 1, |ph| {
+            ph.segment_type = ELF_PROG_NOTE;
+            ph.memory_size = 0;
+        });
+        let file = ElfFile::parse(&image).unwrap();
+        assert_eq!(
+            file.segments().nth(1).unwrap().segment_type(),
+            SegmentType::Note
+        );
+    }
+
+    #[test]
+    fn test_segment_type() {
+        assert_eq!(
+            SegmentType::from_raw(ELF_PROG_GNU_STACK),
+            SegmentType::GnuStack
+        );
+        assert_eq!(
+            SegmentType::from_raw(0x7000_0000),
+            SegmentType::Other(0x7000_0000)
+        );
+    }
+
+    #[test]
+    fn test_user_program() {
+        // The `hello` user program, as linked by `user.ld`.
+        let file = ElfFile::parse(include_bytes!("../fixtures/hello")).unwrap();
+        assert_eq!(file.file_type(), Some(FileType::Executable));
+        let loads: Vec<_> = file
+            .segments()
+            .filter(|segment| segment.segment_type() == SegmentType::Load)
+            .collect();
+        assert_eq!(loads.len(), 2);
+        assert_eq!(loads[0].virtual_address(), 0);
+        assert!(loads[0].flags().executable() && !loads[0].flags().writable());
+        assert_eq!(loads[1].virtual_address() % 0x1000, 0);
+        assert!(loads[1].flags().writable() && !loads[1].flags().executable());
+    }
+}
@@ -1,3 +1,15 @@
 #![cfg_attr(not(test), no_std)]
 
+#[cfg(any(test, feature = "alloc"))]
+extern crate alloc;
+#[cfg(all(not(test), feature = "std"))]
+extern crate std;
+
 pub mod arch;
+pub mod file;
+pub mod note;
+mod pod;
+pub mod symbol_map;
+pub mod symtab;
+#[cfg(any(test, feature = "alloc"))]
+pub mod writer;
@@ -0,0 +1,112 @@
+//! Notes: the contents of `PT_NOTE` segments and `SHT_NOTE` sections, such
+//! as the registers of a process in a core file (`NT_PRSTATUS`).
+//!
+//! Each note is a header of three `u32`s (name size, descriptor size and
+//! type) followed by the NUL-terminated name and the descriptor, both padded
+//! to 4 bytes.
+
+use crate::pod::read_u32;
+
+const HEADER_SIZE: usize = 12;
+
+/// One note.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Note<'a> {
+    /// Owner, e.g. `b"CORE"`, without the NUL
+    pub name: &'a [u8],
+    /// Type, whose meaning depends on the owner (e.g. `NT_PRSTATUS`)
+    pub note_type: u32,
+    pub desc: &'a [u8],
+}
+
+impl Note<'_> {
+    /// Number of bytes the note takes in a note segment.
+    pub const fn encoded_len(&self) -> usize {
+        HEADER_SIZE
+            + (self.name.len() + 1).next_multiple_of(4)
+            + self.desc.len().next_multiple_of(4)
+    }
+}
+
+/// Iterator over the notes of a note segment or section. A truncated note
+/// ends it.
+#[derive(Debug, Clone)]
+pub struct Notes<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Notes<'a> {
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn parse(&self) -> Option<(Note<'a>, &'a [u8])> {
+        let name_size = read_u32(self.data, 0)? as usize;
+        let desc_size = read_u32(self.data, 4)? as usize;
+        let note_type = read_u32(self.data, 8)?;
+        let name = self
+            .data
+            .get(HEADER_SIZE..HEADER_SIZE.checked_add(name_size)?)?;
+        let desc_start = HEADER_SIZE.checked_add(name_size.checked_next_multiple_of(4)?)?;
+        let desc = self
+            .data
+            .get(desc_start..desc_start.checked_add(desc_size)?)?;
+        let end = desc_start.checked_add(desc_size.checked_next_multiple_of(4)?)?;
+        let note = Note {
+            name: name.strip_suffix(b"\0").unwrap_or(name),
+            note_type,
+            desc,
+        };
+        Some((note, self.data.get(end..).unwrap_or_default()))
+    }
+}
+
+impl<'a> Iterator for Notes<'a> {
+    type Item = Note<'a>;
+
+    fn next(&mut self) -> Option<Note<'a>> {
+        let Some((note, rest)) = self.parse() else {
+            self.data = &[];
+            return None;
+        };
+        self.data = rest;
+        Some(note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes() {
+        let mut data = Vec::new();
+        for word in [5, 3, 1] {
+            data.extend_from_slice(&u32::to_le_bytes(word));
+        }
+        data.extend_from_slice(b"CORE\0\0\0\0abc\0");
+        for word in [4, 0, 3] {
+            data.extend_from_slice(&u32::to_le_bytes(word));
+        }
+        data.extend_from_slice(b"xv6\0");
+
+        let notes: Vec<_> = Notes::new(&data).collect();
+        assert_eq!(
+            notes,
+            [
+                Note {
+                    name: b"CORE",
+                    note_type: 1,
+                    desc: b"abc",
+                },
+                Note {
+                    name: b"xv6",
+                    note_type: 3,
+                    desc: b"",
+                },
+            ]
+        );
+        assert_eq!(notes[0].encoded_len(), 24);
+        assert_eq!(Notes::new(&data[..30]).count(), 1, "truncated");
+    }
+}
@@ -0,0 +1,36 @@
+//! Reading `#[repr(C)]` ELF structures out of byte slices.
+
+use crate::arch::x86::{ElfHeader, ProgramHeader, SectionHeader, Symbol};
+
+/// `#[repr(C)]` structs made of integers only, so any bit pattern is a valid value.
+///
+/// # Safety
+/// Implementors must have no padding, references or enums.
+pub(crate) unsafe trait Pod: Sized {}
+
+unsafe impl Pod for ElfHeader {}
+unsafe impl Pod for ProgramHeader {}
+unsafe impl Pod for SectionHeader {}
+unsafe impl Pod for Symbol {}
+
+/// Reads a `T` stored at `offset` in `data`, or `None` if it does not fit.
+pub(crate) fn read<T: Pod>(data: &[u8], offset: usize) -> Option<T> {
+    let end = offset.checked_add(size_of::<T>())?;
+    let bytes = data.get(offset..end)?;
+    // SAFETY: `bytes` holds `size_of::<T>()` bytes and `T: Pod`.
+    Some(unsafe { bytes.as_ptr().cast::<T>().read_unaligned() })
+}
+
+/// Reads the little-endian `u32` at `offset` in `data`, or `None` if it does
+/// not fit.
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// The bytes of `value`, as stored in a file.
+#[cfg(any(test, feature = "alloc"))]
+pub(crate) fn bytes<T: Pod>(value: &T) -> &[u8] {
+    // SAFETY: `T: Pod` has no padding, so all its bytes are initialized.
+    unsafe { core::slice::from_raw_parts((&raw const *value).cast::<u8>(), size_of::<T>()) }
+}
@@ -0,0 +1,200 @@
+//! Compact address to function name table.
+//!
+//! `xtask image` builds one from the kernel's `.symtab` and writes it into
+//! the kernel's `.ksymtab` section, so the kernel can symbolize backtraces
+//! without keeping the whole ELF symbol table in memory.
+//!
+//! Layout (all integers are little-endian `u32`):
+//!
+//! ```txt
+//! +-------+-------+--------------------------------+-----------------+
+//! | magic | count | entries: count * 16 bytes      | names (UTF-8)   |
+//! | KSYM  |       | address, size, name_off, len   |                 |
+//! +-------+-------+--------------------------------+-----------------+
+//! ```
+//!
+//! Entries are sorted by address. `name_off` is relative to the start of the
+//! names area.
+
+use crate::pod::read_u32;
+
+/// Marks an initialized table.
+pub const MAGIC: [u8; 4] = *b"KSYM";
+
+const HEADER_SIZE: usize = 8;
+const ENTRY_SIZE: usize = 16;
+
+/// One function of the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry<'a> {
+    pub address: u32,
+    /// Size in bytes, or 0 if unknown.
+    pub size: u32,
+    pub name: &'a str,
+}
+
+/// Read-only view of an encoded table.
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolMap<'a> {
+    entries: &'a [u8],
+    names: &'a [u8],
+}
+
+impl<'a> SymbolMap<'a> {
+    /// Returns `None` if `data` does not start with an initialized table.
+    pub fn new(data: &'a [u8]) -> Option<Self> {
+        if data.get(..4)? != MAGIC {
+            return None;
+        }
+        let count = read_u32(data, 4)? as usize;
+        let entries_end = HEADER_SIZE.checked_add(count.checked_mul(ENTRY_SIZE)?)?;
+        Some(Self {
+            entries: data.get(HEADER_SIZE..entries_end)?,
+            names: &data[entries_end..],
+        })
+    }
+
+    pub const fn len(&self) -> usize {
+        self.entries.len() / ENTRY_SIZE
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<Entry<'a>> {
+        let offset = index.checked_mul(ENTRY_SIZE)?;
+        let address = read_u32(self.entries, offset)?;
+        let size = read_u32(self.entries, offset + 4)?;
+        let name_offset = read_u32(self.entries, offset + 8)? as usize;
+        let name_len = read_u32(self.entries, offset + 12)? as usize;
+        let name = self
+            .names
+            .get(name_offset..name_offset.checked_add(name_len)?)?;
+        Some(Entry {
+            address,
+            size,
+            name: core::str::from_utf8(name).ok()?,
+        })
+    }
+
+    /// Finds the function containing `address`.
+    ///
+    /// Returns the entry and the offset of `address` into it.
+    pub fn lookup(&self, address: u32) -> Option<(Entry<'a>, u32)> {
+        // Number of entries starting at or before `address`.
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.get(mid)?.address <= address {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let entry = self.get(low.checked_sub(1)?)?;
+        let offset = address - entry.address;
+        if entry.size != 0 && offset >= entry.size {
+            return None;
+        }
+        Some((entry, offset))
+    }
+}
+
+/// Number of bytes [`write`] needs for `entries`.
+pub fn encoded_len(entries: &[Entry<'_>]) -> usize {
+    let names: usize = entries.iter().map(|entry| entry.name.len()).sum();
+    HEADER_SIZE + entries.len() * ENTRY_SIZE + names
+}
+
+/// Encodes `entries`, which must be sorted by address, into `out`.
+///
+/// Returns the number of bytes written, or `None` if `out` is shorter than
+/// [`encoded_len`].
+pub fn write(entries: &[Entry<'_>], out: &mut [u8]) -> Option<usize> {
+    let len = encoded_len(entries);
+    let out = out.get_mut(..len)?;
+    debug_assert!(entries.is_sorted_by_key(|entry| entry.address));
+
+    let (header, rest) = out.split_at_mut(HEADER_SIZE);
+    header[..4].copy_from_slice(&MAGIC);
+    header[4..].copy_from_slice(&u32::try_from(entries.len()).ok()?.to_le_bytes());
+
+    let (table, names) = rest.split_at_mut(entries.len() * ENTRY_SIZE);
+    let mut name_offset = 0;
+    for (entry, slot) in entries.iter().zip(table.chunks_exact_mut(ENTRY_SIZE)) {
+        let name_len = entry.name.len();
+        slot[0..4].copy_from_slice(&entry.address.to_le_bytes());
+        slot[4..8].copy_from_slice(&entry.size.to_le_bytes());
+        slot[8..12].copy_from_slice(&u32::try_from(name_offset).ok()?.to_le_bytes());
+        slot[12..16].copy_from_slice(&u32::try_from(name_len).ok()?.to_le_bytes());
+        names[name_offset..name_offset + name_len].copy_from_slice(entry.name.as_bytes());
+        name_offset += name_len;
+    }
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRIES: [Entry<'static>; 3] = [
+        Entry {
+            address: 0x8010_0000,
+            size: 0x10,
+            name: "entry",
+        },
+        Entry {
+            address: 0x8010_0100,
+            size: 0,
+            name: "kernel::main",
+        },
+        Entry {
+            address: 0x8010_0200,
+            size: 0x20,
+            name: "kernel::vm::setupkvm",
+        },
+    ];
+
+    fn encode() -> Vec<u8> {
+        let mut out = vec![0; encoded_len(&ENTRIES)];
+        assert_eq!(write(&ENTRIES, &mut out), Some(out.len()));
+        out
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data = encode();
+        let map = SymbolMap::new(&data).unwrap();
+        assert_eq!(map.len(), ENTRIES.len());
+        for (i, entry) in ENTRIES.iter().enumerate() {
+            assert_eq!(map.get(i).as_ref(), Some(entry));
+        }
+    }
+
+    #[test]
+    fn test_lookup() {
+        let data = encode();
+        let map = SymbolMap::new(&data).unwrap();
+
+        assert_eq!(map.lookup(0x8010_0000), Some((ENTRIES[0], 0)));
+        assert_eq!(map.lookup(0x8010_000F), Some((ENTRIES[0], 0xF)));
+        assert_eq!(map.lookup(0x8010_0010), None, "past the end of `entry`");
+        // Unknown size: everything up to the next symbol.
+        assert_eq!(map.lookup(0x8010_01FF), Some((ENTRIES[1], 0xFF)));
+        assert_eq!(map.lookup(0x8010_0210), Some((ENTRIES[2], 0x10)));
+        assert_eq!(map.lookup(0x800F_FFFF), None, "before the first symbol");
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        assert!(SymbolMap::new(&[0; 64]).is_none(), "uninitialized");
+
+        let mut data = encode();
+        data.truncate(HEADER_SIZE + ENTRY_SIZE);
+        assert!(SymbolMap::new(&data).is_none(), "truncated");
+
+        let mut short = vec![0; encoded_len(&ENTRIES) - 1];
+        assert_eq!(write(&ENTRIES, &mut short), None);
+    }
+}
@@ -0,0 +1,313 @@
+//! Section headers and the symbol table of an ELF image held in memory.
+//!
+//! Every accessor is bounds checked: a truncated or corrupt image yields
+//! `None` instead of reading out of bounds.
+
+use crate::arch::x86::{
+    ELF_MAGIC, ElfHeader, SHN_UNDEF, SHT_SYMTAB, STT_FILE, STT_SECTION, SectionHeader, Symbol,
+};
+use crate::pod::read;
+
+/// Iterator over the section headers of an ELF image.
+#[derive(Debug, Clone)]
+pub struct Sections<'a> {
+    image: &'a [u8],
+    offset: usize,
+    entry_size: usize,
+    index: usize,
+    count: usize,
+    names_index: usize,
+}
+
+impl<'a> Sections<'a> {
+    /// Returns `None` if `image` is not an ELF file.
+    pub fn new(image: &'a [u8]) -> Option<Self> {
+        let header: ElfHeader = read(image, 0)?;
+        if header.magic != ELF_MAGIC {
+            return None;
+        }
+        Some(Self::from_header(image, &header))
+    }
+
+    pub(crate) const fn from_header(image: &'a [u8], header: &ElfHeader) -> Self {
+        Self {
+            image,
+            offset: header.section_header_offset as usize,
+            entry_size: header.section_header_entry_size as usize,
+            index: 0,
+            count: header.section_header_count as usize,
+            names_index: header.section_name_string_index as usize,
+        }
+    }
+
+    /// Returns the section at `index`.
+    pub fn get(&self, index: usize) -> Option<SectionHeader> {
+        if index >= self.count || self.entry_size < size_of::<SectionHeader>() {
+            return None;
+        }
+        let offset = self
+            .offset
+            .checked_add(index.checked_mul(self.entry_size)?)?;
+        read(self.image, offset)
+    }
+
+    /// Returns the contents of `section` in the file.
+    pub fn data(&self, section: &SectionHeader) -> Option<&'a [u8]> {
+        let start = section.offset as usize;
+        let end = start.checked_add(section.size as usize)?;
+        self.image.get(start..end)
+    }
+
+    /// Returns the section name string table.
+    pub fn names(&self) -> Option<StringTable<'a>> {
+        Some(StringTable::new(self.data(&self.get(self.names_index)?)?))
+    }
+
+    /// Finds the section called `name` through the section name string table.
+    pub fn by_name(&self, name: &str) -> Option<SectionHeader> {
+        self.named()
+            .find(|&(n, _)| n == name)
+            .map(|(_, section)| section)
+    }
+
+    /// Iterates over the sections with their names. Sections whose name
+    /// cannot be read get an empty name.
+    pub fn named(&self) -> impl Iterator<Item = (&'a str, SectionHeader)> + 'a {
+        let names = self.names();
+        self.clone().map(move |section| {
+            let name = names.and_then(|names| names.get(section.name));
+            (name.unwrap_or(""), section)
+        })
+    }
+}
+
+impl Iterator for Sections<'_> {
+    type Item = SectionHeader;
+
+    fn next(&mut self) -> Option<SectionHeader> {
+        let section = self.get(self.index)?;
+        self.index += 1;
+        Some(section)
+    }
+}
+
+/// Table of NUL-terminated strings (`SHT_STRTAB`).
+#[derive(Debug, Clone, Copy)]
+pub struct StringTable<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StringTable<'a> {
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Returns the string starting at `offset`, or `None` if it is out of
+    /// bounds, unterminated or not UTF-8.
+    pub fn get(&self, offset: u32) -> Option<&'a str> {
+        let rest = self.data.get(offset as usize..)?;
+        let len = rest.iter().position(|&b| b == 0)?;
+        core::str::from_utf8(&rest[..len]).ok()
+    }
+}
+
+/// The symbol table (`.symtab`) of an ELF image with its string table.
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolTable<'a> {
+    symbols: &'a [u8],
+    strings: StringTable<'a>,
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Finds the `SHT_SYMTAB` section of `image`.
+    ///
+    /// Returns `None` if there is none, e.g. because the image was stripped.
+    pub fn from_elf(image: &'a [u8]) -> Option<Self> {
+        Self::from_sections(&Sections::new(image)?)
+    }
+
+    /// Finds the `SHT_SYMTAB` section among `sections`.
+    pub fn from_sections(sections: &Sections<'a>) -> Option<Self> {
+        let symtab = sections
+            .clone()
+            .find(|section| section.section_type == SHT_SYMTAB)?;
+        let strtab = sections.get(symtab.link as usize)?;
+        Some(Self {
+            symbols: sections.data(&symtab)?,
+            strings: StringTable::new(sections.data(&strtab)?),
+        })
+    }
+
+    /// Number of symbols, including the null symbol at index 0.
+    pub const fn len(&self) -> usize {
+        self.symbols.len() / size_of::<Symbol>()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the symbol at `index`.
+    pub fn get(&self, index: usize) -> Option<Symbol> {
+        read(self.symbols, index.checked_mul(size_of::<Symbol>())?)
+    }
+
+    /// Returns the name of `symbol` in the string table.
+    pub fn name(&self, symbol: &Symbol) -> Option<&'a str> {
+        self.strings.get(symbol.name)
+    }
+
+    /// Iterates over all symbols with their names. Symbols whose name cannot
+    /// be read get an empty name.
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &'a str)> + 'a {
+        let strings = self.strings;
+        self.symbols
+            .chunks_exact(size_of::<Symbol>())
+            .filter_map(|entry| read::<Symbol>(entry, 0))
+            .map(move |symbol| (symbol, strings.get(symbol.name).unwrap_or("")))
+    }
+
+    /// Finds the first symbol called `name`.
+    pub fn by_name(&self, name: &str) -> Option<Symbol> {
+        self.iter()
+            .find(|&(_, n)| n == name)
+            .map(|(symbol, _)| symbol)
+    }
+
+    /// Iterates over the defined symbols whose range contains `address`:
+    /// `value..value + size`, or just `value` if the size is unknown (0).
+    /// Section and file symbols are left out.
+    pub fn at(&self, address: u32) -> impl Iterator<Item = (Symbol, &'a str)> + 'a {
+        self.iter().filter(move |(symbol, _)| {
+            is_located(symbol)
+                && match address.checked_sub(symbol.value) {
+                    Some(offset) => offset < symbol.size || offset == 0,
+                    None => false,
+                }
+        })
+    }
+
+    /// Finds the symbol `address` belongs to: the closest defined symbol at
+    /// or before it that does not end before it. A symbol of unknown size
+    /// (0) extends up to the next one, as in [`symbol_map`](crate::symbol_map).
+    ///
+    /// Returns the symbol, its name and the offset of `address` into it.
+    pub fn lookup(&self, address: u32) -> Option<(Symbol, &'a str, u32)> {
+        let mut best: Option<(Symbol, &'a str)> = None;
+        for (symbol, name) in self.iter() {
+            if !is_located(&symbol) || symbol.value > address {
+                continue;
+            }
+            let closer = match best {
+                None => true,
+                Some((b, _)) => symbol.value > b.value || (symbol.value == b.value && b.size == 0),
+            };
+            if closer {
+                best = Some((symbol, name));
+            }
+        }
+        let (symbol, name) = best?;
+        let offset = address - symbol.value;
+        if symbol.size != 0 && offset >= symbol.size {
+            return None;
+        }
+        Some((symbol, name, offset))
+    }
+}
+
+/// Whether `symbol` is defined at an address: not undefined, nor naming a
+/// section or a source file.
+fn is_located(symbol: &Symbol) -> bool {
+    symbol.section_index != SHN_UNDEF
+        && symbol.symbol_type() != STT_SECTION
+        && symbol.symbol_type() != STT_FILE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::x86::{SHF_ALLOC, SHF_EXECINSTR, SHT_STRTAB, STT_FUNC};
+    use crate::file::ElfFile;
+
+    /// The `hello` user program, unstripped (`cargo xtask fixtures`).
+    const HELLO: &[u8] = include_bytes!("../fixtures/hello");
+
+    #[test]
+    fn test_string_table() {
+        let strings = StringTable::new(b"\0.text\0.symtab\0bad");
+        assert_eq!(strings.get(0), Some(""));
+        assert_eq!(strings.get(1), Some(".text"));
+        assert_eq!(strings.get(3), Some("ext"));
+        assert_eq!(strings.get(15), None, "unterminated");
+        assert_eq!(strings.get(100), None);
+    }
+
+    #[test]
+    fn test_not_elf() {
+        assert!(Sections::new(b"").is_none());
+        assert!(Sections::new(&[0; 64]).is_none());
+        assert!(SymbolTable::from_elf(&[0; 64]).is_none());
+    }
+
+    #[test]
+    fn test_sections() {
+        let file = ElfFile::parse(HELLO).unwrap();
+        let sections = file.sections();
+        let names: Vec<_> = sections.named().map(|(name, _)| name).collect();
+        for name in [
+            ".text",
+            ".rodata",
+            ".data",
+            ".symtab",
+            ".strtab",
+            ".shstrtab",
+        ] {
+            assert!(names.contains(&name), "{name} in {names:?}");
+        }
+
+        let text = sections.by_name(".text").unwrap();
+        assert_eq!(
+            text.flags & (SHF_ALLOC | SHF_EXECINSTR),
+            SHF_ALLOC | SHF_EXECINSTR
+        );
+        assert!((text.address..text.address + text.size).contains(&file.entry()));
+        assert_eq!(sections.data(&text).unwrap().len(), text.size as usize);
+        assert!(sections.by_name(".nothing").is_none());
+
+        let symtab = sections.by_name(".symtab").unwrap();
+        let strtab = sections.get(symtab.link as usize).unwrap();
+        assert_eq!(strtab.section_type, SHT_STRTAB);
+        assert_eq!(sections.names().unwrap().get(strtab.name), Some(".strtab"));
+    }
+
+    #[test]
+    fn test_symbols() {
+        let file = ElfFile::parse(HELLO).unwrap();
+        let symbols = file.symbols().unwrap();
+        assert!(symbols.len() > 1);
+        assert_eq!(symbols.get(0).unwrap().name, 0, "null symbol");
+        assert!(symbols.get(symbols.len()).is_none());
+
+        let start = symbols.by_name("_start").unwrap();
+        assert_eq!(start.value, file.entry());
+        assert_eq!(start.symbol_type(), STT_FUNC);
+        assert_eq!(symbols.name(&start), Some("_start"));
+
+        let main = symbols.by_name("main").unwrap();
+        assert!(main.size > 0);
+        let (symbol, name, offset) = symbols.lookup(main.value + 1).unwrap();
+        assert_eq!((symbol.value, name, offset), (main.value, "main", 1));
+        assert!(symbols.at(main.value).any(|(_, name)| name == "main"));
+        assert!(
+            symbols
+                .at(main.value + main.size)
+                .all(|(_, name)| name != "main")
+        );
+
+        // The system call stubs are assembly labels, of unknown size.
+        let write = symbols.by_name("write").unwrap();
+        assert_eq!(write.size, 0);
+        let (_, name, offset) = symbols.lookup(write.value + 2).unwrap();
+        assert_eq!((name, offset), ("write", 2));
+    }
+}
@@ -0,0 +1,511 @@
+//! Building i386 ELF32 files (with the `alloc` feature).
+//!
+//! [`ElfBuilder`] lays out the file header, the program headers, the
+//! contents of the segments and sections, and the section headers, filling
+//! in every offset and size. The other header fields are taken as given, so
+//! tests can build executables the parser or `exec` must reject. Core files
+//! are laid out from a process's registers and memory:
+//!
+//! ```ignore
+//! let core = ElfBuilder::new(FileType::Core)
+//!     .notes(&[Note { name: b"CORE", note_type: NT_PRSTATUS, desc: &regs }])
+//!     .load(0, ELF_PROG_FLAG_READ | ELF_PROG_FLAG_EXEC, &text, text.len() as u32)
+//!     .build();
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::arch::x86::{
+    ELF_MAGIC, ELF_PROG_FLAG_READ, ELF_PROG_LOAD, ELF_PROG_NOTE, ELFCLASS32, ELFDATA2LSB, EM_386,
+    ET_CORE, ET_DYN, ET_EXEC, ET_REL, EV_CURRENT, ElfHeader, ProgramHeader, SHF_ALLOC, SHT_NOBITS,
+    SHT_NOTE, SHT_NULL, SHT_STRTAB, SHT_SYMTAB, STB_LOCAL, SectionHeader, Symbol,
+};
+use crate::file::FileType;
+use crate::note::Note;
+use crate::pod::bytes;
+
+/// Alignment of loadable segments, the page size.
+const LOAD_ALIGN: u32 = 0x1000;
+
+/// Builder of an ELF32 file.
+#[derive(Debug, Clone)]
+pub struct ElfBuilder<'a> {
+    header: ElfHeader,
+    segments: Vec<(ProgramHeader, &'a [u8])>,
+    /// Notes are encoded into the builder, so are owned.
+    notes: Vec<Vec<u8>>,
+    sections: Vec<(&'a str, SectionHeader, &'a [u8])>,
+    symbols: Vec<(&'a str, Symbol)>,
+}
+
+impl<'a> ElfBuilder<'a> {
+    /// An empty file of type `file_type` for the i386, entered at 0.
+    pub fn new(file_type: FileType) -> Self {
+        let file_type = match file_type {
+            FileType::Relocatable => ET_REL,
+            FileType::Executable => ET_EXEC,
+            FileType::SharedObject => ET_DYN,
+            FileType::Core => ET_CORE,
+        };
+        let mut ident = [0; 12];
+        ident[..3].copy_from_slice(&[ELFCLASS32, ELFDATA2LSB, EV_CURRENT as u8]);
+        Self {
+            header: ElfHeader {
+                magic: ELF_MAGIC,
+                ident,
+                file_type,
+                machine: EM_386,
+                version: EV_CURRENT,
+                entry: 0,
+                program_header_offset: 0,
+                section_header_offset: 0,
+                flags: 0,
+                header_size: size_of::<ElfHeader>() as u16,
+                program_header_entry_size: 0,
+                program_header_count: 0,
+                section_header_entry_size: 0,
+                section_header_count: 0,
+                section_name_string_index: 0,
+            },
+            segments: Vec::new(),
+            notes: Vec::new(),
+            sections: Vec::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Sets the file header fields that are not about the layout, e.g. to
+    /// make a file for another machine.
+    #[must_use]
+    pub fn header(mut self, f: impl FnOnce(&mut ElfHeader)) -> Self {
+        f(&mut self.header);
+        self
+    }
+
+    #[must_use]
+    pub const fn entry(mut self, entry: u32) -> Self {
+        self.header.entry = entry;
+        self
+    }
+
+    /// Adds a segment with contents `data`. Its offset and file size are
+    /// filled in; the offset keeps the segment's alignment, like the address.
+    #[must_use]
+    pub fn segment(mut self, header: ProgramHeader, data: &'a [u8]) -> Self {
+        self.segments.push((header, data));
+        self
+    }
+
+    /// Adds a page-aligned loadable segment of `memory_size` bytes at
+    /// `virtual_address`, starting with `data`, with `ELF_PROG_FLAG_*`
+    /// permissions `flags`.
+    #[must_use]
+    pub fn load(self, virtual_address: u32, flags: u32, data: &'a [u8], memory_size: u32) -> Self {
+        let header = ProgramHeader {
+            segment_type: ELF_PROG_LOAD,
+            offset: 0,
+            virtual_address,
+            physical_address: virtual_address,
+            file_size: 0,
+            memory_size,
+            flags,
+            alignment: LOAD_ALIGN,
+        };
+        self.segment(header, data)
+    }
+
+    /// Adds a note segment holding `notes`, e.g. the registers of a core
+    /// file's process.
+    #[must_use]
+    pub fn notes(mut self, notes: &[Note<'_>]) -> Self {
+        self.notes.push(encode_notes(notes));
+        self
+    }
+
+    /// Adds section `name` with contents `data` (none for `SHT_NOBITS`).
+    /// Its name, offset and size are filled in, except the size of a
+    /// `SHT_NOBITS` section.
+    #[must_use]
+    pub fn section(mut self, name: &'a str, header: SectionHeader, data: &'a [u8]) -> Self {
+        self.sections.push((name, header, data));
+        self
+    }
+
+    /// Adds a symbol named `name`, in a `.symtab` section with its `.strtab`.
+    /// Its name is filled in. Local symbols must come first.
+    #[must_use]
+    pub fn symbol(mut self, name: &'a str, symbol: Symbol) -> Self {
+        self.symbols.push((name, symbol));
+        self
+    }
+
+    /// Lays out the file.
+    pub fn build(&self) -> Vec<u8> {
+        let mut header = self.header;
+        let mut out = vec![0; size_of::<ElfHeader>()];
+
+        // Program headers, then the contents of the segments.
+        let note_headers = self.notes.iter().map(|notes| {
+            let header = ProgramHeader {
+                segment_type: ELF_PROG_NOTE,
+                offset: 0,
+                virtual_address: 0,
+                physical_address: 0,
+                file_size: 0,
+                memory_size: 0,
+                flags: ELF_PROG_FLAG_READ,
+                alignment: 4,
+            };
+            (header, notes.as_slice())
+        });
+        let mut segments: Vec<_> = note_headers.chain(self.segments.iter().copied()).collect();
+        let table = out.len();
+        out.resize(table + segments.len() * size_of::<ProgramHeader>(), 0);
+        for (ph, data) in &mut segments {
+            pad_to(&mut out, ph.alignment, ph.virtual_address);
+            ph.offset = out.len() as u32;
+            ph.file_size = data.len() as u32;
+            out.extend_from_slice(data);
+        }
+        if !segments.is_empty() {
+            header.program_header_offset = table as u32;
+            header.program_header_entry_size = size_of::<ProgramHeader>() as u16;
+            header.program_header_count = segments.len() as u16;
+            for (i, (ph, _)) in segments.iter().enumerate() {
+                let offset = table + i * size_of::<ProgramHeader>();
+                out[offset..offset + size_of::<ProgramHeader>()].copy_from_slice(bytes(ph));
+            }
+        }
+
+        let sections = self.layout_sections(&mut out);
+        if !sections.is_empty() {
+            pad_to(&mut out, 4, 0);
+            header.section_header_offset = out.len() as u32;
+            header.section_header_entry_size = size_of::<SectionHeader>() as u16;
+            header.section_header_count = sections.len() as u16;
+            header.section_name_string_index = sections.len() as u16 - 1;
+            for section in &sections {
+                out.extend_from_slice(bytes(section));
+            }
+        }
+
+        out[..size_of::<ElfHeader>()].copy_from_slice(bytes(&header));
+        out
+    }
+
+    /// Appends the contents of the sections to `out`, and returns their
+    /// headers: the null section, the added ones, `.symtab` and `.strtab` if
+    /// there are symbols, and `.shstrtab` last.
+    fn layout_sections(&self, out: &mut Vec<u8>) -> Vec<SectionHeader> {
+        if self.sections.is_empty() && self.symbols.is_empty() {
+            return Vec::new();
+        }
+        let mut names = StringTableBuilder::default();
+        let mut sections = vec![null_section()];
+        let mut place = |out: &mut Vec<u8>, name: &str, mut section: SectionHeader, data: &[u8]| {
+            section.name = names.add(name);
+            pad_to(out, section.alignment, section.address);
+            section.offset = out.len() as u32;
+            if section.section_type != SHT_NOBITS {
+                section.size = data.len() as u32;
+                out.extend_from_slice(data);
+            }
+            sections.push(section);
+        };
+
+        for &(name, section, data) in &self.sections {
+            place(out, name, section, data);
+        }
+
+        if !self.symbols.is_empty() {
+            let mut strings = StringTableBuilder::default();
+            let mut symtab = Vec::new();
+            symtab.extend_from_slice(bytes(&null_symbol()));
+            for &(name, mut symbol) in &self.symbols {
+                symbol.name = strings.add(name);
+                symtab.extend_from_slice(bytes(&symbol));
+            }
+            let locals = self
+                .symbols
+                .iter()
+                .take_while(|(_, symbol)| symbol.binding() == STB_LOCAL)
+                .count();
+            let index = self.sections.len() as u32 + 1;
+            let symtab_header = SectionHeader {
+                section_type: SHT_SYMTAB,
+                link: index + 1,
+                info: locals as u32 + 1,
+                alignment: 4,
+                entry_size: size_of::<Symbol>() as u32,
+                ..null_section()
+            };
+            place(out, ".symtab", symtab_header, &symtab);
+            let strtab_header = SectionHeader {
+                section_type: SHT_STRTAB,
+                alignment: 1,
+                ..null_section()
+            };
+            place(out, ".strtab", strtab_header, &strings.data);
+        }
+
+        // The name of `.shstrtab` must be in itself before it is written.
+        let name = names.add(".shstrtab");
+        let shstrtab = SectionHeader {
+            name,
+            section_type: SHT_STRTAB,
+            offset: out.len() as u32,
+            size: names.data.len() as u32,
+            alignment: 1,
+            ..null_section()
+        };
+        out.extend_from_slice(&names.data);
+        sections.push(shstrtab);
+        sections
+    }
+
+    /// Writes the file to `w`.
+    ///
+    /// # Errors
+    ///
+    /// If writing fails.
+    #[cfg(any(test, feature = "std"))]
+    pub fn write_to(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        w.write_all(&self.build())
+    }
+}
+
+/// Section header fields for an allocated section of code or data.
+pub const fn section_header(section_type: u32, flags: u32, address: u32) -> SectionHeader {
+    SectionHeader {
+        section_type,
+        flags: flags | SHF_ALLOC,
+        address,
+        alignment: 4,
+        ..null_section()
+    }
+}
+
+/// Encodes `notes` as the contents of a note segment or `SHT_NOTE` section.
+pub fn encode_notes(notes: &[Note<'_>]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(notes.iter().map(Note::encoded_len).sum());
+    for note in notes {
+        for word in [
+            note.name.len() as u32 + 1,
+            note.desc.len() as u32,
+            note.note_type,
+        ] {
+            out.extend_from_slice(&word.to_le_bytes());
+        }
+        out.extend_from_slice(note.name);
+        out.push(0);
+        pad_to(&mut out, 4, 0);
+        out.extend_from_slice(note.desc);
+        pad_to(&mut out, 4, 0);
+    }
+    out
+}
+
+/// `SHT_NOTE` section header fields, for [`encode_notes`] contents.
+pub const fn note_section_header() -> SectionHeader {
+    SectionHeader {
+        section_type: SHT_NOTE,
+        alignment: 4,
+        ..null_section()
+    }
+}
+
+/// Pads `out` with zeros until its length is `address` modulo `alignment`,
+/// if that is a power of two.
+fn pad_to(out: &mut Vec<u8>, alignment: u32, address: u32) {
+    if !alignment.is_power_of_two() {
+        return;
+    }
+    let mask = alignment as usize - 1;
+    let pad = ((address as usize & mask) + alignment as usize - (out.len() & mask)) & mask;
+    out.resize(out.len() + pad, 0);
+}
+
+const fn null_section() -> SectionHeader {
+    SectionHeader {
+        name: 0,
+        section_type: SHT_NULL,
+        flags: 0,
+        address: 0,
+        offset: 0,
+        size: 0,
+        link: 0,
+        info: 0,
+        alignment: 0,
+        entry_size: 0,
+    }
+}
+
+const fn null_symbol() -> Symbol {
+    Symbol {
+        name: 0,
+        value: 0,
+        size: 0,
+        info: 0,
+        other: 0,
+        section_index: 0,
+    }
+}
+
+/// Strings of a `SHT_STRTAB` section, starting with the empty string.
+struct StringTableBuilder {
+    data: Vec<u8>,
+}
+
+impl Default for StringTableBuilder {
+    fn default() -> Self {
+        Self { data: vec![0] }
+    }
+}
+
+impl StringTableBuilder {
+    /// Appends `s`, and returns its offset.
+    fn add(&mut self, s: &str) -> u32 {
+        if s.is_empty() {
+            return 0;
+        }
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arch::x86::{
+        ELF_PROG_FLAG_EXEC, ELF_PROG_FLAG_WRITE, NT_PRSTATUS, SHF_EXECINSTR, SHF_WRITE,
+        SHT_PROGBITS, STB_GLOBAL, STT_FUNC,
+    };
+    use crate::file::{ElfFile, SegmentType};
+
+    const TEXT: &[u8] = &[0x55, 0x89, 0xE5, 0x5D, 0xC3, 0x90, 0x90, 0x90];
+    const DATA: &[u8] = b"data";
+    const RX: u32 = ELF_PROG_FLAG_READ | ELF_PROG_FLAG_EXEC;
+    const RW: u32 = ELF_PROG_FLAG_READ | ELF_PROG_FLAG_WRITE;
+
+    fn function(value: u32, size: u32) -> Symbol {
+        Symbol {
+            value,
+            size,
+            info: (STB_GLOBAL << 4) | STT_FUNC,
+            section_index: 1,
+            ..null_symbol()
+        }
+    }
+
+    fn executable() -> ElfBuilder<'static> {
+        ElfBuilder::new(FileType::Executable)
+            .entry(0)
+            .load(0, RX, TEXT, TEXT.len() as u32)
+            .load(0x1000, RW, DATA, 0x100)
+            .section(
+                ".text",
+                section_header(SHT_PROGBITS, SHF_EXECINSTR, 0),
+                TEXT,
+            )
+            .section(
+                ".data",
+                section_header(SHT_PROGBITS, SHF_WRITE, 0x1000),
+                DATA,
+            )
+            .symbol("main", function(0, 5))
+            .symbol("nop", function(5, 0))
+    }
+
+    #[test]
+    fn test_executable() {
+        let image = executable().build();
+        let file = ElfFile::parse(&image).unwrap();
+        assert_eq!(file.file_type(), Some(FileType::Executable));
+
+        let segments: Vec<_> = file.segments().collect();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].data(), TEXT);
+        assert_eq!(segments[0].flags().bits(), RX);
+        assert_eq!(segments[1].data(), DATA);
+        assert_eq!(segments[1].virtual_address(), 0x1000);
+        assert_eq!(segments[1].memory_size(), 0x100);
+        for segment in &segments {
+            let header = segment.header();
+            assert_eq!(header.offset % 0x1000, header.virtual_address % 0x1000);
+        }
+
+        let sections = file.sections();
+        let text = sections.by_name(".text").unwrap();
+        assert_eq!(sections.data(&text), Some(TEXT));
+        let data = sections.by_name(".data").unwrap();
+        assert_eq!((data.address, sections.data(&data)), (0x1000, Some(DATA)));
+
+        let symbols = file.symbols().unwrap();
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols.lookup(3).map(|(_, name, _)| name), Some("main"));
+        assert_eq!(
+            symbols.lookup(7).map(|(_, name, offset)| (name, offset)),
+            Some(("nop", 2))
+        );
+    }
+
+    #[test]
+    fn test_core() {
+        let regs = [0xAB; 68];
+        let notes = [Note {
+            name: b"CORE",
+            note_type: NT_PRSTATUS,
+            desc: &regs,
+        }];
+        let image = ElfBuilder::new(FileType::Core)
+            .notes(&notes)
+            .load(0, RX, TEXT, 0x1000)
+            .section(".note", note_section_header(), &[])
+            .build();
+        let file = ElfFile::parse(&image).unwrap();
+        assert_eq!(file.file_type(), Some(FileType::Core));
+
+        let note = file.segments().next().unwrap();
+        assert_eq!(note.segment_type(), SegmentType::Note);
+        assert!(note.notes().eq(notes));
+        let load = file.segments().nth(1).unwrap();
+        assert_eq!(
+            (load.segment_type(), load.data()),
+            (SegmentType::Load, TEXT)
+        );
+        assert!(file.symbols().is_none());
+    }
+
+    #[test]
+    fn test_no_sections() {
+        let image = ElfBuilder::new(FileType::Executable)
+            .load(0, RX, TEXT, 8)
+            .build();
+        let file = ElfFile::parse(&image).unwrap();
+        assert_eq!(file.header().section_header_count, 0);
+        assert_eq!(file.sections().count(), 0);
+    }
+
+    #[test]
+    fn test_write_to() {
+        let mut out = Vec::new();
+        executable().write_to(&mut out).unwrap();
+        assert_eq!(out, executable().build());
+    }
+
+    #[test]
+    fn test_pad_to() {
+        let mut out = vec![0; 5];
+        pad_to(&mut out, 4, 0);
+        assert_eq!(out.len(), 8);
+        pad_to(&mut out, 0x1000, 0x2010);
+        assert_eq!(out.len(), 0x10);
+        pad_to(&mut out, 0x1000, 0x2004);
+        assert_eq!(out.len(), 0x1004);
+        pad_to(&mut out, 0, 3);
+        assert_eq!(out.len(), 0x1004);
+    }
+}
@@ -1,16 +1,226 @@
-#![cfg_attr(not(test), no_std)]
-
-// memory crate example
-
-pub fn hello() {
-    // dummy function
-}
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn test_hello() {
-        super::hello();
-        assert_eq!(2 + 2, 4);
-    }
-}
+#![cfg_attr(not(test), no_std)]
+//! Physical memory allocator (inherited xv6 kalloc.c)
+//!
+//! Hands out 4096-byte frames for user processes, kernel stacks, page table
+//! pages and pipe buffers. The free list is threaded through the free pages
+//! themselves, so the allocator needs no memory of its own.
+//!
+//! All addresses are the ones the caller can dereference (kernel virtual
+//! addresses in the kernel, a plain buffer in host tests). Locking is left to
+//! the owner of the [`Kmem`].
+
+use core::ptr::NonNull;
+
+use page::{PG_SIZE, pg_round_down, pg_round_up};
+
+/// Byte filled into a page on free to catch dangling references.
+const JUNK_FREED: u8 = 1;
+/// Byte filled into a page on alloc to catch uninitialized reads.
+const JUNK_ALLOCATED: u8 = 5;
+
+/// Free list node stored at the start of each free page.
+#[repr(C)]
+struct Run {
+    next: Option<NonNull<Run>>,
+}
+
+/// Free list of physical page frames.
+#[derive(Debug)]
+pub struct Kmem {
+    free_list: Option<NonNull<Run>>,
+    /// Lowest address that may be handed out (page aligned).
+    start: usize,
+    /// One past the highest address that may be handed out (page aligned).
+    end: usize,
+    /// Number of pages currently on the free list.
+    free_pages: usize,
+}
+
+// SAFETY: The free pages are owned exclusively by the allocator.
+unsafe impl Send for Kmem {}
+
+impl Default for Kmem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Kmem {
+    /// Creates an allocator that manages no memory yet.
+    pub const fn new() -> Self {
+        Self {
+            free_list: None,
+            start: 0,
+            end: 0,
+            free_pages: 0,
+        }
+    }
+
+    /// Sets the region `[start, end)` this allocator is responsible for.
+    ///
+    /// Typically `start` is the linker's `end` symbol and `end` is `PHYSTOP`.
+    /// No page is freed yet; use [`Self::free_range`] for that.
+    pub fn init(&mut self, start: *mut u8, end: *mut u8) {
+        self.start = pg_round_up(start.addr());
+        self.end = pg_round_down(end.addr());
+    }
+
+    /// Frees every whole page in `[start, end)`.
+    ///
+    /// # Safety
+    /// The range must be valid writable memory that nobody else uses.
+    ///
+    /// # Panics
+    /// Panics if a page lies outside the region given to [`Self::init`].
+    pub unsafe fn free_range(&mut self, start: *mut u8, end: *mut u8) {
+        let first = pg_round_up(start.addr()) - start.addr();
+        let mut page = start.wrapping_add(first);
+        while page.addr() + PG_SIZE <= end.addr() {
+            if let Some(page) = NonNull::new(page) {
+                unsafe { self.free(page) };
+            }
+            page = page.wrapping_add(PG_SIZE);
+        }
+    }
+
+    /// Frees the page pointed at by `page`, which normally should have been
+    /// returned by a call to [`Self::alloc`]. (The exception is when
+    /// initializing the allocator; see [`Self::free_range`].)
+    ///
+    /// # Safety
+    /// `page` must not be used after this call.
+    ///
+    /// # Panics
+    /// Panics if `page` is not page aligned or outside the managed region.
+    pub unsafe fn free(&mut self, page: NonNull<u8>) {
+        let addr = page.addr().get();
+        if addr % PG_SIZE != 0 || !self.contains(addr) {
+            panic!("kfree: bad page {addr:#x}");
+        }
+
+        // Fill with junk to catch dangling refs.
+        unsafe { page.write_bytes(JUNK_FREED, PG_SIZE) };
+
+        let run = page.cast::<Run>();
+        unsafe { run.write(Run { next: self.free_list }) };
+        self.free_list = Some(run);
+        self.free_pages += 1;
+    }
+
+    /// Allocates one 4096-byte page.
+    ///
+    /// Returns `None` if the memory cannot be allocated.
+    pub fn alloc(&mut self) -> Option<NonNull<u8>> {
+        let run = self.free_list?;
+        self.free_list = unsafe { run.as_ref() }.next;
+        self.free_pages -= 1;
+
+        let page = run.cast::<u8>();
+        unsafe { page.write_bytes(JUNK_ALLOCATED, PG_SIZE) };
+        Some(page)
+    }
+
+    /// Number of pages currently available.
+    pub const fn free_pages(&self) -> usize {
+        self.free_pages
+    }
+
+    /// Whether `addr` lies in the region given to [`Self::init`].
+    pub const fn contains(&self, addr: usize) -> bool {
+        self.start <= addr && addr < self.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N_PAGES: usize = 8;
+
+    /// Page aligned stand-in for physical memory.
+    #[repr(C, align(4096))]
+    struct Arena([u8; N_PAGES * PG_SIZE]);
+
+    fn arena() -> Box<Arena> {
+        Box::new(Arena([0; N_PAGES * PG_SIZE]))
+    }
+
+    fn bounds(arena: &mut Arena) -> (*mut u8, *mut u8) {
+        let range = arena.0.as_mut_ptr_range();
+        (range.start, range.end)
+    }
+
+    #[test]
+    fn test_alloc_all_then_exhaust() {
+        let mut arena = arena();
+        let (start, end) = bounds(&mut arena);
+
+        let mut kmem = Kmem::new();
+        kmem.init(start, end);
+        unsafe { kmem.free_range(start, end) };
+        assert_eq!(kmem.free_pages(), N_PAGES);
+
+        let mut pages = Vec::new();
+        while let Some(page) = kmem.alloc() {
+            assert_eq!(page.addr().get() % PG_SIZE, 0);
+            assert!(kmem.contains(page.addr().get()));
+            assert_eq!(unsafe { *page.as_ptr() }, JUNK_ALLOCATED);
+            pages.push(page.addr().get());
+        }
+        assert_eq!(pages.len(), N_PAGES);
+        assert_eq!(kmem.free_pages(), 0);
+
+        pages.sort_unstable();
+        pages.dedup();
+        assert_eq!(pages.len(), N_PAGES, "a page was handed out twice");
+    }
+
+    #[test]
+    fn test_free_then_reuse() {
+        let mut arena = arena();
+        let (start, end) = bounds(&mut arena);
+
+        let mut kmem = Kmem::new();
+        kmem.init(start, end);
+        unsafe { kmem.free_range(start, end) };
+
+        let page = kmem.alloc().unwrap();
+        assert_eq!(kmem.free_pages(), N_PAGES - 1);
+        unsafe { kmem.free(page) };
+        assert_eq!(kmem.free_pages(), N_PAGES);
+        assert_eq!(kmem.alloc(), Some(page), "free list must be LIFO");
+    }
+
+    #[test]
+    fn test_free_range_skips_partial_pages() {
+        let mut arena = arena();
+        let (start, end) = bounds(&mut arena);
+
+        let mut kmem = Kmem::new();
+        kmem.init(start, end);
+        unsafe { kmem.free_range(start.wrapping_add(1), end.wrapping_sub(1)) };
+        assert_eq!(kmem.free_pages(), N_PAGES - 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "kfree")]
+    fn test_free_unaligned_panics() {
+        let mut arena = arena();
+        let (start, end) = bounds(&mut arena);
+
+        let mut kmem = Kmem::new();
+        kmem.init(start, end);
+        unsafe { kmem.free(NonNull::new(start.wrapping_add(8)).unwrap()) };
+    }
+
+    #[test]
+    #[should_panic(expected = "kfree")]
+    fn test_free_outside_region_panics() {
+        let mut arena = arena();
+        let (start, end) = bounds(&mut arena);
+
+        let mut kmem = Kmem::new();
+        kmem.init(start, start.wrapping_add(PG_SIZE));
+        unsafe { kmem.free_range(start, end) };
+    }
+}
@@ -1,16 +1,99 @@
-#![cfg_attr(not(test), no_std)]
-
-// page crate example
-
-pub fn hello() {
-    // dummy function
-}
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn test_hello() {
-        super::hello();
-        assert_eq!(2 + 2, 4);
-    }
-}
+#![cfg_attr(not(test), no_std)]
+//! x86 paging calculations (inherited xv6 mmu.h)
+//!
+//! A virtual address `la` has a three-part structure as follows:
+//!
+//! ```txt
+//! +--------10------+-------10-------+---------12----------+
+//! | Page Directory |   Page Table   | Offset within Page  |
+//! |      Index     |      Index     |                     |
+//! +----------------+----------------+---------------------+
+//!  \--- pdx(va) --/ \--- ptx(va) --/
+//! ```
+
+mod pte;
+mod table;
+
+pub use self::pte::{Pte, PteFlags};
+pub use self::table::{FrameAllocator, MapError, PageDirectory, PageTable, PhysMapper};
+
+/// Control Register flags
+pub mod cr {
+    /// Protection Enable
+    pub const CR0_PE: u32 = 0x0000_0001;
+    /// Write Protect
+    pub const CR0_WP: u32 = 0x0001_0000;
+    /// Paging
+    pub const CR0_PG: u32 = 0x8000_0000;
+
+    /// Page size extension
+    pub const CR4_PSE: u32 = 0x0000_0010;
+}
+
+/// Directory entries per page directory
+pub const N_PDENTRIES: usize = 1024;
+/// PTEs per page table
+pub const N_PTENTRIES: usize = 1024;
+/// Bytes mapped by a page
+pub const PG_SIZE: usize = 4096;
+/// Bytes mapped by a page directory entry with [`PteFlags::LARGE`] set (4 MiB)
+pub const LARGE_PG_SIZE: usize = PG_SIZE * N_PTENTRIES;
+
+/// Offset of PTX in a linear address
+pub const PTX_SHIFT: usize = 12;
+/// Offset of PDX in a linear address
+pub const PDX_SHIFT: usize = 22;
+
+/// Page directory index
+#[inline]
+pub const fn pdx(va: usize) -> usize {
+    (va >> PDX_SHIFT) & 0x3FF
+}
+
+/// Page table index
+#[inline]
+pub const fn ptx(va: usize) -> usize {
+    (va >> PTX_SHIFT) & 0x3FF
+}
+
+/// Construct virtual address from indexes and offset
+#[inline]
+pub const fn pg_addr(pdx: usize, ptx: usize, offset: usize) -> usize {
+    (pdx << PDX_SHIFT) | (ptx << PTX_SHIFT) | offset
+}
+
+/// Rounds `addr` up to the next page boundary.
+#[inline]
+pub const fn pg_round_up(addr: usize) -> usize {
+    (addr + PG_SIZE - 1) & !(PG_SIZE - 1)
+}
+
+/// Rounds `addr` down to the start of its page.
+#[inline]
+pub const fn pg_round_down(addr: usize) -> usize {
+    addr & !(PG_SIZE - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pg_round() {
+        assert_eq!(pg_round_up(0), 0);
+        assert_eq!(pg_round_up(1), PG_SIZE);
+        assert_eq!(pg_round_up(PG_SIZE), PG_SIZE);
+        assert_eq!(pg_round_down(PG_SIZE + 1), PG_SIZE);
+        assert_eq!(pg_round_down(PG_SIZE - 1), 0);
+    }
+
+    #[test]
+    fn test_indexes() {
+        let va = 0x8010_2345;
+        assert_eq!(pdx(va), 0x200);
+        assert_eq!(ptx(va), 0x102);
+        assert_eq!(pg_addr(pdx(va), ptx(va), va & 0xFFF), va);
+        assert_eq!(pdx(0xFFFF_FFFF), N_PDENTRIES - 1);
+        assert_eq!(ptx(0xFFFF_FFFF), N_PTENTRIES - 1);
+    }
+}
@@ -0,0 +1,207 @@
+use core::fmt;
+use core::ops::{BitAnd, BitOr, BitOrAssign};
+
+/// Page table/directory entry flags.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct PteFlags(u32);
+
+impl PteFlags {
+    /// Present (`PTE_P`)
+    pub const PRESENT: Self = Self(0x001);
+    /// Writeable (`PTE_W`)
+    pub const WRITABLE: Self = Self(0x002);
+    /// User (`PTE_U`)
+    pub const USER: Self = Self(0x004);
+    /// Write-Through (`PTE_PWT`)
+    pub const WRITE_THROUGH: Self = Self(0x008);
+    /// Cache-Disable (`PTE_PCD`)
+    pub const CACHE_DISABLE: Self = Self(0x010);
+    /// Accessed (`PTE_A`)
+    pub const ACCESSED: Self = Self(0x020);
+    /// Dirty (`PTE_D`)
+    pub const DIRTY: Self = Self(0x040);
+    /// Page Size (`PTE_PS`). Only valid in a page directory entry: maps 4 MiB directly.
+    pub const LARGE: Self = Self(0x080);
+
+    /// Mask of the bits an entry may use for flags.
+    const MASK: u32 = 0xFFF;
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Keeps only the low 12 bits of `bits`.
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Self(bits & Self::MASK)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// `const` version of `|`.
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Whether all flags in `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for PteFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl BitOrAssign for PteFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.union(rhs);
+    }
+}
+
+impl BitAnd for PteFlags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl fmt::Debug for PteFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const NAMES: [(PteFlags, &str); 8] = [
+            (PteFlags::PRESENT, "P"),
+            (PteFlags::WRITABLE, "W"),
+            (PteFlags::USER, "U"),
+            (PteFlags::WRITE_THROUGH, "PWT"),
+            (PteFlags::CACHE_DISABLE, "PCD"),
+            (PteFlags::ACCESSED, "A"),
+            (PteFlags::DIRTY, "D"),
+            (PteFlags::LARGE, "PS"),
+        ];
+
+        let mut first = true;
+        for (flag, name) in NAMES {
+            if self.contains(flag) {
+                if !first {
+                    f.write_str("|")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+        if first {
+            f.write_str("-")?;
+        }
+        Ok(())
+    }
+}
+
+/// Page table entry. Page directory entries share the same layout.
+///
+/// ```txt
+///  31                      12 11        0
+/// +--------------------------+-----------+
+/// | Physical page address    |   Flags   |
+/// +--------------------------+-----------+
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct Pte(u32);
+
+impl Pte {
+    /// Entry that maps nothing.
+    pub const EMPTY: Self = Self(0);
+
+    /// Creates an entry pointing at the page-aligned physical address `pa`.
+    pub const fn new(pa: usize, flags: PteFlags) -> Self {
+        Self((pa as u32 & !PteFlags::MASK) | flags.bits())
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Address in page table or page directory entry (`PTE_ADDR`)
+    pub const fn addr(self) -> usize {
+        (self.0 & !PteFlags::MASK) as usize
+    }
+
+    /// Flags in page table or page directory entry (`PTE_FLAGS`)
+    pub const fn flags(self) -> PteFlags {
+        PteFlags::from_bits_truncate(self.0)
+    }
+
+    pub const fn is_present(self) -> bool {
+        self.flags().contains(PteFlags::PRESENT)
+    }
+
+    /// Whether this directory entry maps a 4 MiB page instead of a page table.
+    pub const fn is_large(self) -> bool {
+        self.flags().contains(PteFlags::LARGE)
+    }
+
+    pub const fn set_flags(&mut self, flags: PteFlags) {
+        *self = Self::new(self.addr(), flags);
+    }
+
+    pub const fn clear(&mut self) {
+        *self = Self::EMPTY;
+    }
+}
+
+impl fmt::Debug for Pte {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pte({:#010x} {:?})", self.addr(), self.flags())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pte_fields() {
+        let flags = PteFlags::PRESENT | PteFlags::WRITABLE | PteFlags::USER;
+        let pte = Pte::new(0x0012_3000, flags);
+
+        assert_eq!(pte.bits(), 0x0012_3007);
+        assert_eq!(pte.addr(), 0x0012_3000);
+        assert_eq!(pte.flags(), flags);
+        assert!(pte.is_present());
+        assert!(!pte.is_large());
+        assert!(!Pte::EMPTY.is_present());
+    }
+
+    #[test]
+    fn test_pte_set_flags_keeps_addr() {
+        let mut pte = Pte::new(0x5000, PteFlags::PRESENT | PteFlags::WRITABLE);
+        pte.set_flags(pte.flags().difference(PteFlags::WRITABLE));
+        assert_eq!(pte, Pte::new(0x5000, PteFlags::PRESENT));
+    }
+
+    #[test]
+    fn test_flags_debug() {
+        let flags = PteFlags::PRESENT | PteFlags::WRITABLE | PteFlags::LARGE;
+        assert_eq!(format!("{flags:?}"), "P|W|PS");
+        assert_eq!(format!("{:?}", PteFlags::empty()), "-");
+        assert_eq!(
+            format!("{:?}", Pte::new(0x1000, PteFlags::PRESENT)),
+            "Pte(0x00001000 P)"
+        );
+    }
+}
@@ -0,0 +1,569 @@
+use core::fmt;
+use core::ops::{Index, IndexMut};
+
+use crate::{
+    N_PDENTRIES, N_PTENTRIES, PG_SIZE, Pte, PteFlags, pdx, pg_addr, pg_round_down, ptx,
+};
+
+/// Turns the physical address of a page table page into a pointer the
+/// current address space can dereference (xv6 `P2V`).
+///
+/// # Safety
+/// For every frame reachable from a page directory walked with this mapper,
+/// [`Self::phys_to_virt`] must return a pointer valid for reads and writes of
+/// [`PG_SIZE`] bytes that nothing else references while the walk runs.
+pub unsafe trait PhysMapper {
+    fn phys_to_virt(&self, pa: usize) -> *mut u8;
+}
+
+/// Source of frames for page table pages.
+pub trait FrameAllocator: PhysMapper {
+    /// Allocates one page-sized frame and returns its physical address.
+    ///
+    /// Returns `None` if the memory cannot be allocated.
+    fn allocate_frame(&mut self) -> Option<usize>;
+
+    /// Returns the frame at physical address `pa` to the allocator.
+    ///
+    /// # Safety
+    /// The frame must have come from [`Self::allocate_frame`] and must not be
+    /// used after this call.
+    unsafe fn deallocate_frame(&mut self, pa: usize);
+}
+
+/// Error returned when a mapping cannot be created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    /// No frame left for a page table page.
+    OutOfMemory,
+    /// The page at `va` is already mapped.
+    Remap { va: usize },
+    /// `va` lies in a 4 MiB page, which has no page table to walk.
+    LargePage { va: usize },
+    /// The page at `va` should be mapped but is not.
+    NotMapped { va: usize },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfMemory => f.write_str("out of memory for page table"),
+            Self::Remap { va } => write!(f, "remap of {va:#x}"),
+            Self::LargePage { va } => write!(f, "{va:#x} is inside a 4 MiB page"),
+            Self::NotMapped { va } => write!(f, "{va:#x} is not mapped"),
+        }
+    }
+}
+
+impl core::error::Error for MapError {}
+
+/// Second level of the x86 two-level page table.
+#[derive(Debug, Clone)]
+#[repr(C, align(4096))]
+pub struct PageTable {
+    entries: [Pte; N_PTENTRIES],
+}
+const _: () = assert!(core::mem::size_of::<PageTable>() == PG_SIZE);
+
+impl Default for PageTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PageTable {
+    pub const fn new() -> Self {
+        Self {
+            entries: [Pte::EMPTY; N_PTENTRIES],
+        }
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, Pte> {
+        self.entries.iter()
+    }
+}
+
+impl Index<usize> for PageTable {
+    type Output = Pte;
+
+    fn index(&self, index: usize) -> &Pte {
+        &self.entries[index]
+    }
+}
+
+impl IndexMut<usize> for PageTable {
+    fn index_mut(&mut self, index: usize) -> &mut Pte {
+        &mut self.entries[index]
+    }
+}
+
+/// First level of the x86 two-level page table, loaded into `%cr3`.
+#[derive(Debug, Clone)]
+#[repr(C, align(4096))]
+pub struct PageDirectory {
+    entries: [Pte; N_PDENTRIES],
+}
+const _: () = assert!(core::mem::size_of::<PageDirectory>() == PG_SIZE);
+
+impl Default for PageDirectory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PageDirectory {
+    pub const fn new() -> Self {
+        Self {
+            entries: [Pte::EMPTY; N_PDENTRIES],
+        }
+    }
+
+    /// `const` builder used for statically allocated directories.
+    pub const fn with_entry(mut self, index: usize, pde: Pte) -> Self {
+        self.entries[index] = pde;
+        self
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, Pte> {
+        self.entries.iter()
+    }
+
+    /// Returns the PTE that maps virtual address `va`, without creating any
+    /// page table page (xv6 `walkpgdir(pgdir, va, 0)`).
+    ///
+    /// Returns `None` if the page table for `va` does not exist.
+    pub fn walk<M>(&mut self, va: usize, mapper: &M) -> Option<&mut Pte>
+    where
+        M: PhysMapper + ?Sized,
+    {
+        let pde = self.entries[pdx(va)];
+        if !pde.is_present() || pde.is_large() {
+            return None;
+        }
+        let table = unsafe { &mut *mapper.phys_to_virt(pde.addr()).cast::<PageTable>() };
+        Some(&mut table[ptx(va)])
+    }
+
+    /// Returns the PTE that maps virtual address `va`, allocating the page
+    /// table page from `frames` if needed (xv6 `walkpgdir(pgdir, va, 1)`).
+    ///
+    /// # Errors
+    /// - [`MapError::OutOfMemory`] if no frame is left for the page table.
+    /// - [`MapError::LargePage`] if `va` is covered by a 4 MiB page.
+    pub fn walk_alloc<A>(&mut self, va: usize, frames: &mut A) -> Result<&mut Pte, MapError>
+    where
+        A: FrameAllocator + ?Sized,
+    {
+        let pde = &mut self.entries[pdx(va)];
+        if pde.is_large() {
+            return Err(MapError::LargePage { va });
+        }
+        if !pde.is_present() {
+            let pa = frames.allocate_frame().ok_or(MapError::OutOfMemory)?;
+            // Make sure all those PTE_P bits are zero.
+            unsafe { frames.phys_to_virt(pa).write_bytes(0, PG_SIZE) };
+            // The permissions here are overly generous, but they can
+            // be further restricted by the permissions in the page table
+            // entries, if necessary.
+            *pde = Pte::new(
+                pa,
+                PteFlags::PRESENT | PteFlags::WRITABLE | PteFlags::USER,
+            );
+        }
+        let table = unsafe { &mut *frames.phys_to_virt(pde.addr()).cast::<PageTable>() };
+        Ok(&mut table[ptx(va)])
+    }
+
+    /// Creates PTEs for virtual addresses starting at `va` that refer to
+    /// physical addresses starting at `pa` (xv6 `mappages`).
+    ///
+    /// `va` and `size` might not be page-aligned. An empty `size` maps nothing.
+    ///
+    /// # Errors
+    /// Same as [`Self::walk_alloc`], plus [`MapError::Remap`] if a page in
+    /// the range is already present. Pages mapped before the error stay mapped.
+    pub fn map_pages<A>(
+        &mut self,
+        va: usize,
+        size: usize,
+        pa: usize,
+        perm: PteFlags,
+        frames: &mut A,
+    ) -> Result<(), MapError>
+    where
+        A: FrameAllocator + ?Sized,
+    {
+        if size == 0 {
+            return Ok(());
+        }
+        let mut a = pg_round_down(va);
+        let last = pg_round_down(va.wrapping_add(size - 1));
+        let mut pa = pa;
+        loop {
+            let pte = self.walk_alloc(a, frames)?;
+            if pte.is_present() {
+                return Err(MapError::Remap { va: a });
+            }
+            *pte = Pte::new(pa, perm | PteFlags::PRESENT);
+            if a == last {
+                return Ok(());
+            }
+            a += PG_SIZE;
+            pa += PG_SIZE;
+        }
+    }
+
+    /// Maps a copy of each page covering `[va, va + size)` at the same
+    /// address in `dst`, with the same permissions (the copying half of xv6
+    /// `copyuvm`). The copies are fresh frames from `frames`.
+    ///
+    /// # Errors
+    /// - [`MapError::NotMapped`] if a page in the range is not present here.
+    /// - [`MapError::OutOfMemory`] if no frame is left for a copy or a page
+    ///   table.
+    /// - Same as [`Self::map_pages`] for mapping the copy in `dst`.
+    ///
+    /// Pages copied before the error stay mapped in `dst`.
+    pub fn copy_pages<A>(
+        &mut self,
+        dst: &mut Self,
+        va: usize,
+        size: usize,
+        frames: &mut A,
+    ) -> Result<(), MapError>
+    where
+        A: FrameAllocator + ?Sized,
+    {
+        if size == 0 {
+            return Ok(());
+        }
+        let mut a = pg_round_down(va);
+        let last = pg_round_down(va.wrapping_add(size - 1));
+        loop {
+            let pte = match self.walk(a, frames) {
+                Some(pte) if pte.is_present() => *pte,
+                _ => return Err(MapError::NotMapped { va: a }),
+            };
+            let pa = frames.allocate_frame().ok_or(MapError::OutOfMemory)?;
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    frames.phys_to_virt(pte.addr()),
+                    frames.phys_to_virt(pa),
+                    PG_SIZE,
+                );
+            }
+            if let Err(err) = dst.map_pages(a, PG_SIZE, pa, pte.flags(), frames) {
+                unsafe { frames.deallocate_frame(pa) };
+                return Err(err);
+            }
+            if a == last {
+                return Ok(());
+            }
+            a += PG_SIZE;
+        }
+    }
+
+    /// Removes the mappings of the pages covering `[va, va + size)`.
+    ///
+    /// Pages that are not mapped are skipped, including whole 4 MiB ranges
+    /// without a page table. If `free_frames` is set, the physical frames of
+    /// the removed mappings are returned to `frames`.
+    ///
+    /// Returns the number of pages unmapped.
+    ///
+    /// # Safety
+    /// If `free_frames` is set, the mapped frames must have come from `frames`
+    /// and must not be referenced elsewhere.
+    pub unsafe fn unmap_pages<A>(
+        &mut self,
+        va: usize,
+        size: usize,
+        frames: &mut A,
+        free_frames: bool,
+    ) -> usize
+    where
+        A: FrameAllocator + ?Sized,
+    {
+        if size == 0 {
+            return 0;
+        }
+        let mut unmapped = 0;
+        let mut a = pg_round_down(va);
+        let last = pg_round_down(va.wrapping_add(size - 1));
+        loop {
+            match self.walk(a, frames) {
+                Some(pte) if pte.is_present() => {
+                    let pa = pte.addr();
+                    pte.clear();
+                    if free_frames {
+                        unsafe { frames.deallocate_frame(pa) };
+                    }
+                    unmapped += 1;
+                }
+                Some(_) => {}
+                // Skip the rest of this page directory entry.
+                None => a = pg_addr(pdx(a), N_PTENTRIES - 1, 0),
+            }
+            if a >= last {
+                return unmapped;
+            }
+            a += PG_SIZE;
+        }
+    }
+
+    /// Frees every page table page this directory points to and clears the
+    /// corresponding entries (the page table half of xv6 `freevm`).
+    ///
+    /// Frames mapped by the page tables are left alone; unmap them first with
+    /// [`Self::unmap_pages`] if they should be freed too.
+    ///
+    /// # Safety
+    /// The page table pages must have come from `frames` and the directory
+    /// must not be in use by any CPU.
+    pub unsafe fn free_tables<A>(&mut self, frames: &mut A)
+    where
+        A: FrameAllocator + ?Sized,
+    {
+        for pde in &mut self.entries {
+            if pde.is_present() && !pde.is_large() {
+                unsafe { frames.deallocate_frame(pde.addr()) };
+            }
+            pde.clear();
+        }
+    }
+}
+
+impl Index<usize> for PageDirectory {
+    type Output = Pte;
+
+    fn index(&self, index: usize) -> &Pte {
+        &self.entries[index]
+    }
+}
+
+impl IndexMut<usize> for PageDirectory {
+    fn index_mut(&mut self, index: usize) -> &mut Pte {
+        &mut self.entries[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const N_FRAMES: usize = 8;
+    /// Fake physical address of the first arena frame.
+    const ARENA_BASE: usize = 0x10_0000;
+
+    /// Frames handed out from a (leaked) host buffer.
+    struct ArenaFrames {
+        base: *mut PageTable,
+        free: Vec<usize>,
+    }
+
+    impl ArenaFrames {
+        fn new() -> Self {
+            let arena: Box<[PageTable; N_FRAMES]> =
+                Box::new(core::array::from_fn(|_| PageTable::new()));
+            Self {
+                base: Box::into_raw(arena).cast(),
+                free: (0..N_FRAMES).rev().map(|i| ARENA_BASE + i * PG_SIZE).collect(),
+            }
+        }
+    }
+
+    unsafe impl PhysMapper for ArenaFrames {
+        fn phys_to_virt(&self, pa: usize) -> *mut u8 {
+            let index = (pa - ARENA_BASE) / PG_SIZE;
+            assert!(index < N_FRAMES, "{pa:#x} is not an arena frame");
+            self.base.wrapping_add(index).cast()
+        }
+    }
+
+    impl FrameAllocator for ArenaFrames {
+        fn allocate_frame(&mut self) -> Option<usize> {
+            self.free.pop()
+        }
+
+        unsafe fn deallocate_frame(&mut self, pa: usize) {
+            assert!(!self.free.contains(&pa), "double free of {pa:#x}");
+            self.free.push(pa);
+        }
+    }
+
+    const RW: PteFlags = PteFlags::WRITABLE.union(PteFlags::USER);
+
+    #[test]
+    fn test_map_then_walk() {
+        let mut frames = ArenaFrames::new();
+        let mut pgdir = PageDirectory::new();
+
+        pgdir
+            .map_pages(0x1234, 2 * PG_SIZE, 0x4000_0000, RW, &mut frames)
+            .unwrap();
+
+        // Unaligned va/size spans three pages.
+        for (i, va) in [0x1000, 0x2000, 0x3000].into_iter().enumerate() {
+            let pte = *pgdir.walk(va, &frames).unwrap();
+            assert_eq!(pte.addr(), 0x4000_0000 + i * PG_SIZE);
+            assert_eq!(pte.flags(), RW | PteFlags::PRESENT);
+        }
+        assert!(!pgdir.walk(0x4000, &frames).unwrap().is_present());
+        assert!(pgdir.walk(0x40_0000, &frames).is_none());
+
+        // One page table page was used.
+        assert_eq!(frames.free.len(), N_FRAMES - 1);
+        assert!(pgdir[0].flags().contains(PteFlags::USER));
+    }
+
+    #[test]
+    fn test_map_across_page_tables() {
+        let mut frames = ArenaFrames::new();
+        let mut pgdir = PageDirectory::new();
+
+        let va = 0x40_0000 - PG_SIZE;
+        pgdir.map_pages(va, 2 * PG_SIZE, 0, RW, &mut frames).unwrap();
+        assert_eq!(frames.free.len(), N_FRAMES - 2);
+        assert_eq!(pgdir.walk(va + PG_SIZE, &frames).unwrap().addr(), PG_SIZE);
+    }
+
+    #[test]
+    fn test_map_top_of_address_space() {
+        let mut frames = ArenaFrames::new();
+        let mut pgdir = PageDirectory::new();
+
+        let va = 0xFFFF_F000;
+        pgdir.map_pages(va, PG_SIZE, 0xFEE0_0000, RW, &mut frames).unwrap();
+        assert_eq!(pgdir.walk(va, &frames).unwrap().addr(), 0xFEE0_0000);
+    }
+
+    #[test]
+    fn test_remap_is_error() {
+        let mut frames = ArenaFrames::new();
+        let mut pgdir = PageDirectory::new();
+
+        pgdir.map_pages(0, PG_SIZE, 0, RW, &mut frames).unwrap();
+        assert_eq!(
+            pgdir.map_pages(0, PG_SIZE, 0x1000, RW, &mut frames),
+            Err(MapError::Remap { va: 0 })
+        );
+    }
+
+    #[test]
+    fn test_large_page_is_error() {
+        let mut frames = ArenaFrames::new();
+        let mut pgdir = PageDirectory::new()
+            .with_entry(1, Pte::new(0, PteFlags::PRESENT | PteFlags::LARGE));
+
+        assert!(pgdir.walk(0x40_0000, &frames).is_none());
+        assert_eq!(
+            pgdir.map_pages(0x40_0000, PG_SIZE, 0, RW, &mut frames),
+            Err(MapError::LargePage { va: 0x40_0000 })
+        );
+    }
+
+    #[test]
+    fn test_out_of_memory() {
+        let mut frames = ArenaFrames::new();
+        let mut pgdir = PageDirectory::new();
+
+        // Every page directory entry needs its own page table page.
+        let size = (N_FRAMES + 1) * N_PTENTRIES * PG_SIZE;
+        assert_eq!(
+            pgdir.map_pages(0, size, 0, RW, &mut frames),
+            Err(MapError::OutOfMemory)
+        );
+    }
+
+    #[test]
+    fn test_copy_pages() {
+        let mut frames = ArenaFrames::new();
+        let mut src = PageDirectory::new();
+        let mut dst = PageDirectory::new();
+
+        let pa = frames.allocate_frame().unwrap();
+        unsafe { frames.phys_to_virt(pa).write_bytes(0xA5, PG_SIZE) };
+        src.map_pages(0, PG_SIZE, pa, RW, &mut frames).unwrap();
+        src.map_pages(PG_SIZE, PG_SIZE, pa, PteFlags::USER, &mut frames)
+            .unwrap();
+
+        src.copy_pages(&mut dst, 0, 2 * PG_SIZE, &mut frames)
+            .unwrap();
+        for (va, flags) in [(0, RW), (PG_SIZE, PteFlags::USER)] {
+            let pte = *dst.walk(va, &frames).unwrap();
+            assert_ne!(pte.addr(), pa, "the copy has its own frame");
+            assert_eq!(pte.flags(), flags | PteFlags::PRESENT);
+            let copy = unsafe { *frames.phys_to_virt(pte.addr()).add(PG_SIZE - 1) };
+            assert_eq!(copy, 0xA5);
+        }
+        // Source frame, two page tables and two copies.
+        assert_eq!(frames.free.len(), N_FRAMES - 5);
+    }
+
+    #[test]
+    fn test_copy_unmapped_is_error() {
+        let mut frames = ArenaFrames::new();
+        let mut src = PageDirectory::new();
+        let mut dst = PageDirectory::new();
+
+        let pa = frames.allocate_frame().unwrap();
+        src.map_pages(0, PG_SIZE, pa, RW, &mut frames).unwrap();
+        assert_eq!(
+            src.copy_pages(&mut dst, 0, 2 * PG_SIZE, &mut frames),
+            Err(MapError::NotMapped { va: PG_SIZE })
+        );
+        assert_eq!(
+            src.copy_pages(&mut dst, 0x40_0000, PG_SIZE, &mut frames),
+            Err(MapError::NotMapped { va: 0x40_0000 })
+        );
+    }
+
+    #[test]
+    fn test_unmap_frees_frames() {
+        let mut frames = ArenaFrames::new();
+        let mut pgdir = PageDirectory::new();
+
+        let mut mapped = Vec::new();
+        for i in 0..3 {
+            let pa = frames.allocate_frame().unwrap();
+            let va = i * 0x40_0000; // one page table each
+            pgdir.map_pages(va, PG_SIZE, pa, RW, &mut frames).unwrap();
+            mapped.push(pa);
+        }
+        assert_eq!(frames.free.len(), N_FRAMES - 6);
+
+        let unmapped = unsafe { pgdir.unmap_pages(0, 3 * 0x40_0000, &mut frames, true) };
+        assert_eq!(unmapped, 3);
+        assert_eq!(frames.free.len(), N_FRAMES - 3);
+        for pa in mapped {
+            assert!(frames.free.contains(&pa));
+        }
+        assert!(!pgdir.walk(0, &frames).unwrap().is_present());
+    }
+
+    #[test]
+    fn test_unmap_skips_missing_tables() {
+        let mut frames = ArenaFrames::new();
+        let mut pgdir = PageDirectory::new();
+
+        pgdir.map_pages(0x80_0000, PG_SIZE, 0, RW, &mut frames).unwrap();
+        let unmapped = unsafe { pgdir.unmap_pages(0, 0x100_0000, &mut frames, false) };
+        assert_eq!(unmapped, 1);
+        assert_eq!(unsafe { pgdir.unmap_pages(0, 0, &mut frames, false) }, 0);
+    }
+
+    #[test]
+    fn test_free_tables() {
+        let mut frames = ArenaFrames::new();
+        let mut pgdir = PageDirectory::new()
+            .with_entry(3, Pte::new(0, PteFlags::PRESENT | PteFlags::LARGE));
+
+        pgdir.map_pages(0, PG_SIZE, 0, RW, &mut frames).unwrap();
+        pgdir.map_pages(0x40_0000, PG_SIZE, 0, RW, &mut frames).unwrap();
+        assert_eq!(frames.free.len(), N_FRAMES - 2);
+
+        unsafe { pgdir.free_tables(&mut frames) };
+        assert_eq!(frames.free.len(), N_FRAMES);
+        assert!(pgdir.iter().all(|pde| !pde.is_present()));
+    }
+}
@@ -0,0 +1,170 @@
+use core::fmt;
+
+use crate::Ring;
+
+/// Segment descriptor: one entry of the GDT.
+///
+/// ```txt
+///  63      56 55 54 53 52 51    48 47 46 45 44 43    40 39             16 15           0
+/// +----------+--+--+--+--+--------+--+-----+--+--------+-----------------+--------------+
+/// |base 31:24| G|DB| L|AV|lim 19:16| P| DPL | S|  type  |    base 23:0    |  limit 15:0  |
+/// +----------+--+--+--+--+--------+--+-----+--+--------+-----------------+--------------+
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct SegmentDescriptor(u64);
+
+/// Executable segment (`STA_X`)
+const STA_X: u8 = 0x8;
+/// Writeable (non-executable segments) (`STA_W`)
+const STA_W: u8 = 0x2;
+/// Readable (executable segments) (`STA_R`)
+const STA_R: u8 = 0x2;
+/// Available 32-bit TSS (`STS_T32A`)
+const STS_T32A: u8 = 0x9;
+
+/// Code or data segment, as opposed to a system segment
+const S: u64 = 1 << 44;
+/// Present
+const P: u64 = 1 << 47;
+/// 32-bit default operand size
+const DB: u64 = 1 << 54;
+/// Limit is in 4 KiB units
+const G: u64 = 1 << 55;
+
+impl SegmentDescriptor {
+    /// The mandatory first entry.
+    pub const NULL: Self = Self(0);
+
+    /// Flat execute/read segment (`SEG(STA_X|STA_R, 0, 0xffffffff, dpl)`).
+    pub const fn code(dpl: Ring) -> Self {
+        Self::new(STA_X | STA_R, 0, 0xFFFF_FFFF, dpl).with(S | DB)
+    }
+
+    /// Flat read/write segment (`SEG(STA_W, 0, 0xffffffff, dpl)`).
+    pub const fn data(dpl: Ring) -> Self {
+        Self::new(STA_W, 0, 0xFFFF_FFFF, dpl).with(S | DB)
+    }
+
+    /// Available 32-bit TSS at `base`, reachable from ring 0 only
+    /// (`SEG16(STS_T32A, base, limit, 0)` with `s = 0`).
+    pub const fn tss(base: u32, limit: u32) -> Self {
+        Self::new(STS_T32A, base, limit, Ring::Kernel)
+    }
+
+    /// A present segment. Limits above 1 MiB switch to 4 KiB granularity,
+    /// so their low 12 bits are dropped.
+    const fn new(ty: u8, base: u32, limit: u32, dpl: Ring) -> Self {
+        let (limit, granularity) = if limit > 0xF_FFFF {
+            (limit >> 12, G)
+        } else {
+            (limit, 0)
+        };
+        let base = base as u64;
+        let limit = limit as u64;
+        Self(
+            (limit & 0xFFFF)
+                | ((base & 0xFF_FFFF) << 16)
+                | ((ty as u64) << 40)
+                | ((dpl as u64) << 45)
+                | P
+                | ((limit >> 16) << 48)
+                | granularity
+                | ((base >> 24) << 56),
+        )
+    }
+
+    const fn with(self, bits: u64) -> Self {
+        Self(self.0 | bits)
+    }
+
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    pub const fn base(self) -> u32 {
+        (((self.0 >> 16) & 0xFF_FFFF) | ((self.0 >> 56) << 24)) as u32
+    }
+
+    /// Offset of the last byte of the segment.
+    pub const fn limit(self) -> u32 {
+        let limit = ((self.0 & 0xFFFF) | ((self.0 >> 32) & 0xF_0000)) as u32;
+        if self.0 & G != 0 {
+            (limit << 12) | 0xFFF
+        } else {
+            limit
+        }
+    }
+
+    /// Descriptor privilege level.
+    pub const fn dpl(self) -> Option<Ring> {
+        Ring::from_bits((self.0 >> 45) as u8)
+    }
+
+    pub const fn is_present(self) -> bool {
+        self.0 & P != 0
+    }
+}
+
+impl fmt::Debug for SegmentDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_present() {
+            return write!(f, "SegmentDescriptor(null)");
+        }
+        write!(
+            f,
+            "SegmentDescriptor(base {:#010x}, limit {:#010x}, type {:#x}, {:?})",
+            self.base(),
+            self.limit(),
+            (self.0 >> 40) & 0xF,
+            self.dpl()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_segments() {
+        // Same bits as xv6's SEG() and the bootloader's SEG_ASM.
+        assert_eq!(
+            SegmentDescriptor::code(Ring::Kernel).bits(),
+            0x00CF_9A00_0000_FFFF
+        );
+        assert_eq!(
+            SegmentDescriptor::data(Ring::Kernel).bits(),
+            0x00CF_9200_0000_FFFF
+        );
+        assert_eq!(
+            SegmentDescriptor::code(Ring::User).bits(),
+            0x00CF_FA00_0000_FFFF
+        );
+        assert_eq!(
+            SegmentDescriptor::data(Ring::User).bits(),
+            0x00CF_F200_0000_FFFF
+        );
+
+        let user_data = SegmentDescriptor::data(Ring::User);
+        assert_eq!(user_data.base(), 0);
+        assert_eq!(user_data.limit(), 0xFFFF_FFFF);
+        assert_eq!(user_data.dpl(), Some(Ring::User));
+    }
+
+    #[test]
+    fn test_tss() {
+        let tss = SegmentDescriptor::tss(0x8010_5A40, 103);
+        assert_eq!(tss.bits(), 0x8000_8910_5A40_0067);
+        assert_eq!(tss.base(), 0x8010_5A40);
+        assert_eq!(tss.limit(), 103);
+        assert_eq!(tss.dpl(), Some(Ring::Kernel));
+        assert!(tss.is_present());
+    }
+
+    #[test]
+    fn test_null() {
+        assert!(!SegmentDescriptor::NULL.is_present());
+        assert_eq!(SegmentDescriptor::default(), SegmentDescriptor::NULL);
+    }
+}
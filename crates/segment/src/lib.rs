@@ -0,0 +1,110 @@
+#![cfg_attr(not(test), no_std)]
+//! x86 segmentation (inherited xv6 mmu.h)
+//!
+//! Segmentation is only used the way xv6 uses it: every code and data
+//! segment is flat (base 0, limit 4 GiB) and they differ only in privilege
+//! level. Each CPU also has one task state segment, which tells the CPU the
+//! kernel stack to switch to when an interrupt arrives in user mode.
+//!
+//! ```txt
+//! GDT (per CPU)
+//! +---+---------+----------+----------+----------+----------+-----+
+//! | # |    0    |    1     |    2     |    3     |    4     |  5  |
+//! |   |  null   |  KCODE   |  KDATA   |  UCODE   |  UDATA   | TSS |
+//! |   |         |  ring 0  |  ring 0  |  ring 3  |  ring 3  |     |
+//! +---+---------+----------+----------+----------+----------+-----+
+//! ```
+
+mod descriptor;
+mod tss;
+
+pub use self::descriptor::SegmentDescriptor;
+pub use self::tss::TaskState;
+
+/// Kernel code
+pub const SEG_KCODE: usize = 1;
+/// Kernel data + stack
+pub const SEG_KDATA: usize = 2;
+/// User code
+pub const SEG_UCODE: usize = 3;
+/// User data + stack
+pub const SEG_UDATA: usize = 4;
+/// This process's task state
+pub const SEG_TSS: usize = 5;
+
+/// Number of entries of a CPU's GDT
+pub const NSEGS: usize = 6;
+
+/// Privilege level of a segment or selector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Ring {
+    Kernel = 0,
+    /// `DPL_USER`
+    User = 3,
+}
+
+impl Ring {
+    /// Returns `None` for the rings xv6 does not use (1 and 2).
+    pub const fn from_bits(bits: u8) -> Option<Self> {
+        match bits & 3 {
+            0 => Some(Self::Kernel),
+            3 => Some(Self::User),
+            _ => None,
+        }
+    }
+}
+
+/// Value loaded into a segment register: GDT index and requested privilege level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct SegmentSelector(u16);
+
+impl SegmentSelector {
+    /// `SEG_KCODE << 3`
+    pub const KERNEL_CODE: Self = Self::new(SEG_KCODE, Ring::Kernel);
+    /// `SEG_KDATA << 3`
+    pub const KERNEL_DATA: Self = Self::new(SEG_KDATA, Ring::Kernel);
+    /// `(SEG_UCODE << 3) | DPL_USER`
+    pub const USER_CODE: Self = Self::new(SEG_UCODE, Ring::User);
+    /// `(SEG_UDATA << 3) | DPL_USER`
+    pub const USER_DATA: Self = Self::new(SEG_UDATA, Ring::User);
+    /// `SEG_TSS << 3`
+    pub const TSS: Self = Self::new(SEG_TSS, Ring::Kernel);
+
+    pub const fn new(index: usize, rpl: Ring) -> Self {
+        Self(((index as u16) << 3) | rpl as u16)
+    }
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    /// Index into the GDT.
+    pub const fn index(self) -> usize {
+        (self.0 >> 3) as usize
+    }
+
+    /// Requested privilege level.
+    pub const fn rpl(self) -> Option<Ring> {
+        Ring::from_bits(self.0 as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selectors() {
+        assert_eq!(SegmentSelector::KERNEL_CODE.bits(), 0x08);
+        assert_eq!(SegmentSelector::KERNEL_DATA.bits(), 0x10);
+        assert_eq!(SegmentSelector::USER_CODE.bits(), 0x1B);
+        assert_eq!(SegmentSelector::USER_DATA.bits(), 0x23);
+        assert_eq!(SegmentSelector::TSS.bits(), 0x28);
+
+        assert_eq!(SegmentSelector::USER_DATA.index(), SEG_UDATA);
+        assert_eq!(SegmentSelector::USER_DATA.rpl(), Some(Ring::User));
+        assert_eq!(SegmentSelector(0x09).rpl(), None);
+    }
+}
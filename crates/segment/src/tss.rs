@@ -0,0 +1,64 @@
+/// 32-bit task state segment (xv6 `struct taskstate`).
+///
+/// xv6 does not use hardware task switching; the CPU only reads `ss0:esp0`
+/// from here to find the kernel stack when an interrupt or system call
+/// arrives in user mode, and `iomb` to decide whether user code may use
+/// `in`/`out`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct TaskState {
+    /// Old ts selector
+    link: u32,
+    /// Stack pointer after an increase in privilege level
+    pub esp0: u32,
+    /// Stack segment after an increase in privilege level
+    pub ss0: u16,
+    _padding: u16,
+    /// Rings 1 and 2 stacks, saved registers and segment selectors: unused.
+    _unused: [u32; 22],
+    /// Trap on task switch
+    trap: u16,
+    /// I/O map base address
+    pub iomb: u16,
+}
+
+const _: () = assert!(size_of::<TaskState>() == 104);
+
+impl TaskState {
+    /// `iomb` pointing past the end of the segment: no I/O permission
+    /// bitmap, so user code faults on every `in`/`out`.
+    pub const NO_IO_BITMAP: u16 = 0xFFFF;
+
+    /// Everything zero and no I/O bitmap.
+    pub const fn new() -> Self {
+        Self {
+            link: 0,
+            esp0: 0,
+            ss0: 0,
+            _padding: 0,
+            _unused: [0; 22],
+            trap: 0,
+            iomb: Self::NO_IO_BITMAP,
+        }
+    }
+}
+
+impl Default for TaskState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::offset_of;
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(offset_of!(TaskState, esp0), 4);
+        assert_eq!(offset_of!(TaskState, ss0), 8);
+        assert_eq!(offset_of!(TaskState, iomb), 102);
+        assert_eq!(TaskState::new().iomb, TaskState::NO_IO_BITMAP);
+    }
+}
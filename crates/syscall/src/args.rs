@@ -0,0 +1,296 @@
+use core::fmt;
+
+/// Memory of the process making a system call, as the kernel reaches it.
+pub trait UserMemory {
+    /// Size of process memory: user addresses below it are valid.
+    fn size(&self) -> usize;
+
+    /// Fills `buf` with the bytes at user address `addr`.
+    ///
+    /// [`Args`] only reads ranges that end at or below [`Self::size`].
+    fn read(&self, addr: usize, buf: &mut [u8]);
+}
+
+/// Error returned when a system call argument is invalid. The call then
+/// fails with -1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgError {
+    /// Memory at `addr` lies outside the process.
+    BadAddress { addr: usize },
+    /// A string does not fit the buffer it is copied to.
+    TooLong,
+    /// `fd` is not an open file descriptor.
+    BadFd { fd: i32 },
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadAddress { addr } => write!(f, "bad user address {addr:#x}"),
+            Self::TooLong => f.write_str("string too long"),
+            Self::BadFd { fd } => write!(f, "bad file descriptor {fd}"),
+        }
+    }
+}
+
+impl core::error::Error for ArgError {}
+
+/// Arguments of a system call.
+///
+/// User code calls the system call stub like a C function, so at the `int`
+/// the stack holds the stub's return address followed by the arguments,
+/// one 32-bit word each. The process may pass anything, so every address is
+/// checked against its [`UserMemory::size`] before it is read.
+pub struct Args<'a, M: ?Sized> {
+    mem: &'a M,
+    /// User `%esp` at the `int`
+    esp: usize,
+}
+
+impl<'a, M: UserMemory + ?Sized> Args<'a, M> {
+    pub const fn new(mem: &'a M, esp: usize) -> Self {
+        Self { mem, esp }
+    }
+
+    /// Checks that `[addr, addr + len)` is inside process memory.
+    fn check(&self, addr: usize, len: usize) -> Result<(), ArgError> {
+        match addr.checked_add(len) {
+            Some(end) if addr < self.mem.size() && end <= self.mem.size() => Ok(()),
+            _ => Err(ArgError::BadAddress { addr }),
+        }
+    }
+
+    /// Fetch the int at `addr` from the current process.
+    ///
+    /// # Errors
+    /// [`ArgError::BadAddress`] if the int is not inside process memory.
+    pub fn fetchint(&self, addr: usize) -> Result<i32, ArgError> {
+        self.check(addr, size_of::<i32>())?;
+        let mut bytes = [0; size_of::<i32>()];
+        self.mem.read(addr, &mut bytes);
+        Ok(i32::from_le_bytes(bytes))
+    }
+
+    /// Fetch the nul-terminated string at `addr` from the current process.
+    /// Copies it into `buf` and returns it, without the NUL.
+    ///
+    /// # Errors
+    /// - [`ArgError::BadAddress`] if the string is not inside process memory,
+    ///   or is not terminated before its end.
+    /// - [`ArgError::TooLong`] if the string and its NUL do not fit in `buf`.
+    pub fn fetchstr<'b>(&self, addr: usize, buf: &'b mut [u8]) -> Result<&'b [u8], ArgError> {
+        self.check(addr, 0)?;
+        let avail = self.mem.size() - addr;
+        let len = buf.len().min(avail);
+        self.mem.read(addr, &mut buf[..len]);
+        match buf[..len].iter().position(|&c| c == 0) {
+            Some(n) => Ok(&buf[..n]),
+            None if len == avail => Err(ArgError::BadAddress { addr }),
+            None => Err(ArgError::TooLong),
+        }
+    }
+
+    /// Length of the nul-terminated string at `addr`, checked as by
+    /// [`Self::fetchstr`] but without copying it out.
+    ///
+    /// # Errors
+    /// [`ArgError::BadAddress`] if the string is not inside process memory,
+    /// or is not terminated before its end.
+    pub fn fetchstrlen(&self, addr: usize) -> Result<usize, ArgError> {
+        self.check(addr, 0)?;
+        let mut buf = [0; 64];
+        let mut len = 0;
+        loop {
+            let start = addr + len;
+            let n = buf.len().min(self.mem.size() - start);
+            if n == 0 {
+                return Err(ArgError::BadAddress { addr });
+            }
+            self.mem.read(start, &mut buf[..n]);
+            if let Some(i) = buf[..n].iter().position(|&c| c == 0) {
+                return Ok(len + i);
+            }
+            len += n;
+        }
+    }
+
+    /// Fetch the nth 32-bit system call argument.
+    ///
+    /// # Errors
+    /// [`ArgError::BadAddress`] if the argument is not inside process memory.
+    pub fn argint(&self, n: usize) -> Result<i32, ArgError> {
+        // Skip the return address.
+        let addr = n
+            .checked_add(1)
+            .and_then(|words| words.checked_mul(size_of::<u32>()))
+            .and_then(|offset| self.esp.checked_add(offset))
+            .ok_or(ArgError::BadAddress { addr: self.esp })?;
+        self.fetchint(addr)
+    }
+
+    /// Fetch the nth word-sized system call argument as a pointer
+    /// to a block of memory of `size` bytes. Check that the pointer
+    /// lies within the process address space, and return it.
+    ///
+    /// # Errors
+    /// [`ArgError::BadAddress`] if the argument or the block is not inside
+    /// process memory.
+    pub fn argptr(&self, n: usize, size: usize) -> Result<usize, ArgError> {
+        let addr = self.argint(n)? as u32 as usize;
+        self.check(addr, size)?;
+        Ok(addr)
+    }
+
+    /// Fetch the nth word-sized system call argument as a string pointer,
+    /// and copy the string into `buf`, as [`Self::fetchstr`] does.
+    ///
+    /// # Errors
+    /// Same as [`Self::argint`] and [`Self::fetchstr`].
+    pub fn argstr<'b>(&self, n: usize, buf: &'b mut [u8]) -> Result<&'b [u8], ArgError> {
+        let addr = self.argint(n)? as u32 as usize;
+        self.fetchstr(addr, buf)
+    }
+
+    /// Fetch the nth word-sized system call argument as a file descriptor
+    /// and return both the descriptor and the corresponding file from the
+    /// process's open files `ofile`.
+    ///
+    /// # Errors
+    /// - Same as [`Self::argint`].
+    /// - [`ArgError::BadFd`] if the descriptor is not open.
+    pub fn argfd<'f, F>(
+        &self,
+        n: usize,
+        ofile: &'f [Option<F>],
+    ) -> Result<(usize, &'f F), ArgError> {
+        let fd = self.argint(n)?;
+        usize::try_from(fd)
+            .ok()
+            .and_then(|i| Some((i, ofile.get(i)?.as_ref()?)))
+            .ok_or(ArgError::BadFd { fd })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Process memory starting at address 0.
+    struct Memory(Vec<u8>);
+
+    impl UserMemory for Memory {
+        fn size(&self) -> usize {
+            self.0.len()
+        }
+
+        fn read(&self, addr: usize, buf: &mut [u8]) {
+            buf.copy_from_slice(&self.0[addr..addr + buf.len()]);
+        }
+    }
+
+    /// A stack at the top of 64 bytes of memory, as seen by `write(1, "hi\0", 3)`
+    /// called from 0x10: the string is at 0x20.
+    fn write_call() -> (Memory, usize) {
+        let mut mem = vec![0; 64];
+        mem[0x20..0x23].copy_from_slice(b"hi\0");
+        let esp = 0x30;
+        for (i, word) in [0x10_u32, 1, 0x20, 3].into_iter().enumerate() {
+            mem[esp + 4 * i..esp + 4 * i + 4].copy_from_slice(&word.to_le_bytes());
+        }
+        (Memory(mem), esp)
+    }
+
+    #[test]
+    fn test_argint() {
+        let (mem, esp) = write_call();
+        let args = Args::new(&mem, esp);
+        assert_eq!(args.argint(0), Ok(1));
+        assert_eq!(args.argint(2), Ok(3));
+        // The last word of memory is the last argument that fits.
+        assert_eq!(args.argint(3), Err(ArgError::BadAddress { addr: 0x40 }));
+        assert_eq!(
+            args.argint(usize::MAX),
+            Err(ArgError::BadAddress { addr: esp })
+        );
+    }
+
+    #[test]
+    fn test_fetchint_bounds() {
+        let (mem, esp) = write_call();
+        let args = Args::new(&mem, esp);
+        assert_eq!(args.fetchint(60), Ok(3));
+        assert_eq!(args.fetchint(61), Err(ArgError::BadAddress { addr: 61 }));
+        assert_eq!(
+            args.fetchint(usize::MAX - 1),
+            Err(ArgError::BadAddress {
+                addr: usize::MAX - 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_argptr() {
+        let (mem, esp) = write_call();
+        let args = Args::new(&mem, esp);
+        assert_eq!(args.argptr(1, 3), Ok(0x20));
+        assert_eq!(args.argptr(1, 0x20), Ok(0x20));
+        assert_eq!(
+            args.argptr(1, 0x21),
+            Err(ArgError::BadAddress { addr: 0x20 })
+        );
+        assert_eq!(
+            args.argptr(1, usize::MAX),
+            Err(ArgError::BadAddress { addr: 0x20 })
+        );
+    }
+
+    #[test]
+    fn test_argstr() {
+        let (mem, esp) = write_call();
+        let args = Args::new(&mem, esp);
+        let mut buf = [0; 8];
+        assert_eq!(args.argstr(1, &mut buf), Ok(&b"hi"[..]));
+        // No room for the NUL.
+        assert_eq!(args.argstr(1, &mut [0; 2]), Err(ArgError::TooLong));
+    }
+
+    #[test]
+    fn test_fetchstr_unterminated() {
+        let mem = Memory(b"abc".to_vec());
+        let args = Args::new(&mem, 0);
+        assert_eq!(
+            args.fetchstr(1, &mut [0; 8]),
+            Err(ArgError::BadAddress { addr: 1 })
+        );
+        assert_eq!(
+            args.fetchstr(3, &mut [0; 8]),
+            Err(ArgError::BadAddress { addr: 3 })
+        );
+        assert_eq!(args.fetchstrlen(1), Err(ArgError::BadAddress { addr: 1 }));
+    }
+
+    #[test]
+    fn test_fetchstrlen() {
+        let mut mem = vec![b'a'; 200];
+        mem[150] = 0;
+        let mem = Memory(mem);
+        let args = Args::new(&mem, 0);
+        assert_eq!(args.fetchstrlen(0), Ok(150));
+        assert_eq!(args.fetchstrlen(150), Ok(0));
+        assert_eq!(
+            args.fetchstrlen(151),
+            Err(ArgError::BadAddress { addr: 151 })
+        );
+    }
+
+    #[test]
+    fn test_argfd() {
+        let (mem, esp) = write_call();
+        let args = Args::new(&mem, esp);
+        let ofile = [Some('i'), Some('o'), None];
+        assert_eq!(args.argfd(0, &ofile), Ok((1, &'o')));
+        // fd 0x20 is out of range.
+        assert_eq!(args.argfd(1, &ofile), Err(ArgError::BadFd { fd: 0x20 }));
+        assert_eq!(args.argfd(0, &ofile[..1]), Err(ArgError::BadFd { fd: 1 }));
+    }
+}
@@ -1,16 +1,24 @@
-#![cfg_attr(not(test), no_std)]
-
-// syscall crate example
-
-pub fn hello() {
-    // dummy function
-}
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn test_hello() {
-        super::hello();
-        assert_eq!(2 + 2, 4);
-    }
-}
+#![cfg_attr(not(test), no_std)]
+//! System calls (inherited xv6 syscall.h/syscall.c)
+//!
+//! User code calls into the kernel with `int $T_SYSCALL` (0x40), the
+//! [`Syscall`] number in `%eax` and the arguments on its stack, as for a C
+//! function call. The result comes back in `%eax`; -1 means failure.
+//!
+//! The kernel side reads the arguments with [`Args`], which checks every
+//! user address against the size of the calling process, and runs the call
+//! through a [`Table`] of handlers.
+
+mod args;
+mod number;
+mod table;
+
+pub use self::args::{ArgError, Args, UserMemory};
+pub use self::number::Syscall;
+pub use self::table::{Handler, Table};
+
+/// System call vector. The only one user code may `int` to.
+pub const T_SYSCALL: u32 = 64;
+
+/// Maximum number of `exec` arguments.
+pub const MAXARG: usize = 32;
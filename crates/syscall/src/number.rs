@@ -0,0 +1,112 @@
+use core::fmt;
+
+/// Invokes macro `$m` with the system call table, one
+/// `Variant name = number,` line per call, in number order.
+///
+/// [`Syscall`] is defined from it, and so are the user-space stubs (the
+/// `usys.S` equivalent), so that the two cannot disagree.
+#[macro_export]
+macro_rules! syscall_table {
+    ($m:path) => {
+        $m! {
+            Fork fork = 1,
+            Exit exit = 2,
+            Wait wait = 3,
+            Pipe pipe = 4,
+            Read read = 5,
+            Kill kill = 6,
+            Exec exec = 7,
+            Fstat fstat = 8,
+            Chdir chdir = 9,
+            Dup dup = 10,
+            Getpid getpid = 11,
+            Sbrk sbrk = 12,
+            Sleep sleep = 13,
+            Uptime uptime = 14,
+            Open open = 15,
+            Write write = 16,
+            Mknod mknod = 17,
+            Unlink unlink = 18,
+            Link link = 19,
+            Mkdir mkdir = 20,
+            Close close = 21,
+        }
+    };
+}
+
+macro_rules! define_syscall {
+    ($($variant:ident $name:ident = $num:literal,)*) => {
+        /// System call numbers, shared by the kernel and user space.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[repr(u32)]
+        pub enum Syscall {
+            $($variant = $num,)*
+        }
+
+        impl Syscall {
+            /// Every system call, in number order.
+            pub const ALL: [Self; [$($num),*].len()] = [$(Self::$variant),*];
+
+            /// Name of the user-space function (xv6 user.h).
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(Self::$variant => stringify!($name),)*
+                }
+            }
+        }
+    };
+}
+
+syscall_table!(define_syscall);
+
+impl Syscall {
+    /// Number of system calls. They are numbered from 1 to `COUNT`.
+    pub const COUNT: usize = Self::ALL.len();
+
+    /// The system call numbered `num`, if any.
+    pub const fn from_number(num: u32) -> Option<Self> {
+        match (num as usize).checked_sub(1) {
+            Some(i) if i < Self::COUNT => Some(Self::ALL[i]),
+            _ => None,
+        }
+    }
+
+    pub const fn number(self) -> u32 {
+        self as u32
+    }
+}
+
+impl fmt::Display for Syscall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbers_match_xv6() {
+        assert_eq!(Syscall::Fork.number(), 1);
+        assert_eq!(Syscall::Exec.number(), 7);
+        assert_eq!(Syscall::Write.number(), 16);
+        assert_eq!(Syscall::Close.number(), 21);
+    }
+
+    #[test]
+    fn test_from_number() {
+        for (i, sys) in Syscall::ALL.into_iter().enumerate() {
+            assert_eq!(sys.number() as usize, i + 1);
+            assert_eq!(Syscall::from_number(sys.number()), Some(sys));
+        }
+        assert_eq!(Syscall::from_number(0), None);
+        assert_eq!(Syscall::from_number(22), None);
+        assert_eq!(Syscall::from_number(u32::MAX), None);
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(Syscall::Getpid.to_string(), "getpid");
+    }
+}
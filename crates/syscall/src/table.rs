@@ -0,0 +1,66 @@
+use crate::Syscall;
+
+/// Kernel implementation of a system call. `C` is the calling process;
+/// the result goes back to user space in `%eax`.
+pub type Handler<C> = fn(&mut C) -> i32;
+
+/// Handler of each system call, like xv6's `syscalls[]` array.
+pub struct Table<C: ?Sized>([Option<Handler<C>>; Syscall::COUNT]);
+
+impl<C: ?Sized> Table<C> {
+    /// A table without handlers.
+    pub const fn new() -> Self {
+        Self([None; Syscall::COUNT])
+    }
+
+    /// Makes `handler` handle `sys`.
+    #[must_use]
+    pub const fn with(mut self, sys: Syscall, handler: Handler<C>) -> Self {
+        self.0[sys as usize - 1] = Some(handler);
+        self
+    }
+
+    /// The handler of system call number `num`, if there is one.
+    pub fn get(&self, num: u32) -> Option<Handler<C>> {
+        let sys = Syscall::from_number(num)?;
+        self.0[sys as usize - 1]
+    }
+
+    /// Runs system call number `num` (from `%eax`) for `caller`.
+    ///
+    /// Returns `None` if `num` is not a system call this table handles.
+    pub fn dispatch(&self, num: u32, caller: &mut C) -> Option<i32> {
+        self.get(num).map(|handler| handler(caller))
+    }
+}
+
+impl<C: ?Sized> Default for Table<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dispatch() {
+        static TABLE: Table<i32> =
+            Table::new()
+                .with(Syscall::Getpid, |pid| *pid)
+                .with(Syscall::Kill, |pid| {
+                    *pid = 0;
+                    0
+                });
+
+        let mut pid = 7;
+        assert_eq!(TABLE.dispatch(Syscall::Getpid.number(), &mut pid), Some(7));
+        assert_eq!(TABLE.dispatch(Syscall::Kill.number(), &mut pid), Some(0));
+        assert_eq!(pid, 0);
+        // Known to user space, but not handled here.
+        assert_eq!(TABLE.dispatch(Syscall::Fork.number(), &mut pid), None);
+        assert_eq!(TABLE.dispatch(0, &mut pid), None);
+        assert_eq!(TABLE.dispatch(99, &mut pid), None);
+    }
+}
@@ -0,0 +1,159 @@
+//! Entry stubs, `alltraps`/`trapret` and the IDT of the running kernel.
+
+use core::arch::{asm, global_asm};
+use core::cell::UnsafeCell;
+
+use segment::SegmentSelector;
+
+use crate::{Idt, N_VECTORS, T_PGFLT, TrapFrame, has_error_code};
+
+/// Bit `n` is set if the CPU pushes an error code for vector `n`. Only
+/// exceptions (vectors 0..32) have one.
+const ERROR_CODES: u32 = {
+    let mut mask = 0;
+    let mut n = 0;
+    while n < 32 {
+        if has_error_code(n) {
+            mask |= 1 << n;
+        }
+        n += 1;
+    }
+    mask
+};
+
+// One stub per vector (replaces vectors.pl). Vectors for which the CPU
+// pushes no error code (see `has_error_code`) push a 0 instead, so every
+// trap frame looks the same.
+global_asm!(
+    r#"
+    .altmacro
+    .macro trap_vector n
+    vector\n:
+    .if !((\n < 32) && (({error_codes} >> (\n & 31)) & 1))
+        pushl $0
+    .endif
+        pushl $\n
+        jmp alltraps
+    .endm
+
+    .macro trap_vector_addr n
+        .long vector\n
+    .endm
+
+    .section .text.vectors, "ax"
+    .set .Lvector, 0
+    .rept {n_vectors}
+        trap_vector %.Lvector
+        .set .Lvector, .Lvector + 1
+    .endr
+
+    # vector table
+    .section .rodata.vectors, "a"
+    .p2align 2
+    .globl vectors
+vectors:
+    .set .Lvector, 0
+    .rept {n_vectors}
+        trap_vector_addr %.Lvector
+        .set .Lvector, .Lvector + 1
+    .endr
+    .noaltmacro
+
+    .text
+    # vectors.S sends all traps here.
+    .globl alltraps
+    .type alltraps, @function
+alltraps:
+    # Build trap frame.
+    pushl %ds
+    pushl %es
+    pushl %fs
+    pushl %gs
+    pushal
+
+    # Set up data segments.
+    movw ${kdata}, %ax
+    movw %ax, %ds
+    movw %ax, %es
+
+    # Call trap(tf), where tf=%esp
+    pushl %esp
+    call {trap}
+    addl $4, %esp
+
+    # Return falls through to trapret...
+    .globl trapret
+trapret:
+    popal
+    popl %gs
+    popl %fs
+    popl %es
+    popl %ds
+    addl $0x8, %esp  # trapno and errcode
+    iret
+    .size alltraps, . - alltraps
+    "#,
+    n_vectors = const N_VECTORS,
+    error_codes = const ERROR_CODES,
+    kdata = const SegmentSelector::KERNEL_DATA.bits(),
+    trap = sym trap,
+    options(att_syntax)
+);
+
+unsafe extern "C" {
+    /// Entry stub address of each vector.
+    static vectors: [u32; N_VECTORS];
+
+    /// Second half of `alltraps`: pops the [`TrapFrame`] at the top of the
+    /// stack and `iret`s to it. A new process's kernel stack is set up to
+    /// return here, so that its first run leaves the kernel like a trap.
+    pub fn trapret();
+}
+
+extern "C" fn trap(tf: *mut TrapFrame) {
+    // SAFETY: `alltraps` passes the frame it just built on this stack.
+    crate::dispatch(unsafe { &mut *tf });
+}
+
+/// Shared by all CPUs; only written by [`tvinit`].
+struct IdtCell(UnsafeCell<Idt>);
+
+unsafe impl Sync for IdtCell {}
+
+static IDT: IdtCell = IdtCell(UnsafeCell::new(Idt::new()));
+
+/// Fills the IDT with the entry stubs and installs the page fault decoder.
+///
+/// # Safety
+/// Must run once, before any CPU calls [`idtinit`].
+pub unsafe fn tvinit() {
+    unsafe { (*IDT.0.get()).set_vectors(&vectors) };
+    crate::register(T_PGFLT as u8, page_fault);
+}
+
+fn page_fault(tf: &mut TrapFrame) {
+    crate::handle_page_fault(rcr2(), tf);
+}
+
+/// Loads the IDT on this CPU.
+///
+/// # Safety
+/// [`tvinit`] must have run.
+pub unsafe fn idtinit() {
+    let idt = unsafe { &*IDT.0.get() }.as_slice();
+    let base = idt.as_ptr().addr();
+    let pd: [u16; 3] = [
+        (size_of_val(idt) - 1) as u16,
+        base as u16,
+        (base >> 16) as u16,
+    ];
+    unsafe { asm!("lidt [{}]", in(reg) &pd, options(readonly, nostack, preserves_flags)) };
+}
+
+/// Linear address of the last page fault.
+#[inline]
+pub fn rcr2() -> usize {
+    let val: usize;
+    unsafe { asm!("mov {}, cr2", out(reg) val, options(nomem, nostack, preserves_flags)) };
+    val
+}
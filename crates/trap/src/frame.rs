@@ -0,0 +1,136 @@
+use core::fmt;
+
+use segment::Ring;
+
+use crate::exception_name;
+
+/// Layout of the trap frame built on the stack by the hardware and by
+/// `alltraps`, and passed to [`dispatch`](crate::dispatch).
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct TrapFrame {
+    // registers as pushed by pusha
+    pub edi: u32,
+    pub esi: u32,
+    pub ebp: u32,
+    /// useless & ignored
+    pub oesp: u32,
+    pub ebx: u32,
+    pub edx: u32,
+    pub ecx: u32,
+    pub eax: u32,
+
+    // rest of trap frame
+    pub gs: u16,
+    _padding1: u16,
+    pub fs: u16,
+    _padding2: u16,
+    pub es: u16,
+    _padding3: u16,
+    pub ds: u16,
+    _padding4: u16,
+    pub trapno: u32,
+
+    // below here defined by x86 hardware
+    pub err: u32,
+    pub eip: u32,
+    pub cs: u16,
+    _padding5: u16,
+    pub eflags: u32,
+
+    // below here only when crossing rings, such as from user to kernel
+    pub esp: u32,
+    pub ss: u16,
+    _padding6: u16,
+}
+
+const _: () = assert!(size_of::<TrapFrame>() == 76);
+
+impl TrapFrame {
+    /// Whether the trap came from user mode. Only then are `esp` and `ss`
+    /// part of the frame.
+    pub const fn from_user(&self) -> bool {
+        self.cs & 3 == Ring::User as u16
+    }
+}
+
+/// Decoded dump of the frame, for unexpected traps.
+///
+/// ```txt
+/// trap 13 (general protection fault) err 0x0 eip 0x80101234 cs 0x8 eflags 0x10002
+///   eax 0x00000000 ebx 0x00000000 ecx 0x00000000 edx 0x00000000
+///   esi 0x00000000 edi 0x00000000 ebp 0x80108f8c
+///   ds 0x10 es 0x10 fs 0x0 gs 0x0
+/// ```
+impl fmt::Display for TrapFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "trap {}", self.trapno)?;
+        if let Some(name) = exception_name(self.trapno) {
+            write!(f, " ({name})")?;
+        }
+        writeln!(
+            f,
+            " err {:#x} eip {:#010x} cs {:#x} eflags {:#x}",
+            self.err, self.eip, self.cs, self.eflags
+        )?;
+        writeln!(
+            f,
+            "  eax {:#010x} ebx {:#010x} ecx {:#010x} edx {:#010x}",
+            self.eax, self.ebx, self.ecx, self.edx
+        )?;
+        writeln!(
+            f,
+            "  esi {:#010x} edi {:#010x} ebp {:#010x}",
+            self.esi, self.edi, self.ebp
+        )?;
+        write!(
+            f,
+            "  ds {:#x} es {:#x} fs {:#x} gs {:#x}",
+            self.ds, self.es, self.fs, self.gs
+        )?;
+        if self.from_user() {
+            write!(f, " ss {:#x} esp {:#010x}", self.ss, self.esp)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem::offset_of;
+
+    #[test]
+    fn test_layout() {
+        // Offsets used by xv6 (and `alltraps`).
+        assert_eq!(offset_of!(TrapFrame, eax), 28);
+        assert_eq!(offset_of!(TrapFrame, gs), 32);
+        assert_eq!(offset_of!(TrapFrame, trapno), 48);
+        assert_eq!(offset_of!(TrapFrame, err), 52);
+        assert_eq!(offset_of!(TrapFrame, eip), 56);
+        assert_eq!(offset_of!(TrapFrame, esp), 68);
+    }
+
+    #[test]
+    fn test_display() {
+        let tf = TrapFrame {
+            trapno: 13,
+            eip: 0x8010_1234,
+            cs: 0x08,
+            ..TrapFrame::default()
+        };
+        let dump = tf.to_string();
+        assert!(dump.starts_with("trap 13 (general protection fault) err 0x0 eip 0x80101234"));
+        assert!(!dump.contains("esp"), "kernel frames have no esp");
+
+        let tf = TrapFrame {
+            trapno: 64,
+            cs: 0x1B,
+            ss: 0x23,
+            esp: 0x2FF0,
+            ..TrapFrame::default()
+        };
+        assert!(!tf.to_string().contains('('));
+        assert!(tf.to_string().ends_with("ss 0x23 esp 0x00002ff0"));
+    }
+}
@@ -0,0 +1,133 @@
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::{N_VECTORS, TrapFrame};
+
+/// Handles one vector. Runs on the kernel stack with interrupts disabled,
+/// except for trap gates (system calls).
+pub type Handler = fn(&mut TrapFrame);
+
+/// Handler of each vector, or null.
+static HANDLERS: [AtomicPtr<()>; N_VECTORS] =
+    [const { AtomicPtr::new(core::ptr::null_mut()) }; N_VECTORS];
+
+/// Makes `handler` handle `vector`, replacing any previous handler.
+pub fn register(vector: u8, handler: Handler) {
+    HANDLERS[vector as usize].store(handler as *mut (), Ordering::Release);
+}
+
+/// Runs after the handler of every trap, on the way back to the
+/// interrupted code: where the kernel reschedules or kills processes.
+pub type ReturnHook = fn(&mut TrapFrame);
+
+static RETURN_HOOK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Makes `hook` run after every handler, replacing any previous hook.
+pub fn set_return_hook(hook: ReturnHook) {
+    RETURN_HOOK.store(hook as *mut (), Ordering::Release);
+}
+
+fn return_hook() -> Option<ReturnHook> {
+    let hook = RETURN_HOOK.load(Ordering::Acquire);
+    // SAFETY: only `set_return_hook` stores non-null values.
+    (!hook.is_null()).then(|| unsafe { core::mem::transmute::<*mut (), ReturnHook>(hook) })
+}
+
+static USER_HANDLER: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Makes `handler` handle every trap from user mode that has no handler of
+/// its own (xv6 kills the process), replacing any previous one.
+pub fn set_user_handler(handler: Handler) {
+    USER_HANDLER.store(handler as *mut (), Ordering::Release);
+}
+
+fn user_handler() -> Option<Handler> {
+    let handler = USER_HANDLER.load(Ordering::Acquire);
+    // SAFETY: only `set_user_handler` stores non-null values.
+    (!handler.is_null()).then(|| unsafe { core::mem::transmute::<*mut (), Handler>(handler) })
+}
+
+fn handler(vector: u32) -> Option<Handler> {
+    let handler = HANDLERS.get(vector as usize)?.load(Ordering::Acquire);
+    if handler.is_null() {
+        return None;
+    }
+    // SAFETY: only `register` stores non-null values, and they are `Handler`s.
+    Some(unsafe { core::mem::transmute::<*mut (), Handler>(handler) })
+}
+
+/// Runs the handler registered for `tf.trapno`, or the user handler for a
+/// trap from user mode without one, then the return hook.
+///
+/// # Panics
+/// Panics with a dump of `tf` if no handler is registered and the trap came
+/// from kernel mode (or no user handler is set).
+pub fn dispatch(tf: &mut TrapFrame) {
+    dispatch_with(tf, user_handler(), return_hook());
+}
+
+/// [`dispatch`] with the given user handler and return hook instead of the
+/// kernel's.
+fn dispatch_with(tf: &mut TrapFrame, user_handler: Option<Handler>, hook: Option<ReturnHook>) {
+    let user_handler = || user_handler.filter(|_| tf.from_user());
+    match handler(tf.trapno).or_else(user_handler) {
+        Some(handler) => handler(tf),
+        None => panic!("unexpected {tf}"),
+    }
+    if let Some(hook) = hook {
+        hook(tf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dispatch() {
+        fn bump_eax(tf: &mut TrapFrame) {
+            tf.eax += 1;
+        }
+
+        register(200, bump_eax);
+        let mut tf = TrapFrame::default();
+        tf.trapno = 200;
+        dispatch(&mut tf);
+        dispatch(&mut tf);
+        assert_eq!(tf.eax, 2);
+    }
+
+    #[test]
+    fn test_return_hook() {
+        fn mark_ebx(tf: &mut TrapFrame) {
+            tf.ebx = tf.trapno;
+        }
+
+        register(201, |_| {});
+        let mut tf = TrapFrame::default();
+        tf.trapno = 201;
+        dispatch_with(&mut tf, None, Some(mark_ebx));
+        assert_eq!(tf.ebx, 201);
+    }
+
+    #[test]
+    fn test_user_handler() {
+        fn mark_ecx(tf: &mut TrapFrame) {
+            tf.ecx = tf.trapno;
+        }
+
+        let mut tf = TrapFrame::default();
+        tf.trapno = 13;
+        tf.cs = 0x1B;
+        dispatch_with(&mut tf, Some(mark_ecx), None);
+        assert_eq!(tf.ecx, 13);
+    }
+
+    #[test]
+    #[should_panic = "unexpected trap 6 (illegal opcode)"]
+    fn test_unexpected() {
+        // From kernel mode, even with a user handler.
+        let mut tf = TrapFrame::default();
+        tf.trapno = 6;
+        dispatch_with(&mut tf, Some(|_| {}), None);
+    }
+}
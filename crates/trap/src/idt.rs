@@ -0,0 +1,181 @@
+use core::fmt;
+
+use segment::{Ring, SegmentSelector};
+
+use crate::N_VECTORS;
+
+/// Gate descriptor for interrupts and traps: one entry of the IDT.
+///
+/// ```txt
+///  63          48 47 46 45 44 43   40 39   32 31          16 15          0
+/// +--------------+--+-----+--+-------+-------+--------------+-------------+
+/// | offset 31:16 | P| DPL | 0|  type |   0   |   selector   | offset 15:0 |
+/// +--------------+--+-----+--+-------+-------+--------------+-------------+
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct GateDescriptor(u64);
+
+/// 32-bit Interrupt Gate (`STS_IG32`)
+const STS_IG32: u64 = 0xE;
+/// 32-bit Trap Gate (`STS_TG32`)
+const STS_TG32: u64 = 0xF;
+
+/// Present
+const P: u64 = 1 << 47;
+
+impl GateDescriptor {
+    /// Not present: using the vector raises #GP.
+    pub const MISSING: Self = Self(0);
+
+    /// Interrupt gate: the CPU clears IF on entry, so the handler runs with
+    /// interrupts disabled.
+    ///
+    /// `dpl` is the privilege level required to use `int` on this vector.
+    pub const fn interrupt(offset: u32, selector: SegmentSelector, dpl: Ring) -> Self {
+        Self::new(STS_IG32, offset, selector, dpl)
+    }
+
+    /// Trap gate: IF is left unchanged.
+    pub const fn trap(offset: u32, selector: SegmentSelector, dpl: Ring) -> Self {
+        Self::new(STS_TG32, offset, selector, dpl)
+    }
+
+    const fn new(ty: u64, offset: u32, selector: SegmentSelector, dpl: Ring) -> Self {
+        let offset = offset as u64;
+        Self(
+            (offset & 0xFFFF)
+                | ((selector.bits() as u64) << 16)
+                | (ty << 40)
+                | ((dpl as u64) << 45)
+                | P
+                | ((offset >> 16) << 48),
+        )
+    }
+
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Handler address.
+    pub const fn offset(self) -> u32 {
+        ((self.0 & 0xFFFF) | ((self.0 >> 32) & 0xFFFF_0000)) as u32
+    }
+
+    pub const fn selector(self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    pub const fn is_trap(self) -> bool {
+        (self.0 >> 40) & 0xF == STS_TG32
+    }
+
+    pub const fn dpl(self) -> Option<Ring> {
+        Ring::from_bits((self.0 >> 45) as u8)
+    }
+
+    pub const fn is_present(self) -> bool {
+        self.0 & P != 0
+    }
+}
+
+impl fmt::Debug for GateDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_present() {
+            return write!(f, "GateDescriptor(missing)");
+        }
+        write!(
+            f,
+            "GateDescriptor({} {:#06x}:{:#010x}, {:?})",
+            if self.is_trap() { "trap" } else { "interrupt" },
+            self.selector(),
+            self.offset(),
+            self.dpl()
+        )
+    }
+}
+
+/// Interrupt descriptor table
+#[derive(Debug, Clone)]
+#[repr(C, align(8))]
+pub struct Idt([GateDescriptor; N_VECTORS]);
+
+impl Idt {
+    /// All gates missing.
+    pub const fn new() -> Self {
+        Self([GateDescriptor::MISSING; N_VECTORS])
+    }
+
+    /// Sets up the xv6 table: an interrupt gate for every vector, except a
+    /// trap gate usable from user mode for [`T_SYSCALL`](crate::T_SYSCALL).
+    /// `vectors[i]` is the address of the entry stub of vector `i`.
+    ///
+    /// Works in place: the table is too big for a temporary on a kernel stack.
+    pub fn set_vectors(&mut self, vectors: &[u32; N_VECTORS]) {
+        for (gate, &vector) in self.0.iter_mut().zip(vectors) {
+            *gate = GateDescriptor::interrupt(vector, SegmentSelector::KERNEL_CODE, Ring::Kernel);
+        }
+        self.0[crate::T_SYSCALL as usize] = GateDescriptor::trap(
+            vectors[crate::T_SYSCALL as usize],
+            SegmentSelector::KERNEL_CODE,
+            Ring::User,
+        );
+    }
+
+    pub const fn get(&self, vector: u8) -> GateDescriptor {
+        self.0[vector as usize]
+    }
+
+    pub const fn set(&mut self, vector: u8, gate: GateDescriptor) {
+        self.0[vector as usize] = gate;
+    }
+
+    pub const fn as_slice(&self) -> &[GateDescriptor] {
+        &self.0
+    }
+}
+
+impl Default for Idt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::T_SYSCALL;
+
+    #[test]
+    fn test_gate_encoding() {
+        // SETGATE(idt[i], 0, SEG_KCODE<<3, 0x80105a3c, 0)
+        let gate =
+            GateDescriptor::interrupt(0x8010_5A3C, SegmentSelector::KERNEL_CODE, Ring::Kernel);
+        assert_eq!(gate.bits(), 0x8010_8E00_0008_5A3C);
+        assert_eq!(gate.offset(), 0x8010_5A3C);
+        assert_eq!(gate.selector(), 0x08);
+        assert!(!gate.is_trap());
+
+        // SETGATE(idt[T_SYSCALL], 1, SEG_KCODE<<3, 0x80105a3c, DPL_USER)
+        let gate = GateDescriptor::trap(0x8010_5A3C, SegmentSelector::KERNEL_CODE, Ring::User);
+        assert_eq!(gate.bits(), 0x8010_EF00_0008_5A3C);
+        assert!(gate.is_trap());
+        assert_eq!(gate.dpl(), Some(Ring::User));
+    }
+
+    #[test]
+    fn test_set_vectors() {
+        let vectors: [u32; N_VECTORS] = core::array::from_fn(|i| 0x8010_0000 + 8 * i as u32);
+        let mut idt = Idt::new();
+        idt.set_vectors(&vectors);
+
+        assert_eq!(idt.as_slice().len(), N_VECTORS);
+        assert!(idt.as_slice().iter().all(|gate| gate.is_present()));
+        assert_eq!(idt.get(14).offset(), 0x8010_0070);
+        assert_eq!(idt.get(14).dpl(), Some(Ring::Kernel));
+
+        let syscall = idt.get(T_SYSCALL as u8);
+        assert!(syscall.is_trap());
+        assert_eq!(syscall.dpl(), Some(Ring::User));
+    }
+}
@@ -1,16 +1,149 @@
-#![cfg_attr(not(test), no_std)]
-
-// trap crate example
-
-pub fn hello() {
-    // dummy function
-}
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn test_hello() {
-        super::hello();
-        assert_eq!(2 + 2, 4);
-    }
-}
+#![cfg_attr(not(test), no_std)]
+//! Traps, interrupts and system calls (inherited xv6 traps.h/trap.c/trapasm.S/vectors.pl)
+//!
+//! Every vector enters through a generated stub that pushes the vector
+//! number (and a dummy error code where the CPU does not push one) and jumps
+//! to the common `alltraps` path. That builds a [`TrapFrame`] on the kernel
+//! stack and calls [`dispatch`], which runs the handler [`register`]ed for the
+//! vector, then the kernel's [`ReturnHook`]. `trapret` then restores the
+//! frame and `iret`s.
+//!
+//! Page faults are decoded here (error code and `%cr2`) and handed to the
+//! kernel's [`PageFaultHandler`]. Any other trap from user mode without a
+//! handler goes to the one set with [`set_user_handler`].
+//!
+//! ```txt
+//!  CPU pushes        stub pushes       alltraps pushes
+//! +--------------+ +----------------+ +-------------------+
+//! | ss, esp      | | err (dummy 0)  | | ds, es, fs, gs    |
+//! | (user only)  | | trapno         | | pushal            |  -> &mut TrapFrame
+//! | eflags,cs,eip| |                | |                   |
+//! | err (some)   | |                | |                   |
+//! +--------------+ +----------------+ +-------------------+
+//! ```
+
+#[cfg(target_arch = "x86")]
+mod arch;
+mod frame;
+mod handler;
+mod idt;
+mod page_fault;
+
+#[cfg(target_arch = "x86")]
+pub use self::arch::{idtinit, rcr2, trapret, tvinit};
+pub use self::frame::TrapFrame;
+pub use self::handler::{
+    Handler, ReturnHook, dispatch, register, set_return_hook, set_user_handler,
+};
+pub use self::idt::{GateDescriptor, Idt};
+pub use self::page_fault::{
+    PageFault, PageFaultError, PageFaultHandler, handle_page_fault, set_page_fault_handler,
+};
+
+// Processor-defined:
+/// divide error
+pub const T_DIVIDE: u32 = 0;
+/// debug exception
+pub const T_DEBUG: u32 = 1;
+/// non-maskable interrupt
+pub const T_NMI: u32 = 2;
+/// breakpoint
+pub const T_BRKPT: u32 = 3;
+/// overflow
+pub const T_OFLOW: u32 = 4;
+/// bounds check
+pub const T_BOUND: u32 = 5;
+/// illegal opcode
+pub const T_ILLOP: u32 = 6;
+/// device not available
+pub const T_DEVICE: u32 = 7;
+/// double fault
+pub const T_DBLFLT: u32 = 8;
+/// invalid task switch segment
+pub const T_TSS: u32 = 10;
+/// segment not present
+pub const T_SEGNP: u32 = 11;
+/// stack exception
+pub const T_STACK: u32 = 12;
+/// general protection fault
+pub const T_GPFLT: u32 = 13;
+/// page fault
+pub const T_PGFLT: u32 = 14;
+/// floating point error
+pub const T_FPERR: u32 = 16;
+/// alignment check
+pub const T_ALIGN: u32 = 17;
+/// machine check
+pub const T_MCHK: u32 = 18;
+/// SIMD floating point error
+pub const T_SIMDERR: u32 = 19;
+
+/// Vectors below this one are reserved for processor exceptions.
+pub const T_EXCEPTIONS: u32 = 32;
+
+pub use syscall::T_SYSCALL;
+
+/// IRQ 0 corresponds to int T_IRQ0
+pub const T_IRQ0: u32 = 32;
+
+pub const IRQ_TIMER: u32 = 0;
+pub const IRQ_KBD: u32 = 1;
+pub const IRQ_COM1: u32 = 4;
+pub const IRQ_IDE: u32 = 14;
+pub const IRQ_ERROR: u32 = 19;
+pub const IRQ_SPURIOUS: u32 = 31;
+
+/// Number of interrupt vectors
+pub const N_VECTORS: usize = 256;
+
+/// Whether the CPU pushes an error code for `vector`.
+pub const fn has_error_code(vector: u32) -> bool {
+    matches!(vector, T_DBLFLT | T_TSS..=T_PGFLT | T_ALIGN)
+}
+
+/// Human readable name of a processor exception.
+pub const fn exception_name(vector: u32) -> Option<&'static str> {
+    Some(match vector {
+        T_DIVIDE => "divide error",
+        T_DEBUG => "debug exception",
+        T_NMI => "non-maskable interrupt",
+        T_BRKPT => "breakpoint",
+        T_OFLOW => "overflow",
+        T_BOUND => "bounds check",
+        T_ILLOP => "illegal opcode",
+        T_DEVICE => "device not available",
+        T_DBLFLT => "double fault",
+        9 => "coprocessor segment overrun",
+        T_TSS => "invalid task switch segment",
+        T_SEGNP => "segment not present",
+        T_STACK => "stack exception",
+        T_GPFLT => "general protection fault",
+        T_PGFLT => "page fault",
+        T_FPERR => "floating point error",
+        T_ALIGN => "alignment check",
+        T_MCHK => "machine check",
+        T_SIMDERR => "SIMD floating point error",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_code_vectors() {
+        let with_code: Vec<u32> = (0..N_VECTORS as u32)
+            .filter(|&v| has_error_code(v))
+            .collect();
+        // Same as vectors.pl.
+        assert_eq!(with_code, [8, 10, 11, 12, 13, 14, 17]);
+    }
+
+    #[test]
+    fn test_exception_names() {
+        assert_eq!(exception_name(T_PGFLT), Some("page fault"));
+        assert_eq!(exception_name(15), None, "reserved");
+        assert_eq!(exception_name(T_SYSCALL), None);
+    }
+}
@@ -0,0 +1,169 @@
+use core::fmt;
+use core::ops::BitOr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::TrapFrame;
+
+/// Error code pushed by the CPU for a page fault.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct PageFaultError(u32);
+
+impl PageFaultError {
+    /// Protection violation; clear if the page was not present
+    pub const PRESENT: Self = Self(0x01);
+    /// The access was a write
+    pub const WRITE: Self = Self(0x02);
+    /// The access came from user mode
+    pub const USER: Self = Self(0x04);
+    /// A reserved bit was set in a paging structure
+    pub const RESERVED: Self = Self(0x08);
+    /// The access was an instruction fetch
+    pub const INSTRUCTION_FETCH: Self = Self(0x10);
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Whether all flags in `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for PageFaultError {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// `protection violation, write, user mode`
+impl fmt::Display for PageFaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.contains(Self::PRESENT) {
+            "protection violation"
+        } else {
+            "page not present"
+        })?;
+        f.write_str(if self.contains(Self::INSTRUCTION_FETCH) {
+            ", instruction fetch"
+        } else if self.contains(Self::WRITE) {
+            ", write"
+        } else {
+            ", read"
+        })?;
+        f.write_str(if self.contains(Self::USER) {
+            ", user mode"
+        } else {
+            ", kernel mode"
+        })?;
+        if self.contains(Self::RESERVED) {
+            f.write_str(", reserved bit set")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PageFaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PageFaultError({:#x}: {self})", self.0)
+    }
+}
+
+/// A decoded page fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageFault {
+    /// Faulting linear address, from `%cr2`
+    pub addr: usize,
+    pub error: PageFaultError,
+}
+
+impl PageFault {
+    pub const fn new(addr: usize, err: u32) -> Self {
+        Self {
+            addr,
+            error: PageFaultError::from_bits(err),
+        }
+    }
+}
+
+/// `page not present, write, kernel mode at 0x00000000`
+impl fmt::Display for PageFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {:#010x}", self.error, self.addr)
+    }
+}
+
+/// The kernel's page fault policy: resolves the fault (e.g. by mapping the
+/// page) and returns, or deals with the faulting code.
+pub type PageFaultHandler = fn(&PageFault, &mut TrapFrame);
+
+static HANDLER: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Routes every page fault to `handler`, replacing any previous one.
+pub fn set_page_fault_handler(handler: PageFaultHandler) {
+    HANDLER.store(handler as *mut (), Ordering::Release);
+}
+
+/// Decodes the fault at `addr` (the value of `%cr2`) described by `tf` and
+/// passes it on. This is the [`T_PGFLT`](crate::T_PGFLT) handler.
+///
+/// # Panics
+/// Panics with the decoded fault if the kernel set no handler.
+pub fn handle_page_fault(addr: usize, tf: &mut TrapFrame) {
+    let fault = PageFault::new(addr, tf.err);
+    let handler = HANDLER.load(Ordering::Acquire);
+    if handler.is_null() {
+        panic!("unexpected page fault: {fault}\n{tf}");
+    }
+    // SAFETY: only `set_page_fault_handler` stores non-null values.
+    let handler = unsafe { core::mem::transmute::<*mut (), PageFaultHandler>(handler) };
+    handler(&fault, tf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        // xv6's "trap 14 err 6": user write to a missing page.
+        let err = PageFaultError::from_bits(6);
+        assert!(err.contains(PageFaultError::WRITE | PageFaultError::USER));
+        assert!(!err.contains(PageFaultError::PRESENT));
+        assert_eq!(err.to_string(), "page not present, write, user mode");
+
+        let err = PageFaultError::from_bits(0x19);
+        assert_eq!(
+            err.to_string(),
+            "protection violation, instruction fetch, kernel mode, reserved bit set"
+        );
+
+        let fault = PageFault::new(0x1000, 0);
+        assert_eq!(
+            fault.to_string(),
+            "page not present, read, kernel mode at 0x00001000"
+        );
+    }
+
+    #[test]
+    fn test_handler() {
+        fn skip_instruction(fault: &PageFault, tf: &mut TrapFrame) {
+            assert_eq!(fault.addr, 0xDEAD_0000);
+            assert!(fault.error.contains(PageFaultError::WRITE));
+            tf.eip += 2;
+        }
+
+        set_page_fault_handler(skip_instruction);
+        let mut tf = TrapFrame::default();
+        tf.err = PageFaultError::WRITE.bits();
+        handle_page_fault(0xDEAD_0000, &mut tf);
+        assert_eq!(tf.eip, 2);
+    }
+}
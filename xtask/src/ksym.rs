@@ -0,0 +1,163 @@
+//! Embeds the kernel's function symbols into its `.ksymtab` section.
+//!
+//! See the kernel's `ksym.rs` for the consumer side.
+
+use std::path::Path;
+
+use elf::arch::x86::STT_FUNC;
+use elf::symbol_map::{self, Entry};
+use elf::symtab::{Sections, SymbolTable};
+
+/// Fills `.ksymtab` of the kernel ELF at `path` in place.
+pub fn embed_symbols(path: &Path) -> Result<(), String> {
+    let mut image = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    let section = Sections::new(&image)
+        .and_then(|sections| sections.by_name(".ksymtab"))
+        .ok_or("kernel has no `.ksymtab` section")?;
+    let symtab = SymbolTable::from_elf(&image).ok_or("kernel has no symbol table (stripped?)")?;
+
+    let mut names = Vec::new();
+    for (symbol, name) in symtab.iter() {
+        if symbol.symbol_type() == STT_FUNC && symbol.value != 0 && !name.is_empty() {
+            names.push((symbol.value, symbol.size, demangle(name)));
+        }
+    }
+    names.sort_by_key(|&(address, _, _)| address);
+    names.dedup_by_key(|(address, _, _)| *address);
+
+    let entries: Vec<Entry<'_>> = names
+        .iter()
+        .map(|(address, size, name)| Entry {
+            address: *address,
+            size: *size,
+            name,
+        })
+        .collect();
+
+    let blob = {
+        let mut blob = vec![0; symbol_map::encoded_len(&entries)];
+        symbol_map::write(&entries, &mut blob).ok_or("failed to encode symbol map")?;
+        blob
+    };
+    if blob.len() > section.size as usize {
+        return Err(format!(
+            "symbol map needs {} bytes but `.ksymtab` has {}; raise `KSYMTAB_SIZE`",
+            blob.len(),
+            section.size
+        ));
+    }
+
+    let start = section.offset as usize;
+    image
+        .get_mut(start..start + blob.len())
+        .ok_or("`.ksymtab` is outside the file")?
+        .copy_from_slice(&blob);
+    std::fs::write(path, image).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    println!(
+        "Embedded {} kernel symbols ({} bytes)",
+        entries.len(),
+        blob.len()
+    );
+    Ok(())
+}
+
+/// Demangles a legacy Rust symbol (`_ZN...E`), dropping the hash.
+///
+/// Anything else is returned as is.
+fn demangle(name: &str) -> String {
+    demangle_legacy(name).unwrap_or_else(|| name.to_string())
+}
+
+fn demangle_legacy(name: &str) -> Option<String> {
+    let mut rest = name.strip_prefix("_ZN")?;
+    let mut segments = Vec::new();
+    while !rest.starts_with('E') {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let len: usize = rest[..digits].parse().ok()?;
+        let segment = rest.get(digits..digits + len)?;
+        segments.push(segment);
+        rest = &rest[digits + len..];
+    }
+
+    if let Some(hash) = segments.last().and_then(|last| last.strip_prefix('h'))
+        && hash.len() == 16
+        && hash.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        segments.pop();
+    }
+
+    let segments: Vec<String> = segments.iter().map(|segment| unescape(segment)).collect();
+    Some(segments.join("::"))
+}
+
+fn unescape(segment: &str) -> String {
+    // A leading `$` is escaped as `_$`.
+    let segment = if segment.starts_with("_$") {
+        &segment[1..]
+    } else {
+        segment
+    };
+    let mut out = String::with_capacity(segment.len());
+    let mut rest = segment;
+    while let Some(c) = rest.chars().next() {
+        if let Some(tail) = rest.strip_prefix("..") {
+            out.push_str("::");
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix('$')
+            && let Some(end) = tail.find('$')
+            && let Some(decoded) = unescape_code(&tail[..end])
+        {
+            out.push(decoded);
+            rest = &tail[end + 1..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+fn unescape_code(code: &str) -> Option<char> {
+    Some(match code {
+        "SP" => '@',
+        "BP" => '*',
+        "RF" => '&',
+        "LT" => '<',
+        "GT" => '>',
+        "LP" => '(',
+        "RP" => ')',
+        "C" => ',',
+        _ => char::from_u32(u32::from_str_radix(code.strip_prefix('u')?, 16).ok()?)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demangle() {
+        assert_eq!(
+            demangle("_ZN6kernel2vm8setupkvm17h0123456789abcdefE"),
+            "kernel::vm::setupkvm"
+        );
+        assert_eq!(
+            demangle("_ZN4core3fmt5write17h3a6c0bca1e2c5bf4E"),
+            "core::fmt::write"
+        );
+        assert_eq!(
+            demangle(
+                "_ZN61_$LT$kernel..console..Console$u20$as$u20$core..fmt..Write$GT$9write_str17h0123456789abcdefE"
+            ),
+            "<kernel::console::Console as core::fmt::Write>::write_str"
+        );
+        assert_eq!(
+            demangle("_ZN44_$LT$$RF$T$u20$as$u20$core..fmt..Display$GT$3fmt17h2c7c5b3fa25472ebE"),
+            "<&T as core::fmt::Display>::fmt"
+        );
+        assert_eq!(demangle("main"), "main");
+        assert_eq!(demangle("_ZN3bad"), "_ZN3bad", "unterminated");
+    }
+}
@@ -1,7 +1,10 @@
-use std::fs::{File, read};
-use std::io::Write as _;
+use std::ffi::OsStr;
+use std::fs::{self, File, read};
+use std::io::{BufRead as _, BufReader, Write as _};
 use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus};
+use std::process::{Command, ExitStatus, Stdio};
+
+mod ksym;
 
 fn main() {
     let mut args = std::env::args().skip(1);
@@ -10,6 +13,7 @@ fn main() {
     match subcommand.as_deref() {
         Some("image") => {
             let mut profile = "debug".to_string(); // default
+            let mut features = None;
 
             while let Some(arg) = args.next() {
                 match arg.as_str() {
@@ -21,27 +25,55 @@ fn main() {
                             std::process::exit(1);
                         }
                     }
+                    "--features" => {
+                        if let Some(f) = args.next() {
+                            features = Some(f);
+                        } else {
+                            eprintln!("--features requires a value (e.g. panic-exit-qemu)");
+                            std::process::exit(1);
+                        }
+                    }
                     unknown => {
-                        eprintln!("Unknown argument: {}", unknown);
+                        eprintln!("Unknown argument: {unknown}");
                         std::process::exit(1);
                     }
                 }
             }
 
+            build_image(&profile, features.as_deref());
+        }
+        Some("acpi-tables") => {
+            let profile = "debug";
+            let img_path = build_image(profile, Some("acpi-dump"));
+            for machine in ACPI_MACHINES {
+                if let Err(e) = capture_acpi_tables(&img_path, machine) {
+                    eprintln!("Failed to capture the ACPI tables of `{machine}`: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some("fixtures") => {
+            // Release builds are stripped; the tests need the symbol table.
+            let profile = "release";
             let target_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
                 .join("../target/i686-xv6-none")
-                .join(&profile);
-
-            build_crate("boot", &profile);
-            build_crate("kernel", &profile);
+                .join(profile);
+            build_crate(
+                "user",
+                profile,
+                None,
+                &[("CARGO_PROFILE_RELEASE_STRIP", OsStr::new("false"))],
+            );
 
-            if let Err(e) = create_image(&target_dir) {
-                eprintln!("Failed to create image: {}", e);
+            if let Err(e) = install_fixtures(&target_dir) {
+                eprintln!("Failed to install ELF fixtures: {e}");
                 std::process::exit(1);
             }
         }
         Some("qemu") => {
             let mut profile = "debug".to_string(); // default
+            let mut machine = None;
+            let mut cpus = None;
 
             while let Some(arg) = args.next() {
                 match arg.as_str() {
@@ -53,8 +85,24 @@ fn main() {
                             std::process::exit(1);
                         }
                     }
+                    "--machine" => {
+                        if let Some(m) = args.next() {
+                            machine = Some(m);
+                        } else {
+                            eprintln!("--machine requires a value (e.g. isapc)");
+                            std::process::exit(1);
+                        }
+                    }
+                    "--cpus" => {
+                        if let Some(n) = args.next().and_then(|n| n.parse::<u8>().ok()) {
+                            cpus = Some(n);
+                        } else {
+                            eprintln!("--cpus requires a number (e.g. 4)");
+                            std::process::exit(1);
+                        }
+                    }
                     unknown => {
-                        eprintln!("Unknown argument: {}", unknown);
+                        eprintln!("Unknown argument: {unknown}");
                         std::process::exit(1);
                     }
                 }
@@ -74,17 +122,59 @@ fn main() {
                 std::process::exit(1);
             }
 
-            run_qemu(&img_path);
+            run_qemu(&img_path, machine.as_deref(), cpus);
         }
         _ => {
-            eprintln!("Usage: cargo run -p xtask -- image [--profile <debug|release>]");
+            eprintln!(
+                "Usage: cargo run -p xtask -- image [--profile <debug|release>] [--features <kernel features>]"
+            );
+            eprintln!(
+                "       cargo run -p xtask -- qemu [--profile <debug|release>] [--machine <type>] [--cpus <n>]"
+            );
+            eprintln!("       cargo run -p xtask -- fixtures");
+            eprintln!("       cargo run -p xtask -- acpi-tables");
             std::process::exit(1);
         }
     }
 }
 
-fn build_crate(crate_name: &str, profile: &str) {
-    println!("Building `{}` with profile `{}`", crate_name, profile);
+/// Builds `xv6.img` with `profile` and the kernel `features`, and returns its
+/// path.
+fn build_image(profile: &str, features: Option<&str>) -> PathBuf {
+    let target_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../target/i686-xv6-none")
+        .join(profile);
+
+    build_crate("boot", profile, None, &[]);
+    build_crate("user", profile, None, &[]);
+
+    let programs = target_dir.join("programs");
+    if let Err(e) = install_programs(&target_dir, &programs) {
+        eprintln!("Failed to install user programs: {e}");
+        std::process::exit(1);
+    }
+
+    build_crate(
+        "kernel",
+        profile,
+        features,
+        &[("XV6_USER_PROGRAMS", programs.as_os_str())],
+    );
+
+    if let Err(e) = ksym::embed_symbols(&target_dir.join("kernel")) {
+        eprintln!("Failed to embed kernel symbols: {e}");
+        std::process::exit(1);
+    }
+
+    if let Err(e) = create_image(&target_dir) {
+        eprintln!("Failed to create image: {e}");
+        std::process::exit(1);
+    }
+    target_dir.join("xv6.img")
+}
+
+fn build_crate(crate_name: &str, profile: &str, features: Option<&str>, envs: &[(&str, &OsStr)]) {
+    println!("Building `{crate_name}` with profile `{profile}`");
 
     let target_json = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .join("../app/os/kernel/targets/i686-xv6-none.json");
@@ -94,18 +184,118 @@ fn build_crate(crate_name: &str, profile: &str) {
     cmd.arg("build")
         .arg("-p")
         .arg(crate_name)
-        .args(&["--target", &target_json])
+        .args(["--target", &target_json])
         .env("CARGO_UNSTABLE_BUILD_STD", "1")
-        .args(&["-Z", "build-std=core"])
-        .args(&["--profile", profile]);
+        .args(["-Z", "build-std=core"])
+        .args(["--profile", profile]);
+    if let Some(features) = features {
+        cmd.args(["--features", features]);
+    }
+    cmd.envs(envs.iter().copied());
 
     let status = cmd.status().expect("Failed to run cargo build");
     if !status.success() {
-        eprintln!("Failed to build `{}`", crate_name);
+        eprintln!("Failed to build `{crate_name}`");
         std::process::exit(1);
     }
 }
 
+/// User programs, the binaries of the `user` crate. The kernel embeds them
+/// (see its `build.rs`), and `exec` runs them as `/<name>`.
+const USER_PROGRAMS: &[&str] = &["hello", "init"];
+
+/// Copies the user programs from `target_dir` to `programs`, without their
+/// debug info.
+fn install_programs(target_dir: &Path, programs: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(programs)?;
+    for name in USER_PROGRAMS {
+        let status = Command::new("rust-objcopy")
+            .arg("--strip-debug")
+            .arg(target_dir.join(name))
+            .arg(programs.join(name))
+            .status()?;
+        if !status.success() {
+            return Err(std::io::Error::other(format!(
+                "rust-objcopy {name} failed: {status}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// User programs the `elf` crate's tests read, from its `fixtures`
+/// directory.
+const ELF_FIXTURES: &[&str] = &["hello"];
+
+/// Copies the unstripped [`ELF_FIXTURES`] from `target_dir` to the `elf`
+/// crate.
+fn install_fixtures(target_dir: &Path) -> std::io::Result<()> {
+    let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../crates/elf/fixtures");
+    fs::create_dir_all(&fixtures)?;
+    for name in ELF_FIXTURES {
+        fs::copy(target_dir.join(name), fixtures.join(name))?;
+    }
+    println!("ELF fixtures installed in {}", fixtures.display());
+    Ok(())
+}
+
+/// QEMU machines whose ACPI tables the `acpi` crate is tested against.
+const ACPI_MACHINES: &[&str] = &["pc", "q35"];
+
+/// Boots `img_path` (built with the kernel's `acpi-dump` feature) on QEMU
+/// `machine`, and saves the tables the kernel prints to the `acpi` crate's
+/// `testdata/qemu-<machine>` directory, one file per signature.
+fn capture_acpi_tables(img_path: &Path, machine: &str) -> std::io::Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../crates/acpi/testdata")
+        .join(format!("qemu-{machine}"));
+    fs::create_dir_all(&dir)?;
+
+    let mut child = Command::new("qemu-system-i386")
+        .args(["-machine", machine, "-smp", "2", "-m", "128M"])
+        .arg("-drive")
+        .arg(format!("format=raw,file={}", img_path.display()))
+        .args(["-display", "none", "-serial", "stdio", "-no-reboot"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    let mut result = Err(std::io::Error::other("QEMU exited before the tables"));
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        let Some(dump) = line.trim_end().strip_prefix("acpi-dump ") else {
+            continue;
+        };
+        if dump == "end" {
+            result = Ok(());
+            break;
+        }
+        let Some((name, hex)) = dump.split_once(' ') else {
+            continue;
+        };
+        let bytes =
+            decode_hex(hex).ok_or_else(|| std::io::Error::other(format!("bad dump of {name}")))?;
+        fs::write(dir.join(name), bytes)?;
+    }
+    child.kill()?;
+    child.wait()?;
+    if result.is_ok() {
+        println!("ACPI tables of `{machine}` saved in {}", dir.display());
+    }
+    result
+}
+
+/// Decodes a string of hex digit pairs.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
 /// ```txt
 /// [ 0x0000 ---------------------- ]
 /// |                               |
@@ -137,7 +327,7 @@ fn create_image(target_dir: &Path) -> std::io::Result<()> {
     match boot_len {
         510 => boot.extend_from_slice(BOOT_SIGNATURE),
         len if len < 510 => {
-            boot.extend(std::iter::repeat(0).take(510 - len));
+            boot.extend(std::iter::repeat_n(0, 510 - len));
             boot.extend_from_slice(BOOT_SIGNATURE);
         }
         _ => panic!("Bootloader is too large ({boot_len} bytes). need <= 510bytes",),
@@ -164,32 +354,52 @@ fn to_bin(src: &Path, dst: &Path) -> std::io::Result<ExitStatus> {
         // .arg("--binary-architecture=i386")
         // .arg("--strip-all")
         .arg("-S")
-        .args(&["-O", "binary"])
+        .args(["-O", "binary"])
         .arg(src)
         .arg(dst)
         .status()
 }
 
-fn run_qemu(img_path: &Path) {
+/// QEMU exit status for the kernel's `ExitCode::Panic` (`(0x11 << 1) | 1`).
+const QEMU_EXIT_PANIC: i32 = 0x23;
+
+fn run_qemu(img_path: &Path, machine: Option<&str>, cpus: Option<u8>) {
     println!("Running QEMU with image: {}", img_path.display());
 
-    let status = Command::new("qemu-system-i386")
+    let mut cmd = Command::new("qemu-system-i386");
+    // e.g. `isapc`: no APIC, so the kernel falls back to the 8259 PIC.
+    if let Some(machine) = machine {
+        cmd.args(["-machine", machine]);
+    }
+    if let Some(cpus) = cpus {
+        cmd.arg("-smp").arg(cpus.to_string());
+    }
+    let status = cmd
         .arg("-drive")
         .arg(format!("format=raw,file={}", img_path.display()))
         .arg("-m")
-        .arg("64M")
+        .arg("512M")
+        // No `-no-shutdown`: an ACPI power-off (see kernel `acpi.rs`) ends the run.
         .arg("-no-reboot")
         .arg("-serial")
         .arg("mon:stdio")
         // .arg("-display")
         // .arg("none")
-        .args(&["-d", "guest_errors"])
-        .args(&["-no-reboot", "-no-shutdown"])
+        .args(["-d", "guest_errors"])
+        // Lets the kernel terminate QEMU (see kernel `qemu.rs`).
+        .args(["-device", "isa-debug-exit,iobase=0x501,iosize=0x02"])
         .status()
         .expect("Failed to execute qemu-system-i386");
 
-    if !status.success() {
-        eprintln!("QEMU exited with error code: {:?}", status.code());
-        std::process::exit(1);
+    match status.code() {
+        Some(0) => {}
+        Some(QEMU_EXIT_PANIC) => {
+            eprintln!("Kernel panicked");
+            std::process::exit(1);
+        }
+        code => {
+            eprintln!("QEMU exited with error code: {code:?}");
+            std::process::exit(1);
+        }
     }
 }